@@ -0,0 +1,269 @@
+use anyhow::{Context, Result};
+use folio_core::{
+    format_size, format_xmp_date, hash_file, sidecar_path, update_manifests, XmpDocument, XmpField,
+    MANIFEST_FILE,
+};
+use folio_ingest::{
+    preflight, space_needed, transfer_file, verify_or_remove, Journal, LinkMode, MoveLog,
+    PlannedFile, TransferMethod,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Pre-flight checks, run before any prompt or copy: the destination must be
+/// writable and have room for `copies` (`require_archive` has checked it is
+/// an archive)
+pub fn check_destination(archive: &Path, copies: &[(PathBuf, u64)], link: LinkMode) -> Result<()> {
+    let needed = space_needed(copies, archive, link);
+    preflight(archive, needed)?;
+    println!(
+        "Pre-flight checks passed: {} to copy into {}",
+        format_size(needed),
+        archive.display()
+    );
+    Ok(())
+}
+
+/// Carry out the plan recorded in a journal, skipping files it already records as done
+///
+/// Used both for fresh ingests and for `--resume`. The journal is marked
+/// completed once every file is archived (and every source removed in move mode).
+pub fn execute(journal: &mut Journal, archive: &Path) -> Result<()> {
+    let plan = journal.plan().clone();
+
+    let mut copied = 0;
+    let mut skipped = 0;
+    let mut failed = 0;
+    let mut removed = 0;
+    let mut move_log: Option<MoveLog> = None;
+    let mut transfers: Vec<(usize, TransferMethod)> = Vec::new();
+
+    for batch in batch_ranges(&plan.files) {
+        let mut batch_failed = false;
+
+        for index in batch.clone() {
+            if journal.is_done(index) {
+                continue;
+            }
+            let file = &plan.files[index];
+
+            if file.duplicate {
+                skipped += 1;
+                journal.record_done(index, None)?;
+                continue;
+            }
+
+            // Only a file this journal began placing may already be there
+            let resumed = journal.is_started(index);
+            if !resumed {
+                journal.record_started(index)?;
+            }
+            match transfer_planned(file, archive, plan.link, plan.move_sources, resumed) {
+                Ok((method, sidecar)) => {
+                    // Undo only deletes the sidecar if it still has this content
+                    if let Some(hash) = sidecar {
+                        journal.record_sidecar(index, hash)?;
+                    }
+                    journal.record_done(index, Some(method))?;
+                    copied += 1;
+                    transfers.push((index, method));
+                }
+                Err(e) => {
+                    eprintln!("❌ {:#}", e);
+                    failed += 1;
+                    batch_failed = true;
+                }
+            }
+        }
+
+        if plan.move_sources {
+            let batch_name = &plan.files[batch.start].batch;
+
+            // Sources are only removed when every file of the batch is safe
+            if batch_failed {
+                eprintln!(
+                    "⚠️  Keeping source files of batch '{}' because some files failed",
+                    batch_name
+                );
+                continue;
+            }
+
+            for index in batch {
+                if journal.is_source_removed(index) {
+                    continue;
+                }
+                let file = &plan.files[index];
+
+                // Removed before an interruption, but not yet journaled
+                if !file.source.exists() {
+                    journal.record_source_removed(index)?;
+                    continue;
+                }
+
+                // Content found in the archive wasn't placed by this ingest;
+                // only a copy that still matches makes the source redundant
+                let archived = archive.join(&file.archived);
+                if journal.method(index).is_none()
+                    && hash_file(&archived).ok() != Some(file.blake3()?)
+                {
+                    eprintln!(
+                        "⚠️  Keeping {}: {} no longer matches it",
+                        file.source.display(),
+                        archived.display()
+                    );
+                    continue;
+                }
+
+                let log = match move_log.as_mut() {
+                    Some(log) => log,
+                    None => move_log.insert(MoveLog::create(archive)?),
+                };
+                log.remove_source(&file.source, &file.blake3()?, &archived)?;
+                journal.record_source_removed(index)?;
+                removed += 1;
+            }
+        }
+    }
+
+    if plan.link == LinkMode::Copy {
+        println!("\nCopied {} files", copied);
+    } else {
+        // Linking can fall back to copying, so say what happened to each file
+        println!("\nTransfer methods:");
+        for (index, method) in &transfers {
+            println!(
+                "  {:<10} {}",
+                method.label(),
+                archive.join(&plan.files[*index].archived).display()
+            );
+        }
+        let count = |method| transfers.iter().filter(|(_, m)| *m == method).count();
+        println!(
+            "\nArchived {} files ({} hardlinked, {} reflinked, {} copied)",
+            copied,
+            count(TransferMethod::Hardlink),
+            count(TransferMethod::Reflink),
+            count(TransferMethod::Copy)
+        );
+    }
+    if skipped > 0 {
+        println!("Skipped {} duplicate files", skipped);
+    }
+    if let Some(log) = &move_log {
+        println!(
+            "Removed {} source {} (log: {})",
+            removed,
+            if removed == 1 { "file" } else { "files" },
+            log.path().display()
+        );
+    }
+
+    // Checksums next to the files, so copies can be checked without folio
+    let archived = plan
+        .files
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| journal.is_done(*index))
+        .map(|(_, file)| Ok((file.archived.clone(), file.blake3()?)))
+        .collect::<Result<Vec<_>>>()?;
+    let manifests = update_manifests(archive, &archived, &[])?;
+    if manifests > 0 {
+        println!(
+            "Updated {} in {} {}",
+            MANIFEST_FILE,
+            manifests,
+            if manifests == 1 { "folder" } else { "folders" }
+        );
+    }
+
+    if failed > 0 {
+        anyhow::bail!(
+            "{} {} could not be copied; fix the problem and re-run with --resume",
+            failed,
+            if failed == 1 { "file" } else { "files" }
+        );
+    }
+
+    journal.complete()?;
+    println!("Ingest {} complete", journal.id());
+    Ok(())
+}
+
+/// Place one planned file in the archive and return how it got there, and the
+/// hash of the sidecar written for it
+///
+/// With `resumed`, an earlier run began placing the file, and a complete copy
+/// it left is kept. Any other file at the destination is a conflict.
+fn transfer_planned(
+    file: &PlannedFile,
+    archive: &Path,
+    link: LinkMode,
+    move_sources: bool,
+    resumed: bool,
+) -> Result<(TransferMethod, Option<blake3::Hash>)> {
+    let dest_file = archive.join(&file.archived);
+    let hash = file.blake3()?;
+
+    // Create the folder structure from the template
+    if let Some(dest_folder) = dest_file.parent() {
+        fs::create_dir_all(dest_folder).context("Failed to create date-based folder")?;
+    }
+
+    // Left behind by an interruption: keep it if complete, otherwise start over
+    let method = if resumed && dest_file.exists() && verify_or_remove(&dest_file, &hash).is_ok() {
+        TransferMethod::Copy
+    } else {
+        // A hardlink is the source's own data; any other copy is verified
+        // before the source is removed
+        let method = transfer_file(&file.source, &dest_file, link)?;
+        if move_sources && method != TransferMethod::Hardlink {
+            verify_or_remove(&dest_file, &hash)?;
+        }
+        method
+    };
+
+    // The EXIF time is still the camera's, file names only hold the slug and
+    // viewers don't geocode; record the corrected time, title and place
+    if file.writes_sidecar() {
+        let sidecar = sidecar_path(&dest_file);
+        let mut xmp = XmpDocument::load_or_default(&sidecar)?;
+        if let Some(corrected) = file.corrected_time {
+            xmp.set(XmpField::DateTimeOriginal, &format_xmp_date(corrected));
+        }
+        if let Some(title) = &file.title {
+            xmp.set(XmpField::Title, title);
+        }
+        if let Some(location) = &file.location {
+            for (field, value) in [
+                (XmpField::City, &location.city),
+                (XmpField::State, &location.region),
+                (XmpField::Country, &location.country),
+            ] {
+                if let Some(value) = value {
+                    xmp.set(field, value);
+                }
+            }
+        }
+        xmp.save(&sidecar)?;
+        return Ok((method, Some(hash_file(&sidecar)?)));
+    }
+    Ok((method, None))
+}
+
+/// Index ranges of consecutive files of the same batch
+///
+/// Grouped by the batch index, not the name: a device profile's prefix gives
+/// one batch's files different names.
+fn batch_ranges(files: &[PlannedFile]) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for index in 1..=files.len() {
+        if index == files.len() || files[index].batch_index != files[start].batch_index {
+            if start < index {
+                ranges.push(start..index);
+            }
+            start = index;
+        }
+    }
+    ranges
+}
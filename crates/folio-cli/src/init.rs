@@ -0,0 +1,71 @@
+use anyhow::{Context, Result};
+use folio_core::config::CONFIG_FILE_NAME;
+use folio_core::{ArchiveMarker, FolioConfig, PathTemplate};
+use folio_ingest::Destination;
+use std::fs;
+use std::path::Path;
+
+/// Set up `archive` as a new archive: its `.folio/archive.toml` marker and a
+/// folio.toml giving its layout
+///
+/// The layout is `template`, or else the one in effect now (the user's
+/// folio.toml or the default), so later changes to the user's config can't
+/// move where retime, rename and reorganize look for the archive's files.
+pub fn run(archive: &Path, template: Option<PathTemplate>) -> Result<()> {
+    let destination = Destination::inspect(archive)?;
+    if let Destination::Archive(marker) = &destination {
+        anyhow::bail!(
+            "{} is already a folio archive ({})",
+            archive.display(),
+            marker.id
+        );
+    }
+
+    let config = archive.join(CONFIG_FILE_NAME);
+    let existing = if config.exists() {
+        fs::read_to_string(&config).with_context(|| format!("Failed to read {:?}", config))?
+    } else {
+        String::new()
+    };
+    let has_template = FolioConfig::parse(&existing)
+        .with_context(|| format!("Failed to load {:?}", config))?
+        .template
+        .is_some();
+    if has_template && template.is_some() {
+        anyhow::bail!(
+            "{} already sets a template in its {}; change it there instead of with --template",
+            archive.display(),
+            CONFIG_FILE_NAME
+        );
+    }
+
+    fs::create_dir_all(archive).with_context(|| format!("Failed to create {:?}", archive))?;
+    if !has_template {
+        let template = match template {
+            Some(template) => template,
+            None => FolioConfig::load_layered(archive)?
+                .template
+                .unwrap_or_default(),
+        };
+        // Top-level keys have to come before any [[profile]] table
+        let text = format!("template = {:?}\n{}", template.as_str(), existing);
+        let partial = archive.join(format!(".{}.partial", CONFIG_FILE_NAME));
+        fs::write(&partial, text).with_context(|| format!("Failed to write {:?}", partial))?;
+        fs::rename(&partial, &config).with_context(|| format!("Failed to write {:?}", config))?;
+        println!("Layout: {}", template.as_str());
+    }
+    let marker = ArchiveMarker::create(archive)?;
+
+    println!("Initialized archive {} ({})", archive.display(), marker.id);
+    match destination {
+        Destination::Unmarked => {
+            println!("Adopted the existing archive; its files and history are kept")
+        }
+        Destination::Foreign => println!(
+            "The directory already holds files; use `folio reorganize --into {}` to bring them into the archive's layout",
+            archive.display()
+        ),
+        _ => {}
+    }
+    Ok(())
+}
@@ -1,14 +1,34 @@
+mod ingest;
+mod init;
+mod mounts;
+mod rename_batch;
+mod reorganize;
+mod replicate;
+mod retime;
+mod undo;
+mod verify;
+mod watch;
+
 use anyhow::{Context, Result};
-use chrono::{Duration, Utc};
-use clap::{Parser, Subcommand};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use folio_core::fs_ops::volume_label;
 use folio_core::{
-    generate_filename, group_by_temporal_proximity, scan_directory, validate_batch_name,
-    TemporalBatch,
+    apply_device_timezones, apply_time_shifts, dedupe_by_hash, device_timezone, format_size,
+    parse_offset, parse_size, resolve_undated, scan_directory, scan_directory_skipping,
+    sidecar_path, slugify_batch_name, undated_items, validate_batch_name, with_counter,
+    AdaptiveGap, BatchingStrategy, CalendarDay, DateRange, FixedGap, FolioConfig, Gazetteer,
+    GpsDistance, MediaItem, MediaKinds, NameSuggester, PathTemplate, PerCamera, ScanConfig,
+    ScanFilter, TemplateContext, TemporalBatch, TimeShift, UndatedResolution, ARCHIVE_MARKER,
+};
+use folio_ingest::{
+    Destination, ImportHistory, ImportedFile, IngestPlan, Journal, LinkMode, PlannedFile,
+    S3Location, Sample, DEFAULT_PART_SIZE, MIN_PART_SIZE,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "folio")]
@@ -21,15 +41,39 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Ingest photos/videos from a source directory
-    Ingest {
-        /// Source directory (e.g., SD card, phone backup)
+    /// Make a directory a folio archive: creates .folio/ with the archive's id
+    /// and schema version. Commands that change an archive refuse other directories
+    Init {
+        /// Directory to initialize (created if missing; files in it are kept)
         #[arg(short, long)]
-        source: String,
+        archive: String,
+
+        /// Folder and filename template of the archive's layout, written to its
+        /// folio.toml (default: the user's folio.toml, then the standard one)
+        #[arg(long)]
+        template: Option<PathTemplate>,
+    },
+
+    /// Ingest photos/videos from one or more source directories
+    Ingest {
+        /// Source directory (e.g., SD card, phone backup); repeat to ingest
+        /// several cards as one session with shared batches
+        #[arg(short, long, required_unless_present = "auto")]
+        source: Vec<String>,
+
+        /// Ingest from every mounted volume with a DCIM folder instead of --source
+        #[arg(long, conflicts_with = "source")]
+        auto: bool,
+
+        /// Where --auto looks for mounted volumes (default: /media and /run/media,
+        /// or `mount_root` in the user's folio.toml)
+        #[arg(long, requires = "auto")]
+        mount_root: Option<PathBuf>,
 
-        /// Destination directory in archive
+        /// Destination directory in archive (default: `destination` in the
+        /// user's folio.toml)
         #[arg(short, long)]
-        dest: String,
+        dest: Option<String>,
 
         /// Perform dry run without copying files
         #[arg(long)]
@@ -39,9 +83,279 @@ enum Commands {
         #[arg(long)]
         batch_name: Option<String>,
 
-        /// Time gap in hours to separate batches (default: 2.0)
-        #[arg(long, default_value = "2.0")]
-        gap_threshold: f64,
+        /// Batching strategy used to detect events
+        #[arg(long, value_enum, default_value_t = BatchingKind::FixedGap)]
+        batching: BatchingKind,
+
+        /// Time gap in hours to separate batches (default: 2.0, or
+        /// `gap_threshold` in folio.toml)
+        #[arg(long)]
+        gap_threshold: Option<f64>,
+
+        /// Adaptive batching: split when a gap exceeds this multiple of the local shooting rate
+        #[arg(long, default_value = "5.0")]
+        adaptive_factor: f64,
+
+        /// Adaptive batching: gaps shorter than this many hours never split
+        #[arg(long, default_value = "0.25")]
+        min_gap: f64,
+
+        /// Adaptive batching: gaps longer than this many hours always split
+        #[arg(long, default_value = "4.0")]
+        max_gap: f64,
+
+        /// GPS batching: distance in kilometres that starts a new batch
+        #[arg(long, default_value = "5.0")]
+        gps_distance: f64,
+
+        /// Remove each source file once its archive copy is verified by hash
+        /// (or it is a verified duplicate already in the archive)
+        #[arg(long = "move")]
+        move_sources: bool,
+
+        /// How files are placed in the archive; hard and reflink fall back to
+        /// copying when the source is on another device or cloning isn't supported
+        #[arg(long, value_enum, default_value_t = LinkKind::Copy)]
+        link: LinkKind,
+
+        /// Continue an interrupted ingest of the same source from its journal,
+        /// without rescanning or asking for batch names again
+        #[arg(long, conflicts_with = "dry_run")]
+        resume: bool,
+
+        /// Read and hash every file again, including ones already imported
+        /// from the same device (always the case with --move, which verifies
+        /// each file before removing it)
+        #[arg(long)]
+        rescan: bool,
+
+        /// Folder and filename template, e.g. "{year}/{date}_{batch}/{date}-{time}-{seq}.{ext}"
+        /// Overrides the template in the archive's folio.toml
+        #[arg(long)]
+        template: Option<PathTemplate>,
+
+        /// Correct a camera's clock before batching, e.g. "NIKON D800=+1h"
+        /// The camera is matched by model, make and model, or serial number (repeatable)
+        #[arg(long, value_name = "CAMERA=OFFSET", allow_hyphen_values = true)]
+        time_shift: Vec<TimeShift>,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+
+        /// Run even if the destination is not an archive set up with `folio init`
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Fix capture times of already-archived files (renames, refolders and updates XMP)
+    #[command(group(ArgGroup::new("selection").required(true).multiple(true).args(["camera", "batch"])))]
+    Retime {
+        /// Archive directory containing the files to fix
+        #[arg(short, long)]
+        archive: String,
+
+        /// Clock offset to apply, e.g. "+1h" or "-30m"
+        #[arg(long, value_parser = parse_offset, allow_hyphen_values = true)]
+        shift: Duration,
+
+        /// Only fix files from this camera (model, make and model, or serial number)
+        #[arg(long)]
+        camera: Option<String>,
+
+        /// Only fix files with this batch name
+        #[arg(long)]
+        batch: Option<String>,
+
+        /// Show the planned renames without changing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Run even if the directory is not an archive set up with `folio init`
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Rename a batch in an archive, including sidecars and the ingest journals
+    RenameBatch {
+        /// Archive directory containing the batch
+        #[arg(short, long)]
+        archive: String,
+
+        /// Current batch name
+        #[arg(long)]
+        from: String,
+
+        /// New batch name
+        #[arg(long)]
+        to: String,
+
+        /// New title for files that have one (dc:title); defaults to the new name
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Only rename files captured in this range, e.g. "2024-11-01..2024-11-30"
+        #[arg(long)]
+        date_range: Option<DateRange>,
+
+        /// Show the planned renames without changing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Run even if the directory is not an archive set up with `folio init`
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Move an existing (legacy) tree into the folio layout
+    #[command(group(ArgGroup::new("naming").args(["batch_from_folders", "batch_name"])))]
+    Reorganize {
+        /// Existing directory tree to reorganize
+        #[arg(short, long, required_unless_present = "rollback")]
+        archive: Option<String>,
+
+        /// Root of the folio layout to move files into (may be the same directory)
+        #[arg(long, required_unless_present = "rollback")]
+        into: Option<String>,
+
+        /// Name batches after the files' current parent folders
+        #[arg(long)]
+        batch_from_folders: bool,
+
+        /// Batch name for all files (skips interactive prompts)
+        #[arg(long)]
+        batch_name: Option<String>,
+
+        /// Time gap in hours to separate batches when naming interactively
+        /// (default: 2.0, or `gap_threshold` in folio.toml)
+        #[arg(long)]
+        gap_threshold: Option<f64>,
+
+        /// Folder and filename template (overrides folio.toml in the destination)
+        #[arg(long)]
+        template: Option<PathTemplate>,
+
+        /// Print the mapping report without moving anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Move files back using the manifest of an earlier reorganize
+        #[arg(long, value_name = "MANIFEST", conflicts_with_all = ["archive", "into"])]
+        rollback: Option<PathBuf>,
+
+        /// Run even if the --into directory is not an archive set up with `folio init`
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Watch a folder (e.g. a phone sync directory) and ingest new files once they settle
+    Watch {
+        /// Folder to watch
+        #[arg(short, long)]
+        source: String,
+
+        /// Destination directory in archive (default: `destination` in the
+        /// user's folio.toml)
+        #[arg(short, long)]
+        dest: Option<String>,
+
+        /// Batch name for everything ingested (default: a name from location,
+        /// holidays or birthdays, else an inbox-<date> placeholder queued for renaming)
+        #[arg(long)]
+        batch_name: Option<String>,
+
+        /// Poll for changes instead of using filesystem notifications (for network mounts)
+        #[arg(long)]
+        poll: bool,
+
+        /// Seconds between checks; files are ingested once unchanged for one interval
+        #[arg(long, default_value = "5")]
+        interval: u64,
+
+        /// Log file (default: <dest>/.folio/logs/watch.log)
+        #[arg(long)]
+        log: Option<PathBuf>,
+
+        /// Run even if the destination is not an archive set up with `folio init`
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Watch for camera media being mounted and start an ingest for each card
+    WatchMounts {
+        /// Destination directory in archive (default: `destination` in the
+        /// user's folio.toml)
+        #[arg(short, long)]
+        dest: Option<String>,
+
+        /// Where volumes get mounted (default: /media and /run/media, or
+        /// `mount_root` in the user's folio.toml)
+        #[arg(long)]
+        mount_root: Option<PathBuf>,
+
+        /// Seconds between checks for newly mounted volumes
+        #[arg(long, default_value = "2")]
+        interval: u64,
+    },
+
+    /// Revert an ingest, removing exactly the files it added (latest ingest by default)
+    Undo {
+        /// Archive the ingest went into
+        #[arg(short, long)]
+        archive: String,
+
+        /// Ingest to revert, as printed when it completed (see .folio/journal/)
+        ingest_id: Option<String>,
+
+        /// Show what would be removed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Run even if the directory is not an archive set up with `folio init`
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Check archived files against the hashes recorded at ingest (bit rot,
+    /// missing and unexpected files); exits non-zero on any problem
+    Verify {
+        /// Archive directory to verify, or an S3 replica (s3://bucket/prefix)
+        #[arg(short, long)]
+        archive: String,
+
+        /// Only hash part of the archive per run, e.g. "5%" or "500"; files
+        /// verified longest ago go first, so repeated runs cover everything
+        #[arg(long)]
+        sample: Option<Sample>,
+    },
+
+    /// Copy new and changed archive files to a backup root by content hash,
+    /// verifying each copy and reporting drift between the two
+    Replicate {
+        /// Archive to replicate
+        #[arg(long)]
+        from: String,
+
+        /// Backup directory (any local or mounted path), or S3-compatible
+        /// storage as s3://bucket/prefix (credentials from AWS_* variables)
+        #[arg(long)]
+        to: String,
+
+        /// Also remove files deleted from the archive from the target (asks first)
+        #[arg(long)]
+        delete: bool,
+
+        /// Show what would be copied without changing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Upload files larger than this to S3 in parts of this size, e.g. "64MB"
+        /// (default 16MB, at least 5MB)
+        #[arg(long, value_parser = parse_part_size)]
+        part_size: Option<u64>,
+
+        /// Run even if the source is not an archive set up with `folio init`
+        #[arg(long)]
+        force: bool,
     },
 
     /// Find and report duplicate files
@@ -59,20 +373,621 @@ enum Commands {
     Version,
 }
 
+/// Which files a scan picks up; adds to the `[scan]` settings in folio.toml
+#[derive(Debug, Args)]
+struct ScanArgs {
+    /// Only pick up files matching this glob, e.g. "*.jpg" or "Camera/**" (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files and folders matching this glob, e.g. "Screenshots" (repeatable);
+    /// trash folders, thumbnail caches and other OS/NAS junk are always skipped
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Also scan trash folders, thumbnail caches and other OS/NAS junk
+    #[arg(long)]
+    no_default_excludes: bool,
+
+    /// Skip files smaller than this, e.g. "20k" or "1M"
+    #[arg(long, value_parser = parse_size)]
+    min_size: Option<u64>,
+
+    /// Only files captured on or after this date (YYYY-MM-DD)
+    #[arg(long)]
+    since: Option<NaiveDate>,
+
+    /// Only files captured on or before this date (YYYY-MM-DD)
+    #[arg(long)]
+    until: Option<NaiveDate>,
+
+    /// Only pick up photos
+    #[arg(long, conflicts_with = "videos_only")]
+    photos_only: bool,
+
+    /// Only pick up videos
+    #[arg(long)]
+    videos_only: bool,
+
+    /// How many folder levels below the source to look into (1: only its own files)
+    #[arg(long)]
+    max_depth: Option<usize>,
+}
+
+impl ScanArgs {
+    /// Combine the flags with the configured scan settings
+    fn filter(self, config: &ScanConfig) -> Result<ScanFilter> {
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            if since > until {
+                anyhow::bail!("--since {} is after --until {}", since, until);
+            }
+        }
+        let mut config = config.clone();
+        if self.no_default_excludes {
+            config.default_excludes = Some(false);
+        }
+        let mut filter = config.filter(&self.include, &self.exclude)?;
+        if let Some(min_size) = self.min_size {
+            filter.min_size = min_size;
+        }
+        filter.since = self.since;
+        filter.until = self.until;
+        filter.kinds = if self.photos_only {
+            MediaKinds::Photos
+        } else if self.videos_only {
+            MediaKinds::Videos
+        } else {
+            MediaKinds::All
+        };
+        filter.max_depth = self.max_depth;
+        Ok(filter)
+    }
+}
+
+/// Transfer methods selectable with `--link`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LinkKind {
+    /// Hardlink files into the archive (source and archive share the data)
+    Hard,
+    /// Copy-on-write clone where the filesystem supports it
+    Reflink,
+    /// Full copy
+    Copy,
+}
+
+impl From<LinkKind> for LinkMode {
+    fn from(kind: LinkKind) -> Self {
+        match kind {
+            LinkKind::Hard => LinkMode::Hard,
+            LinkKind::Reflink => LinkMode::Reflink,
+            LinkKind::Copy => LinkMode::Copy,
+        }
+    }
+}
+
+/// Batching strategies selectable with `--batching`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BatchingKind {
+    /// Split when the time gap exceeds --gap-threshold
+    FixedGap,
+    /// One batch per calendar day
+    CalendarDay,
+    /// Split on gaps that are long relative to the local shooting rate
+    Adaptive,
+    /// Split when the camera moves further than --gps-distance (or on --gap-threshold)
+    Gps,
+    /// Never mix cameras in a batch; each camera is split with --gap-threshold
+    PerCamera,
+}
+
+/// Options shared by the batching strategies
+struct BatchingOptions {
+    gap_threshold: f64,
+    adaptive_factor: f64,
+    min_gap: f64,
+    max_gap: f64,
+    gps_distance: f64,
+}
+
+/// Batch gap when neither the command line nor folio.toml sets one
+const DEFAULT_GAP_HOURS: f64 = 2.0;
+
+fn hours_to_duration(hours: f64) -> Duration {
+    Duration::seconds((hours * 3600.0) as i64)
+}
+
+/// Build the batching strategy selected on the command line
+fn build_batching_strategy(
+    kind: BatchingKind,
+    options: &BatchingOptions,
+) -> Box<dyn BatchingStrategy> {
+    let fixed_gap = FixedGap {
+        gap_threshold: hours_to_duration(options.gap_threshold),
+    };
+
+    match kind {
+        BatchingKind::FixedGap => Box::new(fixed_gap),
+        BatchingKind::CalendarDay => Box::new(CalendarDay),
+        BatchingKind::Adaptive => Box::new(AdaptiveGap {
+            min_gap: hours_to_duration(options.min_gap),
+            max_gap: hours_to_duration(options.max_gap),
+            factor: options.adaptive_factor,
+        }),
+        BatchingKind::Gps => Box::new(GpsDistance {
+            max_distance_km: options.gps_distance,
+            gap_threshold: hours_to_duration(options.gap_threshold),
+        }),
+        BatchingKind::PerCamera => Box::new(PerCamera {
+            inner: Box::new(fixed_gap),
+        }),
+    }
+}
+
+/// Build a batch from arbitrary items (e.g. a single named batch or the undated files)
+/// Items without timestamps don't contribute to the date range
+fn batch_from_items(items: Vec<MediaItem>) -> TemporalBatch {
+    TemporalBatch {
+        start_time: items
+            .iter()
+            .filter_map(|i| i.timestamp)
+            .min()
+            .unwrap_or_else(Utc::now),
+        end_time: items
+            .iter()
+            .filter_map(|i| i.timestamp)
+            .max()
+            .unwrap_or_else(Utc::now),
+        items,
+    }
+}
+
+/// One source directory of an ingest
+struct IngestSource {
+    /// As given on the command line (scanned item paths start with it)
+    path: PathBuf,
+    /// Canonical path, recorded in the journal
+    root: PathBuf,
+    volume: Option<String>,
+}
+
+impl IngestSource {
+    /// Name the import history is kept under: the profile set up for the
+    /// source's volume, else the volume label, else the source folder
+    fn device(&self, config: &FolioConfig) -> String {
+        let volume = self.volume.as_deref();
+        config
+            .profiles
+            .iter()
+            .find(|profile| {
+                profile.volume.as_deref().is_some_and(|expected| {
+                    volume.is_some_and(|volume| volume.eq_ignore_ascii_case(expected))
+                })
+            })
+            .map(|profile| profile.name.clone())
+            .or_else(|| self.volume.clone())
+            .unwrap_or_else(|| self.root.display().to_string())
+    }
+
+    /// The source an item was scanned from (the innermost one if sources nest)
+    fn of<'a>(sources: &'a [IngestSource], item: &MediaItem) -> &'a IngestSource {
+        sources
+            .iter()
+            .filter(|source| item.path.starts_with(&source.path))
+            .max_by_key(|source| source.path.components().count())
+            .unwrap_or(&sources[0])
+    }
+}
+
+fn modified_time(metadata: &fs::Metadata) -> Option<DateTime<Utc>> {
+    metadata.modified().ok().map(DateTime::from)
+}
+
+/// Import history entries for scanned items, relative to the folder they were scanned from
+fn imported_files(items: &[MediaItem], source: &Path) -> Vec<(PathBuf, ImportedFile)> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let modified = modified_time(&fs::metadata(&item.path).ok()?)?;
+            let relative = item.path.strip_prefix(source).ok()?.to_path_buf();
+            Some((
+                relative,
+                ImportedFile {
+                    size: item.size,
+                    modified,
+                    hash: item.hash.to_hex().to_string(),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Content already in an archive, by hash
+fn archive_index(archive: &Path) -> HashMap<blake3::Hash, PathBuf> {
+    scan_directory(archive)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| (item.hash, item.path))
+        .collect()
+}
+
+/// Everything needed to decide where ingested files go
+struct IngestPlanner<'a> {
+    archive: &'a Path,
+    template: &'a PathTemplate,
+    config: &'a FolioConfig,
+    sources: &'a [IngestSource],
+    time_shift: &'a [TimeShift],
+    places: Option<&'a Gazetteer>,
+    /// Content already in the archive, see `archive_index`
+    archived: &'a HashMap<blake3::Hash, PathBuf>,
+}
+
+impl IngestPlanner<'_> {
+    /// Decide every destination up front so an interrupted run can resume
+    ///
+    /// Files whose name is already taken, in the archive or earlier in the
+    /// plan, get a counter (see `with_counter`) instead of replacing anything.
+    fn plan(&self, batches: &[(TemporalBatch, BatchName)]) -> Vec<PlannedFile> {
+        let mut files = Vec::new();
+        let mut taken = HashSet::new();
+        for (batch_index, (batch, name)) in batches.iter().enumerate() {
+            let named: Vec<_> = batch
+                .items
+                .iter()
+                .map(|item| {
+                    let source = IngestSource::of(self.sources, item);
+                    // A device profile may prefix the batch name, e.g. "dad-"
+                    let batch_name = match self
+                        .config
+                        .profile_for(item.camera.as_ref(), source.volume.as_deref())
+                        .and_then(|profile| profile.prefix.as_deref())
+                    {
+                        Some(prefix) => format!("{}{}", prefix, name.slug),
+                        None => name.slug.clone(),
+                    };
+                    (item, source, batch_name)
+                })
+                .collect();
+
+            // {seq} carries on after the batch's files already in their folders
+            let mut highest = HashMap::new();
+            let first_seq = if self.template.has_seq() {
+                named
+                    .iter()
+                    .filter_map(|(item, _, batch_name)| {
+                        let folder = self.destination(item, batch_name, 1);
+                        let folder = folder.parent()?.to_path_buf();
+                        Some(
+                            *highest
+                                .entry((folder, batch_name.as_str()))
+                                .or_insert_with_key(|(folder, batch_name)| {
+                                    highest_seq(
+                                        self.archive,
+                                        self.template,
+                                        folder,
+                                        batch_name,
+                                        &taken,
+                                    )
+                                }),
+                        )
+                    })
+                    .max()
+                    .unwrap_or(0)
+            } else {
+                0
+            };
+
+            for (seq, (item, source, batch_name)) in named.iter().enumerate() {
+                let timestamp = self.timestamp(item);
+                let relative = self.destination(item, batch_name, first_seq + seq + 1);
+
+                // Check if already exists in destination (its hash was just computed)
+                let existing = self.archived.get(&item.hash).map(|existing| {
+                    existing
+                        .strip_prefix(self.archive)
+                        .unwrap_or(existing)
+                        .to_path_buf()
+                });
+
+                let duplicate = existing.is_some();
+                let archived = match existing {
+                    Some(existing) => existing,
+                    None => unused(self.archive, relative, &mut taken),
+                };
+
+                files.push(PlannedFile {
+                    source: source
+                        .root
+                        .join(item.path.strip_prefix(&source.path).unwrap_or(&item.path)),
+                    hash: item.hash.to_hex().to_string(),
+                    duplicate,
+                    archived,
+                    batch: batch_name.clone(),
+                    batch_index,
+                    title: name.title.clone(),
+                    corrected_time: (self.time_shift.iter().any(|shift| shift.applies_to(item))
+                        || device_timezone(self.config, item, source.volume.as_deref()).is_some())
+                    .then_some(timestamp),
+                    location: self
+                        .places
+                        .zip(item.gps.as_ref())
+                        .and_then(|(places, gps)| places.lookup(gps)),
+                });
+            }
+        }
+        files
+    }
+
+    /// Capture time, or the modification time of files without one
+    fn timestamp(&self, item: &MediaItem) -> DateTime<Utc> {
+        item.timestamp.unwrap_or_else(|| {
+            std::fs::metadata(&item.path)
+                .and_then(|m| m.modified())
+                .map(|t| t.into())
+                .unwrap_or_else(|_| Utc::now())
+        })
+    }
+
+    /// Where the template puts `item` as file `seq` of its batch
+    fn destination(&self, item: &MediaItem, batch_name: &str, seq: usize) -> PathBuf {
+        let relative = self.template.render(&TemplateContext {
+            timestamp: self.timestamp(item),
+            batch_name,
+            seq,
+            camera: item.camera.as_ref(),
+            original_path: &item.path,
+            media_type: &item.media_type,
+        });
+
+        // Undated files keep the template's filename under unknown-date
+        if item.timestamp.is_some() {
+            relative
+        } else {
+            item.folder_path
+                .join(relative.file_name().unwrap_or_default())
+        }
+    }
+}
+
+/// The highest `{seq}` that files of `batch_name` use in `folder` (relative to
+/// `archive`), on disk or planned so far
+fn highest_seq(
+    archive: &Path,
+    template: &PathTemplate,
+    folder: &Path,
+    batch_name: &str,
+    taken: &HashSet<PathBuf>,
+) -> usize {
+    let archived = fs::read_dir(archive.join(folder))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some(folder.join(entry.ok()?.file_name())));
+    let planned = taken
+        .iter()
+        .filter(|path| path.parent() == Some(folder))
+        .cloned();
+    archived
+        .chain(planned)
+        .filter_map(|path| template.read(&path))
+        .filter(|fields| fields.batch().map_or(true, |batch| batch == batch_name))
+        .filter_map(|fields| fields.seq())
+        .max()
+        .unwrap_or(0)
+}
+
+/// `relative`, or the first of its numbered variants that is free in `archive`
+/// and not `taken` by an earlier file of the plan
+///
+/// The sidecar name is claimed too: `IMG_1.jpg` and `IMG_1.mov` would
+/// otherwise share `IMG_1.xmp`.
+fn unused(archive: &Path, relative: PathBuf, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let free = |path: &PathBuf| {
+        [path.clone(), sidecar_path(path)]
+            .iter()
+            .all(|path| !taken.contains(path) && !archive.join(path).exists())
+    };
+    let path = if free(&relative) {
+        relative
+    } else {
+        (2..)
+            .map(|counter| with_counter(&relative, counter))
+            .find(free)
+            .expect("some counter is free")
+    };
+    taken.insert(sidecar_path(&path));
+    taken.insert(path.clone());
+    path
+}
+
+/// Say which device profiles apply, and warn about time zones that can't be used
+fn report_profiles(config: &FolioConfig, items: &[MediaItem], sources: &[IngestSource]) {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for profile in items.iter().filter_map(|item| {
+        let volume = IngestSource::of(sources, item).volume.as_deref();
+        config.profile_for(item.camera.as_ref(), volume)
+    }) {
+        match counts.iter_mut().find(|(name, _)| *name == profile.name) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&profile.name, 1)),
+        }
+    }
+    for (name, count) in &counts {
+        println!(
+            "Using profile '{}' for {} {}",
+            name,
+            count,
+            if *count == 1 { "file" } else { "files" }
+        );
+    }
+
+    if config.timezone.is_none() && config.profiles.iter().any(|p| p.timezone.is_some()) {
+        eprintln!(
+            "⚠️  Profile time zones are ignored until `timezone` is set for the archive in folio.toml"
+        );
+    }
+}
+
+/// Parse `--part-size`; S3 only completes a multipart upload whose parts
+/// (all but the last) are 5MB or more
+fn parse_part_size(s: &str) -> Result<u64> {
+    let size = parse_size(s)?;
+    if size < MIN_PART_SIZE {
+        anyhow::bail!(
+            "{} is too small; S3 needs parts of at least {}",
+            s,
+            format_size(MIN_PART_SIZE)
+        );
+    }
+    Ok(size)
+}
+
+/// Refuse to change `dir` unless `folio init` made it an archive, or `force` is given
+fn require_archive(dir: &Path, force: bool) -> Result<()> {
+    let hint = match Destination::inspect(dir)? {
+        Destination::Archive(_) => return Ok(()),
+        _ if force => {
+            eprintln!(
+                "⚠️  {} is not an initialized archive; continuing because of --force",
+                dir.display()
+            );
+            return Ok(());
+        }
+        Destination::New => {
+            " It is missing or empty; if it lives on an external drive or NAS, is it mounted?"
+        }
+        Destination::Unmarked => " It looks like an archive from an older version of folio.",
+        Destination::Foreign => "",
+    };
+    anyhow::bail!(
+        "{} is not a folio archive (no {}).{} Run `folio init --archive {}` first, or pass --force",
+        dir.display(),
+        ARCHIVE_MARKER,
+        hint,
+        dir.display()
+    )
+}
+
+/// Read one line from stdin, failing instead of looping forever on end of input
+fn read_line() -> Result<String> {
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        anyhow::bail!("Unexpected end of input while waiting for an answer");
+    }
+    Ok(input.trim().to_string())
+}
+
+/// Show the first 3 filenames of a set of items
+fn print_samples(items: &[MediaItem]) {
+    println!("  Samples:");
+    for (i, item) in items.iter().take(3).enumerate() {
+        if let Some(filename) = item.path.file_name() {
+            println!("    {}. {}", i + 1, filename.to_string_lossy());
+        }
+    }
+    if items.len() > 3 {
+        println!("    ... and {} more", items.len() - 3);
+    }
+}
+
+/// Ask how files without a capture timestamp should be dated
+fn prompt_for_undated_resolution(undated: &[MediaItem]) -> Result<UndatedResolution> {
+    println!("\n--- Undated files ---");
+    let plural = if undated.len() == 1 {
+        "file has"
+    } else {
+        "files have"
+    };
+    println!("  {} {} no capture timestamp", undated.len(), plural);
+    print_samples(undated);
+
+    println!("\n  How should they be dated?");
+    println!("    1. Assign a date manually");
+    println!("    2. Borrow the date of neighboring files (by filename)");
+    println!("    3. File under unknown-date");
+
+    loop {
+        print!("\nChoose [1-3]: ");
+        io::stdout().flush()?;
+
+        match read_line()?.as_str() {
+            "1" => loop {
+                print!("Enter date (YYYY-MM-DD): ");
+                io::stdout().flush()?;
+
+                match NaiveDate::parse_from_str(&read_line()?, "%Y-%m-%d") {
+                    Ok(date) => return Ok(UndatedResolution::ManualDate(date)),
+                    Err(_) => eprintln!("❌ Invalid date, expected YYYY-MM-DD"),
+                }
+            },
+            "2" => return Ok(UndatedResolution::BorrowFromNeighbors),
+            "3" => return Ok(UndatedResolution::UnknownDate),
+            other => eprintln!("❌ Invalid choice: '{}'", other),
+        }
+    }
+}
+
+/// A batch name as used in file names, plus the title it was typed as when
+/// that had to be slugified
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BatchName {
+    slug: String,
+    title: Option<String>,
+}
+
+impl BatchName {
+    fn plain(slug: impl Into<String>) -> Self {
+        BatchName {
+            slug: slug.into(),
+            title: None,
+        }
+    }
+
+    /// Use `name` as typed if it is valid, otherwise its slug with `name` as the title
+    fn from_human(name: &str) -> Result<Self> {
+        if validate_batch_name(name).is_ok() {
+            return Ok(BatchName::plain(name));
+        }
+        let slug = slugify_batch_name(name);
+        validate_batch_name(&slug).with_context(|| format!("Invalid batch name '{}'", name))?;
+        Ok(BatchName {
+            slug,
+            title: Some(name.trim().to_string()),
+        })
+    }
+}
+
 /// Prompt user for batch name with validation
 fn prompt_for_batch_name(
     batch_num: usize,
     total_batches: usize,
     batch: &TemporalBatch,
-) -> Result<String> {
+    suggester: &NameSuggester,
+    places: Option<&Gazetteer>,
+) -> Result<BatchName> {
+    let undated = batch.items.iter().all(|i| i.timestamp.is_none());
+    let location = places.and_then(|places| places.dominant_location(&batch.items));
+    let suggestions = if undated {
+        Vec::new()
+    } else {
+        suggester.suggest(
+            batch.start_time.date_naive(),
+            batch.end_time.date_naive(),
+            location.as_ref(),
+        )
+    };
+
     loop {
         // Display batch information
         println!("\n--- Batch {} of {} ---", batch_num, total_batches);
-        println!(
-            "  Date range: {} to {}",
-            batch.start_time.format("%Y-%m-%d %H:%M:%S"),
-            batch.end_time.format("%Y-%m-%d %H:%M:%S")
-        );
+        if undated {
+            println!("  Date range: unknown (filed under unknown-date)");
+        } else {
+            println!(
+                "  Date range: {} to {}",
+                batch.start_time.format("%Y-%m-%d %H:%M:%S"),
+                batch.end_time.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
 
         let photo_count = batch
             .items
@@ -91,70 +1006,252 @@ fn prompt_for_batch_name(
             video_count
         );
 
-        // Show first 3 filenames as samples
-        println!("  Samples:");
-        for (i, item) in batch.items.iter().take(3).enumerate() {
-            if let Some(filename) = item.path.file_name() {
-                println!("    {}. {}", i + 1, filename.to_string_lossy());
-            }
-        }
-        if batch.items.len() > 3 {
-            println!("    ... and {} more", batch.items.len() - 3);
+        if let Some(location) = &location {
+            println!("  Location: {}", location.describe());
         }
 
-        // Prompt for batch name
-        print!("\nEnter batch name: ");
+        print_samples(&batch.items);
+
+        if suggestions.is_empty() {
+            print!("\nEnter batch name: ");
+        } else {
+            println!("  Suggested names:");
+            for (i, suggestion) in suggestions.iter().enumerate() {
+                println!(
+                    "    [{}] {} ({})",
+                    i + 1,
+                    suggestion.name,
+                    suggestion.reason
+                );
+            }
+            print!("\nEnter batch name or suggestion number: ");
+        }
         io::stdout().flush()?;
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let batch_name = input.trim().to_string();
-
-        // Validate batch name
-        match validate_batch_name(&batch_name) {
-            Ok(_) => return Ok(batch_name),
-            Err(e) => {
-                eprintln!("❌ Invalid batch name: {}", e);
-                eprintln!("   Please use only alphanumeric characters, hyphens, and underscores.");
-                // Loop to re-prompt
+        let batch_name = read_line()?;
+
+        // A number picks one of the suggestions
+        if let Some(suggestion) = batch_name
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| suggestions.get(i))
+        {
+            return Ok(BatchName::plain(suggestion.name.clone()));
+        }
+
+        // Validate batch name, offering a slug for human-readable names
+        let error = match validate_batch_name(&batch_name) {
+            Ok(_) => return Ok(BatchName::plain(batch_name)),
+            Err(e) => e,
+        };
+        if let Ok(name) = BatchName::from_human(&batch_name) {
+            print!("Use '{}'? [Y/n]: ", name.slug);
+            io::stdout().flush()?;
+            if !read_line()?.eq_ignore_ascii_case("n") {
+                return Ok(name);
             }
+            continue;
         }
+        eprintln!("❌ Invalid batch name: {}", error);
+        eprintln!("   Please use only alphanumeric characters, hyphens, and underscores.");
+        // Loop to re-prompt
     }
 }
 
 fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+    // Initialize tracing; warnings show without RUST_LOG, on stderr like ours
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
 
     let cli = Cli::parse();
 
     match cli.command {
         Commands::Ingest {
             source,
+            auto,
+            mount_root,
             dest,
             dry_run,
             batch_name,
+            batching,
             gap_threshold,
+            adaptive_factor,
+            min_gap,
+            max_gap,
+            gps_distance,
+            time_shift,
+            template,
+            move_sources,
+            link,
+            resume,
+            rescan,
+            scan,
+            force,
         } => {
-            // Validate batch name if provided
+            // Validate batch name if provided, suggesting its slug
             if let Some(ref name) = batch_name {
-                validate_batch_name(name).context("Invalid batch name")?;
+                if let Err(e) = validate_batch_name(name) {
+                    let slug = slugify_batch_name(name);
+                    if validate_batch_name(&slug).is_ok() {
+                        return Err(e.context(format!("Invalid batch name (try '{}')", slug)));
+                    }
+                    return Err(e.context("Invalid batch name"));
+                }
             }
+            let batch_name = batch_name.map(BatchName::plain);
 
-            let source_path = PathBuf::from(&source);
-            let dest_path = PathBuf::from(&dest);
+            // Settings come from the flags, then the archive's folio.toml, then the user's
+            let user_config = FolioConfig::load_user()?;
+            let dest_path = dest
+                .map(PathBuf::from)
+                .or_else(|| user_config.destination.clone())
+                .context("No destination given: pass --dest or set `destination` in folio.toml")?;
+            // Before hashing the archive or reading any card: an unmounted
+            // destination shouldn't cost a full scan to find out about
+            if !dry_run {
+                require_archive(&dest_path, force)?;
+            }
+
+            // With --auto the sources are the DCIM folders of mounted cards
+            let source = if auto {
+                mounts::detect(&mounts::mount_roots(mount_root, &user_config))?
+                    .into_iter()
+                    .map(|volume| volume.dcim.to_string_lossy().into_owned())
+                    .collect()
+            } else {
+                source
+            };
+
+            // Journals identify sources by their canonical paths
+            let mut sources: Vec<IngestSource> = Vec::new();
+            for path in &source {
+                let root = fs::canonicalize(path)
+                    .with_context(|| format!("Source directory not found: {}", path))?;
+                if sources.iter().any(|s| s.root == root) {
+                    continue;
+                }
+                sources.push(IngestSource {
+                    path: PathBuf::from(path),
+                    // Device profiles can match the card's volume label
+                    volume: volume_label(&root),
+                    root,
+                });
+            }
+            let roots: Vec<PathBuf> = sources.iter().map(|s| s.root.clone()).collect();
+
+            if resume {
+                let mut journal =
+                    Journal::find_incomplete(&dest_path, &roots)?.with_context(|| {
+                        format!(
+                            "No interrupted ingest of {} found in {}",
+                            source.join(", "),
+                            dest_path.display()
+                        )
+                    })?;
+                let done = (0..journal.plan().files.len())
+                    .filter(|&i| journal.is_done(i))
+                    .count();
+                println!(
+                    "Resuming ingest {} ({} of {} files already done)",
+                    journal.id(),
+                    done,
+                    journal.plan().files.len()
+                );
+                let plan = journal.plan();
+                let copies: Vec<(PathBuf, u64)> = plan
+                    .files
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, file)| !file.duplicate && !journal.is_done(*i))
+                    .map(|(_, file)| {
+                        let size = fs::metadata(&file.source).map_or(0, |meta| meta.len());
+                        (file.source.clone(), size)
+                    })
+                    .collect();
+                ingest::check_destination(&dest_path, &copies, plan.link)?;
+                return ingest::execute(&mut journal, &dest_path);
+            }
+
+            // Resolve the layout before touching anything: flag, then folio.toml, then default
+            let config = FolioConfig::load(&dest_path)?.with_fallback(user_config);
+            let filter = scan.filter(&config.scan)?;
+            let template = template.or(config.template.clone()).unwrap_or_default();
+            let gap_threshold = gap_threshold
+                .or(config.gap_threshold)
+                .unwrap_or(DEFAULT_GAP_HOURS);
 
             if dry_run {
                 println!("Dry run mode - no files will be copied\n");
+            } else if move_sources {
+                println!(
+                    "Move mode - source files are removed once their archive copy is verified\n"
+                );
+            }
+
+            // Scan every source, then batch them together as one session
+            let mut source_items = Vec::new();
+            let mut shifted = 0;
+            let mut converted = 0;
+            let mut imports = Vec::new();
+            let mut known_files = 0;
+            let rescan = rescan || move_sources;
+            for source in &sources {
+                println!("Scanning source: {}", source.path.display());
+                // Files imported from the same device before are skipped unread
+                let history = ImportHistory::load(&dest_path, &source.device(&config))?;
+                let mut known = 0;
+                let mut items = scan_directory_skipping(&source.path, &filter, |relative, meta| {
+                    let skip = !rescan
+                        && modified_time(meta).is_some_and(|modified| {
+                            history.is_known(relative, meta.len(), modified)
+                        });
+                    known += usize::from(skip);
+                    skip
+                })
+                .context("Failed to scan source directory")?;
+                if let Some(last) = history.last_import {
+                    println!(
+                        "{}: {} new {} since {} ({} imported before)",
+                        history.device,
+                        items.len(),
+                        if items.len() == 1 { "file" } else { "files" },
+                        last.format("%Y-%m-%d"),
+                        known
+                    );
+                }
+                known_files += known;
+                // Recorded once the ingest completes; stat now, --move removes the files
+                let imported = imported_files(&items, &source.path);
+                imports.push((history, imported));
+                // Correct camera clocks before anything depends on the timestamps
+                shifted += apply_time_shifts(&mut items, &time_shift);
+                // Profile time zones depend on the source's volume, so convert per source
+                converted += apply_device_timezones(&mut items, &config, source.volume.as_deref());
+                source_items.extend(items);
             }
 
-            // Scan source directory
-            println!("Scanning source: {}", source);
-            let source_items =
-                scan_directory(&source_path).context("Failed to scan source directory")?;
+            // The same photo on two cards is only ingested once
+            let merged = dedupe_by_hash(&mut source_items);
+            if merged > 0 {
+                println!(
+                    "Ignoring {} {} found more than once across the sources",
+                    merged,
+                    if merged == 1 { "copy" } else { "copies" }
+                );
+            }
 
             if source_items.is_empty() {
-                println!("No media files found in source directory");
+                if known_files > 0 {
+                    println!("No new media files since the last import");
+                } else {
+                    println!("No media files found in source directory");
+                }
                 return Ok(());
             }
 
@@ -181,129 +1278,329 @@ fn main() -> Result<()> {
                 video_count
             );
 
+            if !time_shift.is_empty() {
+                println!(
+                    "Applied clock correction to {} {}",
+                    shifted,
+                    if shifted == 1 { "file" } else { "files" }
+                );
+            }
+            report_profiles(&config, &source_items, &sources);
+            if converted > 0 {
+                println!(
+                    "Converted {} {} to the archive time zone ({})",
+                    converted,
+                    if converted == 1 { "file" } else { "files" },
+                    config.timezone.map(|zone| zone.name()).unwrap_or_default()
+                );
+            }
+
+            // Check the destination before asking anything; content already
+            // archived isn't copied again, so it needs no space
+            let archived = if dry_run {
+                None
+            } else {
+                let archived = archive_index(&dest_path);
+                let copies: Vec<(PathBuf, u64)> = source_items
+                    .iter()
+                    .filter(|item| !archived.contains_key(&item.hash))
+                    .map(|item| (item.path.clone(), item.size))
+                    .collect();
+                ingest::check_destination(&dest_path, &copies, link.into())?;
+                Some(archived)
+            };
+
+            // Places for batch suggestions and sidecars; only needed for a real run
+            let places = if dry_run {
+                None
+            } else {
+                Some(Gazetteer::load_default()?)
+            };
+
             // Decide on batching strategy based on --batch-name flag
-            let batches_with_names: Vec<(TemporalBatch, String)> = if let Some(ref single_name) =
-                batch_name
+            let batches_with_names: Vec<(_, BatchName)> = if let Some(ref single_name) = batch_name
             {
                 // User provided single batch name - treat all files as one batch
                 // Temporal batching is disabled when --batch-name is provided
                 println!("Using single batch name for all files (temporal batching disabled)");
 
                 // Create single batch with all items
-                let all_batch = TemporalBatch {
-                    start_time: source_items
-                        .iter()
-                        .filter_map(|i| i.timestamp)
-                        .min()
-                        .unwrap_or_else(Utc::now),
-                    end_time: source_items
-                        .iter()
-                        .filter_map(|i| i.timestamp)
-                        .max()
-                        .unwrap_or_else(Utc::now),
-                    items: source_items.clone(),
-                };
+                let all_batch = batch_from_items(source_items.clone());
                 vec![(all_batch, single_name.clone())]
             } else {
                 // No batch name provided - detect temporal batches for interactive naming
-                let gap_threshold_duration = Duration::seconds((gap_threshold * 3600.0) as i64);
-                let batches = group_by_temporal_proximity(&source_items, gap_threshold_duration);
+                let strategy = build_batching_strategy(
+                    batching,
+                    &BatchingOptions {
+                        gap_threshold,
+                        adaptive_factor,
+                        min_gap,
+                        max_gap,
+                        gps_distance,
+                    },
+                );
+                let batches = strategy.group(&source_items);
+                let undated = undated_items(&source_items);
 
-                if batches.is_empty() {
-                    vec![]
-                } else {
+                if !batches.is_empty() {
                     let batch_plural = if batches.len() == 1 {
                         "batch"
                     } else {
                         "batches"
                     };
                     println!(
-                        "Detected {} temporal {} (gap threshold: {:.1} hours)",
+                        "Detected {} temporal {} ({})",
                         batches.len(),
                         batch_plural,
-                        gap_threshold
+                        strategy.describe()
+                    );
+                }
+                if !undated.is_empty() {
+                    println!(
+                        "Found {} undated {} (no capture timestamp)",
+                        undated.len(),
+                        if undated.len() == 1 { "file" } else { "files" }
                     );
+                }
 
-                    if dry_run {
-                        // In dry-run mode, skip interactive prompts, use placeholder names
-                        batches
-                            .into_iter()
-                            .enumerate()
-                            .map(|(i, batch)| (batch, format!("batch-{}", i + 1)))
-                            .collect()
-                    } else {
-                        // Interactive prompts for batch naming
-                        let total_batches = batches.len();
-                        let mut batches_with_names = Vec::new();
-                        for (i, batch) in batches.into_iter().enumerate() {
-                            let name = prompt_for_batch_name(i + 1, total_batches, &batch)?;
-                            batches_with_names.push((batch, name));
-                        }
+                // Undated files form one extra batch at the end
+                let total_batches = batches.len() + usize::from(!undated.is_empty());
+                let mut batches_with_names = Vec::new();
+
+                if dry_run {
+                    // In dry-run mode, skip interactive prompts, use placeholder names
+                    for (i, batch) in batches.into_iter().enumerate() {
+                        batches_with_names
+                            .push((batch, BatchName::plain(format!("batch-{}", i + 1))));
+                    }
+                    if !undated.is_empty() {
                         batches_with_names
+                            .push((batch_from_items(undated), BatchName::plain("undated")));
+                    }
+                } else {
+                    // Interactive prompts for batch naming, with suggestions from the archive
+                    let suggester = NameSuggester::load(&dest_path)?;
+                    for (i, batch) in batches.into_iter().enumerate() {
+                        let name = prompt_for_batch_name(
+                            i + 1,
+                            total_batches,
+                            &batch,
+                            &suggester,
+                            places.as_ref(),
+                        )?;
+                        batches_with_names.push((batch, name));
+                    }
+                    if !undated.is_empty() {
+                        let resolution = prompt_for_undated_resolution(&undated)?;
+                        let resolved = resolve_undated(&undated, &source_items, &resolution);
+                        let batch = batch_from_items(resolved);
+                        let name = prompt_for_batch_name(
+                            total_batches,
+                            total_batches,
+                            &batch,
+                            &suggester,
+                            places.as_ref(),
+                        )?;
+                        batches_with_names.push((batch, name));
                     }
                 }
+                batches_with_names
             };
 
-            if !dry_run {
+            if let Some(archived) = archived {
                 // Create destination directory if it doesn't exist
                 fs::create_dir_all(&dest_path).context("Failed to create destination directory")?;
 
-                // Scan destination to check for duplicates
-                let dest_items = scan_directory(&dest_path).unwrap_or_default();
-                let dest_hashes: HashMap<_, _> = dest_items
-                    .iter()
-                    .map(|item| (item.hash, &item.path))
-                    .collect();
-
-                // Copy files from each batch
-                let mut copied = 0;
-                let mut skipped = 0;
-
-                for (batch, batch_name) in &batches_with_names {
-                    for item in &batch.items {
-                        // Generate destination filename with batch name
-                        let timestamp = item.timestamp.unwrap_or_else(|| {
-                            // Fallback to modified date if no timestamp
-                            std::fs::metadata(&item.path)
-                                .and_then(|m| m.modified())
-                                .map(|t| t.into())
-                                .unwrap_or_else(|_| Utc::now())
-                        });
-                        let extension = item
-                            .path
-                            .extension()
-                            .and_then(|e| e.to_str())
-                            .unwrap_or("jpg");
-                        let dest_filename = generate_filename(timestamp, batch_name, extension);
-
-                        // Create date-based folder structure
-                        let dest_folder = dest_path.join(&item.folder_path);
-                        fs::create_dir_all(&dest_folder)
-                            .context("Failed to create date-based folder")?;
-
-                        let dest_file = dest_folder.join(&dest_filename);
-
-                        // Check if already exists in destination
-                        if dest_hashes.contains_key(&item.hash) {
-                            skipped += 1;
-                            continue;
-                        }
-
-                        // Copy file
-                        fs::copy(&item.path, &dest_file)
-                            .context(format!("Failed to copy {:?}", dest_filename))?;
-                        copied += 1;
-                    }
+                let files = IngestPlanner {
+                    archive: &dest_path,
+                    template: &template,
+                    config: &config,
+                    sources: &sources,
+                    time_shift: &time_shift,
+                    places: places.as_ref(),
+                    archived: &archived,
                 }
+                .plan(&batches_with_names);
 
-                println!("\nCopied {} files", copied);
-                if skipped > 0 {
-                    println!("Skipped {} duplicate files", skipped);
+                let mut journal = Journal::create(
+                    &dest_path,
+                    IngestPlan {
+                        source: roots[0].clone(),
+                        extra_sources: roots[1..].to_vec(),
+                        link: link.into(),
+                        move_sources,
+                        files,
+                    },
+                )?;
+                ingest::execute(&mut journal, &dest_path)?;
+
+                for (mut history, imported) in imports {
+                    for (relative, file) in imported {
+                        history.record(relative, file);
+                    }
+                    history.last_import = Some(Utc::now());
+                    history.save(&dest_path)?;
                 }
             }
 
             Ok(())
         }
+        Commands::Init { archive, template } => init::run(&PathBuf::from(archive), template),
+        Commands::Retime {
+            archive,
+            shift,
+            camera,
+            batch,
+            dry_run,
+            force,
+        } => {
+            let archive = PathBuf::from(archive);
+            if !dry_run {
+                require_archive(&archive, force)?;
+            }
+            retime::run(&archive, shift, camera, batch, dry_run)
+        }
+        Commands::RenameBatch {
+            archive,
+            from,
+            to,
+            title,
+            date_range,
+            dry_run,
+            force,
+        } => {
+            let archive = PathBuf::from(archive);
+            if !dry_run {
+                require_archive(&archive, force)?;
+            }
+            rename_batch::run(&archive, &from, &to, title.as_deref(), date_range, dry_run)
+        }
+        Commands::Reorganize {
+            archive,
+            into,
+            batch_from_folders,
+            batch_name,
+            gap_threshold,
+            template,
+            dry_run,
+            rollback,
+            force,
+        } => {
+            if let Some(manifest) = rollback {
+                return reorganize::rollback(&manifest);
+            }
+            let into = into.unwrap_or_default();
+            if !dry_run {
+                require_archive(Path::new(&into), force)?;
+            }
+            let naming = match batch_name {
+                Some(name) => reorganize::BatchNaming::Single(name),
+                None if batch_from_folders => reorganize::BatchNaming::Folders,
+                None => {
+                    let config = FolioConfig::load_layered(Path::new(&into))?;
+                    reorganize::BatchNaming::Prompt {
+                        gap_threshold: hours_to_duration(
+                            gap_threshold
+                                .or(config.gap_threshold)
+                                .unwrap_or(DEFAULT_GAP_HOURS),
+                        ),
+                    }
+                }
+            };
+            reorganize::run(
+                &PathBuf::from(archive.unwrap_or_default()),
+                &PathBuf::from(into),
+                naming,
+                template,
+                dry_run,
+            )
+        }
+        Commands::Watch {
+            source,
+            dest,
+            batch_name,
+            poll,
+            interval,
+            log,
+            force,
+        } => {
+            if let Some(name) = &batch_name {
+                validate_batch_name(name).context("Invalid batch name")?;
+            }
+            let dest = dest
+                .map(PathBuf::from)
+                .or(FolioConfig::load_user()?.destination)
+                .context("No destination given: pass --dest or set `destination` in folio.toml")?;
+            require_archive(&dest, force)?;
+            watch::run(watch::WatchOptions {
+                source: PathBuf::from(source),
+                dest,
+                batch_name,
+                poll,
+                interval: std::time::Duration::from_secs(interval.max(1)),
+                log,
+            })
+        }
+        Commands::WatchMounts {
+            dest,
+            mount_root,
+            interval,
+        } => {
+            let config = FolioConfig::load_user()?;
+            mounts::watch(
+                dest.as_deref(),
+                &mounts::mount_roots(mount_root, &config),
+                std::time::Duration::from_secs(interval),
+            )
+        }
+        Commands::Undo {
+            archive,
+            ingest_id,
+            dry_run,
+            force,
+        } => {
+            let archive = PathBuf::from(archive);
+            if !dry_run {
+                require_archive(&archive, force)?;
+            }
+            undo::run(&archive, ingest_id.as_deref(), dry_run)
+        }
+        Commands::Verify { archive, sample } => {
+            let sample = sample.unwrap_or(Sample::All);
+            match S3Location::parse(&archive) {
+                Some(location) => verify::run_s3(location, sample),
+                None => verify::run(&PathBuf::from(archive), sample),
+            }
+        }
+        Commands::Replicate {
+            from,
+            to,
+            delete,
+            dry_run,
+            part_size,
+            force,
+        } => {
+            // Replicating the wrong folder with --delete would empty the backup
+            if !dry_run {
+                require_archive(Path::new(&from), force)?;
+            }
+            match S3Location::parse(&to) {
+                Some(location) => {
+                    if delete {
+                        anyhow::bail!(
+                        "--delete is not supported for S3 targets; objects are stored by content and kept"
+                    );
+                    }
+                    replicate::run_s3(
+                        &PathBuf::from(from),
+                        location,
+                        part_size.unwrap_or(DEFAULT_PART_SIZE),
+                        dry_run,
+                    )
+                }
+                None => replicate::run(&PathBuf::from(from), &PathBuf::from(to), delete, dry_run),
+            }
+        }
         Commands::Dedupe { archive, dry_run } => {
             println!("Finding duplicates in {}", archive);
             if dry_run {
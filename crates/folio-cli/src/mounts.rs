@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use folio_core::fs_ops::{find_camera_volumes, CameraVolume, DEFAULT_MOUNT_ROOTS};
+use folio_core::FolioConfig;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+/// Directories to search for camera media: the flag, then `mount_root` in
+/// folio.toml, then the usual Linux mount points
+pub fn mount_roots(flag: Option<PathBuf>, config: &FolioConfig) -> Vec<PathBuf> {
+    match flag.or_else(|| config.mount_root.clone()) {
+        Some(root) => vec![root],
+        None => DEFAULT_MOUNT_ROOTS.iter().map(PathBuf::from).collect(),
+    }
+}
+
+/// Mounted camera media for `ingest --auto`, failing when there is none
+pub fn detect(roots: &[PathBuf]) -> Result<Vec<CameraVolume>> {
+    let volumes = find_camera_volumes(roots);
+    if volumes.is_empty() {
+        anyhow::bail!(
+            "No camera media (a volume with a DCIM folder) found under {}",
+            describe(roots)
+        );
+    }
+    for volume in &volumes {
+        println!(
+            "Found camera media: {} ({})",
+            volume.label,
+            volume.dcim.display()
+        );
+    }
+    Ok(volumes)
+}
+
+/// Start an ingest session each time camera media is mounted
+///
+/// Volumes already mounted when watching starts are left alone; a volume that
+/// is ejected and plugged in again starts a new session.
+pub fn watch(dest: Option<&str>, roots: &[PathBuf], interval: Duration) -> Result<()> {
+    let folio = std::env::current_exe().context("Failed to locate the folio executable")?;
+    let mut known: BTreeSet<PathBuf> = find_camera_volumes(roots)
+        .into_iter()
+        .map(|volume| volume.dcim)
+        .collect();
+
+    println!(
+        "Watching {} for camera media (Ctrl-C to stop)",
+        describe(roots)
+    );
+    for dcim in &known {
+        println!(
+            "Already mounted: {} (ingest it with: folio ingest --auto)",
+            dcim.display()
+        );
+    }
+
+    loop {
+        std::thread::sleep(interval);
+
+        let volumes = find_camera_volumes(roots);
+        known.retain(|dcim| volumes.iter().any(|volume| &volume.dcim == dcim));
+
+        for volume in volumes {
+            if !known.insert(volume.dcim.clone()) {
+                continue;
+            }
+            println!(
+                "\n📷 {} mounted, starting ingest of {}",
+                volume.label,
+                volume.dcim.display()
+            );
+
+            // Each session runs as its own ingest, so a failure doesn't stop the watcher
+            let mut ingest = Command::new(&folio);
+            ingest.arg("ingest").arg("--source").arg(&volume.dcim);
+            if let Some(dest) = dest {
+                ingest.arg("--dest").arg(dest);
+            }
+            match ingest.status() {
+                Ok(status) if status.success() => {
+                    println!("✅ Finished ingest of {}", volume.label)
+                }
+                Ok(_) => eprintln!("❌ Ingest of {} failed", volume.label),
+                Err(e) => eprintln!("❌ Could not start ingest of {}: {}", volume.label, e),
+            }
+        }
+    }
+}
+
+fn describe(roots: &[PathBuf]) -> String {
+    roots
+        .iter()
+        .map(|root| root.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,183 @@
+use anyhow::{Context, Result};
+use folio_core::fs_ops::prune_empty_parents;
+use folio_core::{
+    apply_batch_rename, dequeue_rename, plan_batch_rename, relocate_in_manifests,
+    revert_batch_rename, sidecar_path, DateRange, FolioConfig, RenameMove, XmpDocument, XmpField,
+};
+use folio_ingest::{relocate_in_catalog, retitle_in_catalog, Retitle};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Rename a batch across the archive, including sidecars and ingest journals
+///
+/// Files that have a title (dc:title) get `title`, or else the new name.
+pub fn run(
+    archive: &Path,
+    from: &str,
+    to: &str,
+    title: Option<&str>,
+    date_range: Option<DateRange>,
+    dry_run: bool,
+) -> Result<()> {
+    println!(
+        "Renaming batch '{}' to '{}' in {}",
+        from,
+        to,
+        archive.display()
+    );
+    if dry_run {
+        println!("Dry run mode - no files will be renamed\n");
+    }
+
+    // Files are found by the archive's layout, batches named by a profile included
+    let config = FolioConfig::load_layered(archive)?;
+    let template = config.template.clone().unwrap_or_default();
+    let prefixes: Vec<&str> = config
+        .profiles
+        .iter()
+        .filter_map(|profile| profile.prefix.as_deref())
+        .collect();
+    let moves = plan_batch_rename(archive, &template, &prefixes, from, to, date_range)?;
+    if moves.is_empty() {
+        anyhow::bail!(
+            "No files of batch '{}' found in the layout {}",
+            from,
+            template.as_str()
+        );
+    }
+
+    let relative = |path: &Path| path.strip_prefix(archive).unwrap_or(path).to_path_buf();
+    for m in &moves {
+        println!(
+            "  {} -> {}",
+            relative(&m.from).display(),
+            relative(&m.to).display()
+        );
+    }
+
+    let plural = if moves.len() == 1 { "file" } else { "files" };
+    if dry_run {
+        println!("\nWould rename {} {}", moves.len(), plural);
+        return Ok(());
+    }
+
+    apply_batch_rename(&moves)?;
+
+    // The journals must follow, otherwise undo and resume would look in the old places
+    let catalog_moves: Vec<_> = moves
+        .iter()
+        .map(|m| {
+            (
+                relative(&m.from),
+                relative(&m.to),
+                Some(m.new_batch.clone()),
+            )
+        })
+        .collect();
+    if let Err(e) = relocate_in_catalog(archive, &catalog_moves) {
+        restore(archive, &moves)?;
+        return Err(e.context("Failed to update the catalog; no files were renamed"));
+    }
+
+    let manifest_moves: Vec<(PathBuf, PathBuf)> = catalog_moves
+        .iter()
+        .map(|(from, to, _)| (from.clone(), to.clone()))
+        .collect();
+    let result = relocate_in_manifests(archive, &manifest_moves)
+        .context("Failed to update the manifests")
+        .and_then(|_| {
+            retitle(archive, &moves, title.unwrap_or(to)).context("Failed to update the titles")
+        });
+    if let Err(e) = result {
+        // Manifests written before the failure are moved back too
+        let back: Vec<(PathBuf, PathBuf)> = manifest_moves
+            .iter()
+            .map(|(from, to)| (to.clone(), from.clone()))
+            .collect();
+        relocate_in_manifests(archive, &back).context("Failed to restore the manifests")?;
+        let catalog_back: Vec<_> = moves
+            .iter()
+            .map(|m| {
+                (
+                    relative(&m.to),
+                    relative(&m.from),
+                    Some(m.old_batch.clone()),
+                )
+            })
+            .collect();
+        relocate_in_catalog(archive, &catalog_back).context("Failed to restore the catalog")?;
+        restore(archive, &moves)?;
+        return Err(e.context("No files were renamed"));
+    }
+    for m in &moves {
+        if let Some(parent) = m.from.parent() {
+            prune_empty_parents(parent, archive)?;
+        }
+    }
+
+    println!("\nRenamed {} {}", moves.len(), plural);
+
+    // A placeholder from `folio watch` has its name now
+    if date_range.is_none() && dequeue_rename(archive, from)? {
+        println!("Removed '{}' from the rename queue", from);
+    }
+    Ok(())
+}
+
+/// Give renamed files that have a title the new one, in their sidecars and
+/// the journals
+///
+/// All or nothing: if a sidecar or journal can't be updated, the sidecars
+/// already rewritten are put back.
+fn retitle(archive: &Path, moves: &[RenameMove], title: &str) -> Result<()> {
+    let mut rewritten: Vec<(PathBuf, String)> = Vec::new();
+
+    let result = (|| -> Result<()> {
+        let mut titles = Vec::new();
+        for m in moves {
+            let sidecar = sidecar_path(&m.to);
+            let mut hashes = None;
+            // A sidecar folio can't read has no title it wrote
+            let xmp = match fs::read_to_string(&sidecar) {
+                Ok(text) => XmpDocument::parse(&text).ok(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", sidecar)),
+            };
+            if let Some(mut xmp) = xmp.filter(|xmp| xmp.get(XmpField::Title).is_some()) {
+                let old = xmp.as_str().to_string();
+                let old_hash = blake3::hash(old.as_bytes());
+                xmp.set(XmpField::Title, title);
+                rewritten.push((sidecar.clone(), old));
+                xmp.save(&sidecar)?;
+                hashes = Some((old_hash, blake3::hash(xmp.as_str().as_bytes())));
+            }
+            titles.push(Retitle {
+                archived: m.to.strip_prefix(archive).unwrap_or(&m.to).to_path_buf(),
+                title: title.to_string(),
+                sidecar: hashes,
+            });
+        }
+        retitle_in_catalog(archive, &titles)?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        for (sidecar, text) in rewritten.iter().rev() {
+            if let Err(e) = fs::write(sidecar, text) {
+                tracing::error!("Failed to restore {:?}: {}", sidecar, e);
+            }
+        }
+    }
+    result
+}
+
+/// Put renamed files back, dropping batch folders the rename created
+fn restore(archive: &Path, moves: &[RenameMove]) -> Result<()> {
+    revert_batch_rename(moves).context("Failed to restore renamed files")?;
+    for m in moves {
+        if let Some(parent) = m.to.parent() {
+            prune_empty_parents(parent, archive)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,288 @@
+use crate::{batch_from_items, highest_seq, prompt_for_batch_name, unused, BatchName};
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use folio_core::{
+    apply_reorganize, check_reorganize, get_file_modified_date, rollback_reorganize,
+    scan_directory, scan_directory_filtered, sidecar_path, slugify_batch_name, undated_items,
+    update_manifests, validate_batch_name, write_reorganize_manifest, BatchingStrategy, FixedGap,
+    FolioConfig, Gazetteer, MediaItem, NameSuggester, PathTemplate, ReorganizeMove,
+    TemplateContext, TemporalBatch, XmpDocument, XmpField,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How reorganized files get their batch names
+pub enum BatchNaming {
+    /// From the legacy parent folder name, slugified
+    Folders,
+    /// One name for everything
+    Single(String),
+    /// Temporal batches, named interactively
+    Prompt { gap_threshold: Duration },
+}
+
+/// Move a legacy tree into the folio layout
+pub fn run(
+    archive: &Path,
+    into: &Path,
+    naming: BatchNaming,
+    template: Option<PathTemplate>,
+    dry_run: bool,
+) -> Result<()> {
+    if let BatchNaming::Single(name) = &naming {
+        validate_batch_name(name).context("Invalid batch name")?;
+    }
+    let config = FolioConfig::load_layered(into)?;
+    let filter = config.scan.filter(&[], &[])?;
+    let template = template.or(config.template).unwrap_or_default();
+
+    println!("Reorganizing {} into {}", archive.display(), into.display());
+    if dry_run {
+        println!("Dry run mode - no files will be moved\n");
+    }
+
+    let mut items = scan_directory_filtered(archive, &filter).context("Failed to scan archive")?;
+    // Files already in the target layout (when it lives inside the tree) stay put
+    if into != archive && into.starts_with(archive) {
+        items.retain(|item| !item.path.starts_with(into));
+    }
+    if items.is_empty() {
+        println!("No media files found");
+        return Ok(());
+    }
+    println!("Found {} media files", items.len());
+
+    let batches = name_batches(archive, into, items, &naming, dry_run)?;
+
+    // Content already in the target layout is left where it is
+    let mut seen: HashMap<_, PathBuf> = if into.exists() && into != archive {
+        scan_directory(into)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| (item.hash, item.path))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    // Names already taken, in the target or earlier in the plan, get a
+    // counter the way ingest does
+    let mut moves = Vec::new();
+    let mut hashes = Vec::new();
+    let mut titles = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut taken = HashSet::new();
+    for (batch, name) in &batches {
+        // {seq} carries on after the batch's files already in their folders
+        let first_seq = if template.has_seq() {
+            batch
+                .items
+                .iter()
+                .filter_map(|item| {
+                    let folder = destination(&template, item, &name.slug, 1);
+                    let folder = folder.parent()?;
+                    Some(highest_seq(into, &template, folder, &name.slug, &taken))
+                })
+                .max()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+
+        for (seq, item) in batch.items.iter().enumerate() {
+            let relative = destination(&template, item, &name.slug, first_seq + seq + 1);
+            if into.join(&relative) == item.path {
+                continue;
+            }
+            if let Some(original) = seen.get(&item.hash) {
+                duplicates.push((item.path.clone(), original.clone()));
+                continue;
+            }
+            let to = into.join(unused(into, relative, &mut taken));
+            seen.insert(item.hash, to.clone());
+            hashes.push(item.hash);
+            titles.push(name.title.as_ref());
+            moves.push(ReorganizeMove {
+                from: item.path.clone(),
+                to,
+            });
+        }
+    }
+
+    // The mapping report
+    println!();
+    for m in &moves {
+        println!(
+            "  {} -> {}",
+            m.from.strip_prefix(archive).unwrap_or(&m.from).display(),
+            m.to.strip_prefix(into).unwrap_or(&m.to).display()
+        );
+    }
+    for (duplicate, original) in &duplicates {
+        println!(
+            "  {} (duplicate of {}, left in place)",
+            duplicate
+                .strip_prefix(archive)
+                .unwrap_or(duplicate)
+                .display(),
+            original.display()
+        );
+    }
+
+    check_reorganize(&moves)?;
+
+    let plural = if moves.len() == 1 { "file" } else { "files" };
+    if dry_run {
+        println!("\nWould move {} {}", moves.len(), plural);
+        if !duplicates.is_empty() {
+            println!("Would leave {} duplicate files in place", duplicates.len());
+        }
+        return Ok(());
+    }
+    if moves.is_empty() {
+        println!("\nNothing to move");
+        return Ok(());
+    }
+
+    fs::create_dir_all(into).context("Failed to create destination directory")?;
+    // Titles typed at the prompt go in the sidecars (dc:title), written before
+    // the manifest so the sidecars move, and roll back, with their files
+    for (m, title) in moves.iter().zip(&titles) {
+        if let Some(title) = title {
+            let sidecar = sidecar_path(&m.from);
+            let mut xmp = XmpDocument::load_or_default(&sidecar)?;
+            xmp.set(XmpField::Title, title);
+            xmp.save(&sidecar)?;
+        }
+    }
+    let manifest = write_reorganize_manifest(into, &moves)?;
+    apply_reorganize(archive, &moves)?;
+
+    // Verify checks the moved files against the hashes taken by the scan
+    let added: Vec<_> = moves
+        .iter()
+        .zip(&hashes)
+        .filter_map(|(m, hash)| Some((m.to.strip_prefix(into).ok()?.to_path_buf(), *hash)))
+        .collect();
+    let removed: Vec<_> = moves
+        .iter()
+        .filter_map(|m| m.from.strip_prefix(into).ok().map(Path::to_path_buf))
+        .collect();
+    update_manifests(into, &added, &removed)?;
+
+    println!("\nMoved {} {}", moves.len(), plural);
+    if !duplicates.is_empty() {
+        println!("Left {} duplicate files in place", duplicates.len());
+    }
+    println!(
+        "Rollback manifest: {} (undo with: folio reorganize --rollback {})",
+        manifest.display(),
+        manifest.display()
+    );
+    Ok(())
+}
+
+/// Put files back where a reorganize manifest says they came from
+pub fn rollback(manifest: &Path) -> Result<()> {
+    let restored = rollback_reorganize(manifest)?;
+    println!(
+        "Moved {} {} back",
+        restored,
+        if restored == 1 { "file" } else { "files" }
+    );
+    Ok(())
+}
+
+/// Group the files and decide every batch's name
+fn name_batches(
+    archive: &Path,
+    into: &Path,
+    items: Vec<MediaItem>,
+    naming: &BatchNaming,
+    dry_run: bool,
+) -> Result<Vec<(TemporalBatch, BatchName)>> {
+    match naming {
+        BatchNaming::Single(name) => Ok(vec![(batch_from_items(items), BatchName::plain(name))]),
+        BatchNaming::Folders => {
+            let mut by_folder: BTreeMap<PathBuf, Vec<MediaItem>> = BTreeMap::new();
+            for item in items {
+                let folder = item.path.parent().unwrap_or(archive).to_path_buf();
+                by_folder.entry(folder).or_default().push(item);
+            }
+            Ok(by_folder
+                .into_iter()
+                .map(|(folder, items)| {
+                    let slug = slugify_batch_name(
+                        &folder.file_name().unwrap_or_default().to_string_lossy(),
+                    );
+                    let name = if folder == archive || slug.is_empty() {
+                        "unsorted".to_string()
+                    } else {
+                        slug
+                    };
+                    (batch_from_items(items), BatchName::plain(name))
+                })
+                .collect())
+        }
+        BatchNaming::Prompt { gap_threshold } => {
+            let strategy = FixedGap {
+                gap_threshold: *gap_threshold,
+            };
+            let mut batches = strategy.group(&items);
+            let undated = undated_items(&items);
+            if !undated.is_empty() {
+                batches.push(batch_from_items(undated));
+            }
+            println!(
+                "Detected {} temporal batches ({})",
+                batches.len(),
+                strategy.describe()
+            );
+
+            let (suggester, places) = if dry_run {
+                (NameSuggester::default(), None)
+            } else {
+                (NameSuggester::load(into)?, Some(Gazetteer::load_default()?))
+            };
+            let total = batches.len();
+            batches
+                .into_iter()
+                .enumerate()
+                .map(|(i, batch)| {
+                    let name = if dry_run {
+                        BatchName::plain(format!("batch-{}", i + 1))
+                    } else {
+                        prompt_for_batch_name(i + 1, total, &batch, &suggester, places.as_ref())?
+                    };
+                    Ok((batch, name))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Where a file goes in the folio layout (relative to it), as ingest would put it
+fn destination(template: &PathTemplate, item: &MediaItem, batch_name: &str, seq: usize) -> PathBuf {
+    // Undated files are still named after their modification time
+    let timestamp = item
+        .timestamp
+        .or_else(|| get_file_modified_date(&item.path).ok())
+        .unwrap_or_else(Utc::now);
+    let relative = template.render(&TemplateContext {
+        timestamp,
+        batch_name,
+        seq,
+        camera: item.camera.as_ref(),
+        original_path: &item.path,
+        media_type: &item.media_type,
+    });
+
+    // Undated files keep the template's filename under unknown-date
+    if item.timestamp.is_some() {
+        relative
+    } else {
+        item.folder_path
+            .join(relative.file_name().unwrap_or_default())
+    }
+}
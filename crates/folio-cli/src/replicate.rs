@@ -0,0 +1,202 @@
+use crate::read_line;
+use anyhow::{Context, Result};
+use folio_ingest::{
+    delete_replica, plan_replication, plan_store_upload, remove_partial_copies, replicate_file,
+    ReplicaRecord, ReplicationPlan, S3Location, S3Store,
+};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Mirror an archive to a second root by content hash
+///
+/// New and changed files are copied and verified; anything that differs in a
+/// way the archive can't explain is reported, never overwritten. Files deleted
+/// from the archive are only removed from the target with `delete` and a "y"
+/// at the prompt. Re-running after an interruption carries on where it stopped.
+pub fn run(archive: &Path, target: &Path, delete: bool, dry_run: bool) -> Result<()> {
+    if !archive.is_dir() {
+        anyhow::bail!("Archive not found: {}", archive.display());
+    }
+    println!("Replicating {} to {}", archive.display(), target.display());
+    if dry_run {
+        println!("Dry run mode - nothing will be copied or removed\n");
+    } else {
+        fs::create_dir_all(target)
+            .with_context(|| format!("Failed to create {}", target.display()))?;
+        let partial = remove_partial_copies(target)?;
+        if partial > 0 {
+            println!(
+                "Removed {} unfinished {} from an interrupted run",
+                partial,
+                if partial == 1 { "copy" } else { "copies" }
+            );
+        }
+    }
+
+    let mut record = ReplicaRecord::load(archive, target)?;
+    let plan = plan_replication(archive, target, &record)?;
+
+    for copy in &plan.copies {
+        let action = if copy.update { "update" } else { "copy" };
+        println!("  {:<7}{}", action, copy.path.display());
+    }
+    for path in &plan.changed_on_target {
+        println!("  ⚠️  changed on target, kept: {}", path.display());
+    }
+    for path in &plan.only_on_target {
+        println!("  ⚠️  only on target: {}", path.display());
+    }
+    for path in &plan.deleted_from_source {
+        println!("  ⚠️  deleted from archive: {}", path.display());
+    }
+
+    let plural = |count: usize| if count == 1 { "file" } else { "files" };
+    if dry_run {
+        println!(
+            "\nWould copy {} {} ({} unchanged)",
+            plan.copies.len(),
+            plural(plan.copies.len()),
+            plan.unchanged
+        );
+        print_drift(&plan);
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for copy in &plan.copies {
+        match replicate_file(archive, target, copy) {
+            Ok(()) => record.record_copied(&copy.path, copy.hash)?,
+            Err(e) => {
+                eprintln!("❌ {:#}", e);
+                failed += 1;
+            }
+        }
+    }
+    for (path, hash) in &plan.adopt {
+        record.record_copied(path, *hash)?;
+    }
+
+    let mut deleted = 0;
+    if delete && !plan.deleted_from_source.is_empty() {
+        print!(
+            "Remove {} {} from {} that were deleted from the archive? [y/N]: ",
+            plan.deleted_from_source.len(),
+            plural(plan.deleted_from_source.len()),
+            target.display()
+        );
+        io::stdout().flush()?;
+        if read_line()?.eq_ignore_ascii_case("y") {
+            for path in &plan.deleted_from_source {
+                delete_replica(target, path)?;
+                record.record_deleted(path)?;
+                deleted += 1;
+            }
+        }
+    }
+
+    let copied = plan.copies.len() - failed;
+    println!(
+        "\nCopied and verified {} {} ({} unchanged)",
+        copied,
+        plural(copied),
+        plan.unchanged
+    );
+    if deleted > 0 {
+        println!("Removed {} {} from the target", deleted, plural(deleted));
+    }
+    print_drift(&plan);
+
+    if failed > 0 {
+        anyhow::bail!(
+            "{} {} could not be replicated; re-run to continue",
+            failed,
+            plural(failed)
+        );
+    }
+    Ok(())
+}
+
+/// Upload an archive to S3-compatible storage, one object per content hash
+///
+/// Content the store already holds is skipped, so re-running after an
+/// interruption only sends what is missing. The store's manifest is rewritten
+/// at the end to list the archive as it is now; objects are never deleted.
+pub fn run_s3(archive: &Path, location: S3Location, part_size: u64, dry_run: bool) -> Result<()> {
+    if !archive.is_dir() {
+        anyhow::bail!("Archive not found: {}", archive.display());
+    }
+    println!("Replicating {} to {}", archive.display(), location);
+    if dry_run {
+        println!("Dry run mode - nothing will be uploaded\n");
+    }
+
+    let store = S3Store::from_env(location)?.with_part_size(part_size);
+    let plan = plan_store_upload(archive, &store)?;
+    let plural = |count: usize| if count == 1 { "file" } else { "files" };
+    let stored = plan.files.len() - plan.uploads.len();
+
+    if dry_run {
+        for (path, _) in &plan.uploads {
+            println!("  upload {}", path.display());
+        }
+        println!(
+            "\nWould upload {} {} ({} already stored)",
+            plan.uploads.len(),
+            plural(plan.uploads.len()),
+            stored
+        );
+        return Ok(());
+    }
+
+    let mut failed = HashSet::new();
+    for (path, hash) in &plan.uploads {
+        println!("  upload {}", path.display());
+        if let Err(e) = store.upload(&archive.join(path), hash) {
+            eprintln!("❌ {:#}", e);
+            failed.insert(*hash);
+        }
+    }
+
+    let manifest = plan
+        .files
+        .iter()
+        .filter(|(_, hash)| !failed.contains(hash))
+        .cloned()
+        .collect();
+    if manifest != store.read_manifest()? {
+        store.write_manifest(&manifest)?;
+    }
+
+    let uploaded = plan.uploads.len() - failed.len();
+    println!(
+        "\nUploaded and verified {} {} ({} already stored)",
+        uploaded,
+        plural(uploaded),
+        stored
+    );
+    if !failed.is_empty() {
+        anyhow::bail!(
+            "{} {} could not be uploaded; re-run to continue",
+            failed.len(),
+            plural(failed.len())
+        );
+    }
+    Ok(())
+}
+
+fn print_drift(plan: &ReplicationPlan) {
+    if !plan.has_drift() {
+        return;
+    }
+    println!(
+        "Drift: {} changed on target, {} only on target, {} deleted from archive",
+        plan.changed_on_target.len(),
+        plan.only_on_target.len(),
+        plan.deleted_from_source.len()
+    );
+    if !plan.deleted_from_source.is_empty() {
+        println!("Deleted files stay on the target; pass --delete to remove them");
+    }
+}
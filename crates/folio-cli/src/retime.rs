@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use chrono::Duration;
+use folio_core::{apply_retime, format_offset, plan_retime, FolioConfig, RetimeFilter};
+use folio_ingest::relocate_in_catalog;
+use std::path::Path;
+
+/// Shift already-archived files by a clock offset
+pub fn run(
+    archive: &Path,
+    shift: Duration,
+    camera: Option<String>,
+    batch: Option<String>,
+    dry_run: bool,
+) -> Result<()> {
+    let filter = RetimeFilter {
+        camera,
+        batch_name: batch,
+    };
+
+    println!(
+        "Retiming files in {} by {}",
+        archive.display(),
+        format_offset(shift)
+    );
+    if dry_run {
+        println!("Dry run mode - no files will be renamed\n");
+    }
+
+    let template = FolioConfig::load_layered(archive)?
+        .template
+        .unwrap_or_default();
+    let moves = plan_retime(archive, &template, shift, &filter).context("Failed to plan retime")?;
+    if moves.is_empty() {
+        println!("No matching files found");
+        return Ok(());
+    }
+
+    for m in &moves {
+        println!(
+            "  {} -> {}",
+            m.from.strip_prefix(archive).unwrap_or(&m.from).display(),
+            m.to.strip_prefix(archive).unwrap_or(&m.to).display()
+        );
+    }
+
+    let plural = if moves.len() == 1 { "file" } else { "files" };
+    if dry_run {
+        println!("\nWould retime {} {}", moves.len(), plural);
+        return Ok(());
+    }
+
+    apply_retime(archive, &moves).context("Failed to retime files")?;
+
+    // Journals must follow, or undo and verify would look in the old places
+    let catalog_moves: Vec<_> = moves
+        .iter()
+        .filter_map(|m| {
+            Some((
+                m.from.strip_prefix(archive).ok()?.to_path_buf(),
+                m.to.strip_prefix(archive).ok()?.to_path_buf(),
+                m.batch.clone(),
+            ))
+        })
+        .collect();
+    relocate_in_catalog(archive, &catalog_moves).context("Failed to update the catalog")?;
+    println!("\nRetimed {} {}", moves.len(), plural);
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use anyhow::{Context, Result};
+use folio_ingest::{apply_undo, plan_undo, Journal};
+use std::path::Path;
+
+/// Revert an ingest recorded in the archive's journal
+pub fn run(archive: &Path, ingest_id: Option<&str>, dry_run: bool) -> Result<()> {
+    let journals = Journal::list(archive)?;
+    let mut journal = match ingest_id {
+        Some(id) => journals
+            .into_iter()
+            .find(|journal| journal.id() == id)
+            .with_context(|| format!("No ingest '{}' found in {}", id, archive.display()))?,
+        None => journals
+            .into_iter()
+            .rev()
+            .find(|journal| !journal.is_undone())
+            .with_context(|| format!("No ingest to undo in {}", archive.display()))?,
+    };
+
+    println!(
+        "Undoing ingest {} (started {})",
+        journal.id(),
+        journal.started().format("%Y-%m-%d %H:%M:%S")
+    );
+    if dry_run {
+        println!("Dry run mode - no files will be removed\n");
+    }
+
+    let plan = plan_undo(&journal, archive)?;
+    for path in &plan.missing {
+        println!(
+            "  ⚠️  already gone: {}",
+            path.strip_prefix(archive).unwrap_or(path).display()
+        );
+    }
+    for sidecar in &plan.kept {
+        println!(
+            "  ⚠️  keeping {}: it may hold edits made since the ingest",
+            sidecar.strip_prefix(archive).unwrap_or(sidecar).display()
+        );
+    }
+    for action in &plan.actions {
+        let archived = action
+            .archived
+            .strip_prefix(archive)
+            .unwrap_or(&action.archived);
+        match &action.restore_to {
+            Some(original) => println!("  {} -> {}", archived.display(), original.display()),
+            None => println!("  remove {}", archived.display()),
+        }
+    }
+
+    let plural = if plan.actions.len() == 1 {
+        "file"
+    } else {
+        "files"
+    };
+    if dry_run {
+        println!("\nWould undo {} {}", plan.actions.len(), plural);
+        return Ok(());
+    }
+
+    apply_undo(&mut journal, archive, &plan).context("Failed to undo ingest")?;
+    println!("\nUndid {} {}", plan.actions.len(), plural);
+
+    Ok(())
+}
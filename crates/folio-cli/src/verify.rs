@@ -0,0 +1,67 @@
+use anyhow::Result;
+use folio_ingest::{verify_archive, verify_store, FixityReport, S3Location, S3Store, Sample};
+use std::path::Path;
+
+/// Check archived files against the hashes recorded when they were ingested
+pub fn run(archive: &Path, sample: Sample) -> Result<()> {
+    println!("Verifying {}", archive.display());
+
+    let report = verify_archive(archive, sample)?;
+    print_report(&report, "Archive")
+}
+
+/// Check the objects of an S3 replica against the hashes in its manifest
+pub fn run_s3(location: S3Location, sample: Sample) -> Result<()> {
+    println!("Verifying {}", location);
+
+    let store = S3Store::from_env(location)?;
+    let report = verify_store(&store, sample)?;
+    print_report(&report, "Replica")
+}
+
+fn print_report(report: &FixityReport, what: &str) -> Result<()> {
+    for path in &report.corrupted {
+        println!("  ❌ corrupted: {}", path.display());
+    }
+    for path in &report.conflicting {
+        println!("  ❌ recorded with different hashes: {}", path.display());
+    }
+    for path in &report.missing {
+        println!("  ❌ missing: {}", path.display());
+    }
+    for path in &report.unexpected {
+        println!("  ⚠️  unexpected: {}", path.display());
+    }
+
+    let oldest_first = if report.checked < report.recorded {
+        " (least recently verified first)"
+    } else {
+        ""
+    };
+    let conflicting = if report.conflicting.is_empty() {
+        String::new()
+    } else {
+        format!(", {} with conflicting records", report.conflicting.len())
+    };
+    println!(
+        "\nChecked {} of {} recorded {}{}: {} corrupted, {} missing, {} unexpected{}",
+        report.checked,
+        report.recorded,
+        if report.recorded == 1 {
+            "file"
+        } else {
+            "files"
+        },
+        oldest_first,
+        report.corrupted.len(),
+        report.missing.len(),
+        report.unexpected.len(),
+        conflicting
+    );
+
+    if !report.is_clean() {
+        anyhow::bail!("{} verification failed", what);
+    }
+    println!("✓ {} verified", what);
+    Ok(())
+}
@@ -0,0 +1,404 @@
+use crate::{
+    archive_index, batch_from_items, hours_to_duration, ingest, BatchName, IngestPlanner,
+    IngestSource, DEFAULT_GAP_HOURS,
+};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use folio_core::fs_ops::volume_label;
+use folio_core::{
+    apply_device_timezones, dedupe_by_hash, detect_media_type, queue_rename, scan_file,
+    undated_items, BatchHistory, BatchingStrategy, DateRange, FamilyConfig, FixedGap, FolioConfig,
+    Gazetteer, NameSuggester, PathTemplate, ScanFilter, TemporalBatch,
+};
+use folio_ingest::{preflight, space_needed, IngestPlan, Journal, LinkMode};
+use notify::{PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+use walkdir::WalkDir;
+
+/// Where `folio watch` logs to unless told otherwise, relative to the archive
+pub const WATCH_LOG: &str = ".folio/logs/watch.log";
+
+pub struct WatchOptions {
+    pub source: PathBuf,
+    pub dest: PathBuf,
+    /// One name for everything, instead of rules and placeholders
+    pub batch_name: Option<String>,
+    /// Poll instead of relying on filesystem notifications
+    pub poll: bool,
+    /// How often files are checked; a file is stable once unchanged for one interval
+    pub interval: Duration,
+    pub log: Option<PathBuf>,
+}
+
+/// Ingest files appearing in a folder (e.g. a phone sync directory) once they stop changing
+///
+/// Files already there when watching starts are ingested too; anything the
+/// archive already holds is skipped. Batches get `--batch-name`, a name from
+/// their location, holidays or birthdays, or an `inbox-<date>` placeholder
+/// queued for `folio rename-batch`.
+pub fn run(mut options: WatchOptions) -> Result<()> {
+    let root = fs::canonicalize(&options.source)
+        .with_context(|| format!("Source directory not found: {}", options.source.display()))?;
+    fs::create_dir_all(&options.dest).context("Failed to create destination directory")?;
+    // Event paths are canonical; an archive inside the watched folder must match them
+    options.dest = fs::canonicalize(&options.dest)?;
+    let log_path = options
+        .log
+        .clone()
+        .unwrap_or_else(|| options.dest.join(WATCH_LOG));
+
+    let config = FolioConfig::load_layered(&options.dest)?;
+    let mut daemon = Daemon {
+        source: IngestSource {
+            path: root.clone(),
+            volume: volume_label(&root),
+            root: root.clone(),
+        },
+        template: config.template.clone().unwrap_or_default(),
+        gap_threshold: hours_to_duration(config.gap_threshold.unwrap_or(DEFAULT_GAP_HOURS)),
+        // Rules only: the archive's history would hand placeholders back as suggestions
+        suggester: NameSuggester::new(
+            FamilyConfig::load(&options.dest)?.birthdays,
+            BatchHistory::default(),
+        ),
+        places: Some(Gazetteer::load_default()?),
+        filter: config.scan.filter(&[], &[])?,
+        archived: archive_index(&options.dest),
+        log: WatchLog::open(&log_path)?,
+        config,
+        options,
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let _watcher = daemon.start_watcher(tx)?;
+
+    // Whatever is already there counts as new; archived files are skipped later
+    let mut pending: HashMap<PathBuf, Option<(u64, SystemTime)>> = WalkDir::new(&root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && daemon.wants(entry.path()))
+        .map(|entry| (entry.into_path(), None))
+        .collect();
+
+    loop {
+        daemon.collect_events(&rx, &mut pending)?;
+        let stable = take_stable(&mut pending);
+        if stable.is_empty() {
+            continue;
+        }
+        if let Err(e) = daemon.ingest(stable) {
+            daemon.log.write(&format!("Ingest failed: {:#}", e));
+        }
+    }
+}
+
+struct Daemon {
+    options: WatchOptions,
+    source: IngestSource,
+    config: FolioConfig,
+    template: PathTemplate,
+    gap_threshold: chrono::Duration,
+    suggester: NameSuggester,
+    places: Option<Gazetteer>,
+    /// The `[scan]` settings of folio.toml
+    filter: ScanFilter,
+    archived: HashMap<blake3::Hash, PathBuf>,
+    log: WatchLog,
+}
+
+impl Daemon {
+    /// Filesystem notifications where they work, polling otherwise (or when asked)
+    fn start_watcher(
+        &mut self,
+        tx: mpsc::Sender<notify::Result<notify::Event>>,
+    ) -> Result<Box<dyn Watcher>> {
+        let root = &self.source.root;
+        if !self.options.poll {
+            let native = RecommendedWatcher::new(tx.clone(), notify::Config::default()).and_then(
+                |mut watcher| {
+                    watcher.watch(root, RecursiveMode::Recursive)?;
+                    Ok(watcher)
+                },
+            );
+            match native {
+                Ok(watcher) => {
+                    self.log.write(&format!("Watching {}", root.display()));
+                    return Ok(Box::new(watcher));
+                }
+                Err(e) => self.log.write(&format!(
+                    "Filesystem notifications unavailable ({}), polling instead",
+                    e
+                )),
+            }
+        }
+
+        let mut watcher = PollWatcher::new(
+            tx,
+            notify::Config::default().with_poll_interval(self.options.interval),
+        )
+        .context("Failed to start polling")?;
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch {}", root.display()))?;
+        self.log.write(&format!(
+            "Watching {} (polling every {}s)",
+            root.display(),
+            self.options.interval.as_secs()
+        ));
+        Ok(Box::new(watcher))
+    }
+
+    /// Media files outside hidden folders (sync tools keep partial files there),
+    /// excluded folders and the archive itself
+    fn wants(&self, path: &Path) -> bool {
+        let relative = self.relative(path);
+        detect_media_type(path).is_some()
+            && !path.starts_with(&self.options.dest)
+            && !self.filter.excludes(relative)
+            && !relative
+                .components()
+                .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.source.root).unwrap_or(path)
+    }
+
+    /// Gather changed paths for one interval
+    fn collect_events(
+        &mut self,
+        rx: &Receiver<notify::Result<notify::Event>>,
+        pending: &mut HashMap<PathBuf, Option<(u64, SystemTime)>>,
+    ) -> Result<()> {
+        let deadline = Instant::now() + self.options.interval;
+        loop {
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(Ok(event)) => {
+                    for path in event.paths {
+                        if self.wants(&path) {
+                            pending.entry(path).or_insert(None);
+                        }
+                    }
+                }
+                Ok(Err(e)) => self.log.write(&format!("Watch error: {}", e)),
+                Err(RecvTimeoutError::Timeout) => return Ok(()),
+                Err(RecvTimeoutError::Disconnected) => anyhow::bail!("File watcher stopped"),
+            }
+        }
+    }
+
+    /// Ingest settled files as one session
+    fn ingest(&mut self, paths: Vec<PathBuf>) -> Result<()> {
+        let mut items = Vec::new();
+        for path in paths {
+            match scan_file(&path) {
+                Ok(Some(item))
+                    if self.filter.accepts_file(
+                        self.relative(&path),
+                        item.size,
+                        &item.media_type,
+                    ) && self.filter.accepts_date(item.timestamp) =>
+                {
+                    items.push(item)
+                }
+                Ok(_) => {}
+                Err(e) => self
+                    .log
+                    .write(&format!("Skipping {}: {:#}", path.display(), e)),
+            }
+        }
+        dedupe_by_hash(&mut items);
+        items.retain(|item| !self.archived.contains_key(&item.hash));
+        if items.is_empty() {
+            return Ok(());
+        }
+        apply_device_timezones(&mut items, &self.config, self.source.volume.as_deref());
+
+        // Nothing is queued or copied into a full, read-only or broken destination
+        let dest = &self.options.dest;
+        let copies: Vec<(PathBuf, u64)> = items
+            .iter()
+            .map(|item| (item.path.clone(), item.size))
+            .collect();
+        preflight(dest, space_needed(&copies, dest, LinkMode::Copy))?;
+
+        let strategy = FixedGap {
+            gap_threshold: self.gap_threshold,
+        };
+        let mut batches = strategy.group(&items);
+        let undated = undated_items(&items);
+        if !undated.is_empty() {
+            batches.push(batch_from_items(undated));
+        }
+
+        let mut placeholders = Vec::new();
+        let mut batches_with_names = Vec::new();
+        for batch in batches {
+            let (name, placeholder) = self.name(&batch);
+            if placeholder {
+                placeholders.push((name.slug.clone(), dates(&batch)));
+            }
+            batches_with_names.push((batch, name));
+        }
+
+        let files = IngestPlanner {
+            archive: &self.options.dest,
+            template: &self.template,
+            config: &self.config,
+            sources: std::slice::from_ref(&self.source),
+            time_shift: &[],
+            places: self.places.as_ref(),
+            archived: &self.archived,
+        }
+        .plan(&batches_with_names);
+
+        // Placeholders are queued as archived, i.e. with any profile prefix
+        let mut queued = HashSet::new();
+        for file in &files {
+            if let Some((_, range)) = placeholders
+                .iter()
+                .find(|(slug, _)| file.batch.ends_with(slug.as_str()))
+            {
+                if queued.insert(file.batch.clone()) {
+                    queue_rename(&self.options.dest, &file.batch, *range)?;
+                }
+            }
+        }
+
+        let mut journal = Journal::create(
+            &self.options.dest,
+            IngestPlan {
+                source: self.source.root.clone(),
+                extra_sources: Vec::new(),
+                link: LinkMode::Copy,
+                move_sources: false,
+                files,
+            },
+        )?;
+        let result = ingest::execute(&mut journal, &self.options.dest);
+
+        let plan = journal.plan();
+        let mut archived = 0;
+        for (index, file) in plan.files.iter().enumerate() {
+            if journal.is_done(index) {
+                self.archived
+                    .insert(file.blake3()?, self.options.dest.join(&file.archived));
+                archived += 1;
+            }
+        }
+        let mut names: Vec<&str> = plan.files.iter().map(|f| f.batch.as_str()).collect();
+        names.dedup();
+        self.log.write(&format!(
+            "Ingest {}: archived {} {} as {}",
+            journal.id(),
+            archived,
+            if archived == 1 { "file" } else { "files" },
+            names.join(", ")
+        ));
+        for batch in &queued {
+            self.log.write(&format!(
+                "Queued '{}' for renaming (folio rename-batch)",
+                batch
+            ));
+        }
+        result
+    }
+
+    /// The batch's name, and whether it is a placeholder
+    fn name(&self, batch: &TemporalBatch) -> (BatchName, bool) {
+        if let Some(name) = &self.options.batch_name {
+            return (BatchName::plain(name.as_str()), false);
+        }
+        if batch.items.iter().any(|item| item.timestamp.is_some()) {
+            let location = self
+                .places
+                .as_ref()
+                .and_then(|places| places.dominant_location(&batch.items));
+            let range = dates(batch);
+            if let Some(suggestion) = self
+                .suggester
+                .suggest(range.start, range.end, location.as_ref())
+                .into_iter()
+                .next()
+            {
+                return (BatchName::plain(suggestion.name), false);
+            }
+        }
+        let placeholder = format!("inbox-{}", dates(batch).start.format("%Y%m%d"));
+        (BatchName::plain(placeholder), true)
+    }
+}
+
+/// Days a batch covers; today for undated files
+fn dates(batch: &TemporalBatch) -> DateRange {
+    if batch.items.iter().all(|item| item.timestamp.is_none()) {
+        let today = Utc::now().date_naive();
+        return DateRange {
+            start: today,
+            end: today,
+        };
+    }
+    DateRange {
+        start: batch.start_time.date_naive(),
+        end: batch.end_time.date_naive(),
+    }
+}
+
+/// Files whose size and modification time haven't changed since the last check
+fn take_stable(pending: &mut HashMap<PathBuf, Option<(u64, SystemTime)>>) -> Vec<PathBuf> {
+    let mut stable = Vec::new();
+    pending.retain(|path, last| {
+        // Gone (deleted, or renamed into place under another name)
+        let Ok(metadata) = fs::metadata(path) else {
+            return false;
+        };
+        let now = (
+            metadata.len(),
+            metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        );
+        if *last == Some(now) {
+            stable.push(path.clone());
+            return false;
+        }
+        *last = Some(now);
+        true
+    });
+    stable.sort();
+    stable
+}
+
+/// Timestamped log lines, also shown on stdout
+struct WatchLog {
+    file: File,
+}
+
+impl WatchLog {
+    fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open log {:?}", path))?;
+        Ok(WatchLog { file })
+    }
+
+    fn write(&mut self, message: &str) {
+        println!("{}", message);
+        if let Err(e) = writeln!(
+            self.file,
+            "{}  {}",
+            Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+            message
+        ) {
+            eprintln!("⚠️  Failed to write the watch log: {}", e);
+        }
+    }
+}
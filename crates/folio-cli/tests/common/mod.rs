@@ -0,0 +1,32 @@
+//! Helpers shared by the CLI integration tests. Each test binary compiles its
+//! own copy and uses only some of them.
+#![allow(dead_code)]
+
+use assert_cmd::cmd::Command;
+use std::path::PathBuf;
+
+pub fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .join("test-data/fixtures")
+}
+
+/// The folio binary under test
+pub fn folio() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+}
+
+/// An empty archive, set up with `folio init`
+pub fn new_archive() -> assert_fs::TempDir {
+    let archive = assert_fs::TempDir::new().unwrap();
+    folio()
+        .arg("init")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+    archive
+}
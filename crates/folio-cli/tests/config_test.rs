@@ -0,0 +1,128 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+
+fn setup() -> (assert_fs::TempDir, assert_fs::TempDir, assert_fs::TempDir) {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    let config_dir = assert_fs::TempDir::new().unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo.jpg"),
+    )
+    .unwrap();
+    (source, archive, config_dir)
+}
+
+#[test]
+fn test_ingest_uses_destination_from_user_config() {
+    let (source, archive, config_dir) = setup();
+    config_dir
+        .child("folio.toml")
+        .write_str(&format!(
+            "destination = {:?}\n",
+            archive.path().display().to_string()
+        ))
+        .unwrap();
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .assert()
+        .success();
+
+    archive
+        .child("2024/11/04/20241104-140215-trip.jpg")
+        .assert(predicate::path::exists());
+}
+
+#[test]
+fn test_ingest_without_destination_fails() {
+    let (source, _archive, config_dir) = setup();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No destination given"));
+}
+
+#[test]
+fn test_ingest_applies_device_profile() {
+    let (source, archive, config_dir) = setup();
+    // The fixture was shot on a Nikon D800 at 14:02:15 New York time
+    archive
+        .child("folio.toml")
+        .write_str(
+            "timezone = \"America/Los_Angeles\"\n\
+             \n\
+             [[profile]]\n\
+             name = \"Dad's camera\"\n\
+             make = \"nikon\"\n\
+             model = \"D800\"\n\
+             timezone = \"America/New_York\"\n\
+             prefix = \"dad-\"\n",
+        )
+        .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Using profile 'Dad's camera' for 1 file",
+        ));
+
+    archive
+        .child("2024/11/04/20241104-110215-dad-trip.jpg")
+        .assert(predicate::path::exists());
+    archive
+        .child("2024/11/04/20241104-110215-dad-trip.xmp")
+        .assert(predicate::str::contains("2024-11-04T11:02:15"));
+}
+
+#[test]
+fn test_gap_threshold_from_archive_config() {
+    let (source, archive, config_dir) = setup();
+    fs::copy(
+        fixtures_dir().join("sample-different-time.jpg"),
+        source.path().join("later.jpg"),
+    )
+    .unwrap();
+    archive
+        .child("folio.toml")
+        .write_str("gap_threshold = 5.0\n")
+        .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Detected 1 temporal batch"))
+        .stdout(predicate::str::contains("gap threshold: 5.0 hours"));
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::fixtures_dir;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+
+/// A NAS share with real media next to thumbnail caches, trash and screenshots
+fn nas_share() -> assert_fs::TempDir {
+    let share = assert_fs::TempDir::new().unwrap();
+    // Trailing bytes keep the copies distinct, so none count as duplicates
+    let copy = |fixture: &str, to: &str| {
+        let target = share.path().join(to);
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        let mut data = fs::read(fixtures_dir().join(fixture)).unwrap();
+        data.extend_from_slice(to.as_bytes());
+        fs::write(target, data).unwrap();
+    };
+    copy("sample-with-exif.jpg", "DCIM/DSC_0001.jpg");
+    copy("sample-different-time.jpg", "DCIM/DSC_0002.jpg");
+    copy("minimal.mov", "DCIM/MOV_0003.mov");
+    copy("sample-with-exif.jpg", "Screenshots/shot.jpg");
+    copy(
+        "sample-with-exif.jpg",
+        "@eaDir/DSC_0001.jpg/SYNOFILE_THUMB_XL.jpg",
+    );
+    copy("sample-with-exif.jpg", ".Trashes/501/old.jpg");
+    copy("sample-with-exif.jpg", "DCIM/._DSC_0001.jpg");
+    share
+}
+
+fn dry_run(source: &Path, args: &[&str]) -> assert_cmd::assert::Assert {
+    let archive = assert_fs::TempDir::new().unwrap();
+    let config_dir = assert_fs::TempDir::new().unwrap();
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("ingest")
+        .arg("--source")
+        .arg(source)
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--dry-run")
+        .args(args)
+        .assert()
+}
+
+#[test]
+fn test_junk_folders_are_skipped_by_default() {
+    let share = nas_share();
+
+    dry_run(share.path(), &[])
+        .success()
+        .stdout(predicate::str::contains(
+            "Found 4 media files (3 photos, 1 videos)",
+        ));
+
+    dry_run(share.path(), &["--no-default-excludes"])
+        .success()
+        .stdout(predicate::str::contains("Found 7 media files"));
+}
+
+#[test]
+fn test_include_and_exclude_globs() {
+    let share = nas_share();
+
+    dry_run(share.path(), &["--exclude", "Screenshots"])
+        .success()
+        .stdout(predicate::str::contains(
+            "Found 3 media files (2 photos, 1 videos)",
+        ));
+
+    dry_run(
+        share.path(),
+        &["--include", "DCIM/**", "--include", "*.mov"],
+    )
+    .success()
+    .stdout(predicate::str::contains("Found 3 media files"));
+}
+
+#[test]
+fn test_date_kind_and_size_filters() {
+    let share = nas_share();
+
+    // The photos were taken on 2024-11-04; the video has no capture date and
+    // is filtered by its modification time
+    dry_run(share.path(), &["--since", "2024-11-05"])
+        .success()
+        .stdout(predicate::str::contains(
+            "Found 1 media file (0 photos, 1 videos)",
+        ));
+    dry_run(share.path(), &["--until", "2024-11-03"])
+        .success()
+        .stdout(predicate::str::contains("No media files found"));
+    dry_run(
+        share.path(),
+        &["--since", "2024-11-04", "--until", "2024-11-04"],
+    )
+    .success()
+    .stdout(predicate::str::contains(
+        "Found 3 media files (3 photos, 0 videos)",
+    ));
+
+    dry_run(share.path(), &["--photos-only"])
+        .success()
+        .stdout(predicate::str::contains(
+            "Found 3 media files (3 photos, 0 videos)",
+        ));
+
+    // The video is the only file above 1k
+    dry_run(share.path(), &["--min-size", "1k"])
+        .success()
+        .stdout(predicate::str::contains(
+            "Found 1 media file (0 photos, 1 videos)",
+        ));
+
+    dry_run(
+        share.path(),
+        &["--since", "2024-11-05", "--until", "2024-11-04"],
+    )
+    .failure()
+    .stderr(predicate::str::contains("is after --until"));
+}
+
+#[test]
+fn test_configured_excludes_apply() {
+    let share = nas_share();
+    let archive = assert_fs::TempDir::new().unwrap();
+    archive
+        .child("folio.toml")
+        .write_str("[scan]\nexclude = [\"Screenshots\"]\nmin_size = \"700\"\n")
+        .unwrap();
+    let config_dir = assert_fs::TempDir::new().unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("ingest")
+        .arg("--source")
+        .arg(share.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Found 2 media files (1 photos, 1 videos)",
+        ));
+}
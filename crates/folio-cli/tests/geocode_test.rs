@@ -0,0 +1,88 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+
+/// sample-with-exif.jpg carries GPS 40.7128, 74.0060; place a town and a lake there
+fn setup() -> (
+    assert_fs::TempDir,
+    assert_fs::TempDir,
+    assert_fs::NamedTempFile,
+) {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo.jpg"),
+    )
+    .unwrap();
+    let places = assert_fs::NamedTempFile::new("places.tsv").unwrap();
+    places
+        .write_str(
+            "# name\tregion\tcountry\tlatitude\tlongitude\tradius_km\n\
+             Testville\tTest Region\tTestland\t40.70\t74.01\n\
+             Lake Test\tTest Region\tTestland\t40.72\t74.00\t5\n",
+        )
+        .unwrap();
+    (source, archive, places)
+}
+
+#[test]
+fn test_ingest_suggests_place_and_writes_it_to_sidecar() {
+    let (source, archive, places) = setup();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("FOLIO_PLACES", places.path())
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .write_stdin("1\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Location: Lake Test (Testville, Test Region, Testland)",
+        ))
+        .stdout(predicate::str::contains("[1] lake-test"))
+        .stdout(predicate::str::contains("[2] testville"));
+
+    archive
+        .child("2024/11/04/20241104-140215-lake-test.jpg")
+        .assert(predicate::path::exists());
+    let sidecar = archive.child("2024/11/04/20241104-140215-lake-test.xmp");
+    sidecar.assert(predicate::str::contains("Testville"));
+    sidecar.assert(predicate::str::contains("Test Region"));
+    sidecar.assert(predicate::str::contains("Testland"));
+}
+
+#[test]
+fn test_ingest_without_nearby_place_writes_no_sidecar() {
+    let (source, archive, _) = setup();
+    let places = assert_fs::NamedTempFile::new("places.tsv").unwrap();
+    places
+        .write_str("Elsewhere\t\tTestland\t-40.0\t-74.0\n")
+        .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("FOLIO_PLACES", places.path())
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .assert()
+        .success();
+
+    archive
+        .child("2024/11/04/20241104-140215-trip.jpg")
+        .assert(predicate::path::exists());
+    archive
+        .child("2024/11/04/20241104-140215-trip.xmp")
+        .assert(predicate::path::missing());
+}
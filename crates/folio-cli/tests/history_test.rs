@@ -0,0 +1,122 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+
+fn ingest(card: &Path, archive: &Path, args: &[&str]) -> assert_cmd::assert::Assert {
+    let config_dir = assert_fs::TempDir::new().unwrap();
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("ingest")
+        .arg("--source")
+        .arg(card)
+        .arg("--dest")
+        .arg(archive)
+        .arg("--batch-name")
+        .arg("trip")
+        .args(args)
+        .assert()
+}
+
+#[test]
+fn test_reingest_only_reads_new_files() {
+    let card = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::create_dir(card.path().join("100NIKON")).unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        card.path().join("100NIKON/DSC_0001.jpg"),
+    )
+    .unwrap();
+
+    ingest(card.path(), archive.path(), &[])
+        .success()
+        .stdout(predicate::str::contains("new file").not());
+    archive
+        .child(".folio/imports")
+        .assert(predicate::path::is_dir());
+
+    // The card wasn't wiped; one more photo was taken
+    fs::copy(
+        fixtures_dir().join("sample-different-time.jpg"),
+        card.path().join("100NIKON/DSC_0002.jpg"),
+    )
+    .unwrap();
+    ingest(card.path(), archive.path(), &[])
+        .success()
+        .stdout(
+            predicate::str::is_match(r"1 new file since \d{4}-\d{2}-\d{2} \(1 imported before\)")
+                .unwrap(),
+        )
+        .stdout(predicate::str::contains("Found 1 media file"));
+    archive
+        .child("2024/11/04/20241104-181530-trip.jpg")
+        .assert(predicate::path::exists());
+
+    ingest(card.path(), archive.path(), &[])
+        .success()
+        .stdout(predicate::str::contains(
+            "No new media files since the last import",
+        ));
+
+    // A changed file is read again
+    let mut data = fs::read(card.path().join("100NIKON/DSC_0001.jpg")).unwrap();
+    data.extend_from_slice(b"edited");
+    fs::write(card.path().join("100NIKON/DSC_0001.jpg"), data).unwrap();
+    ingest(card.path(), archive.path(), &["--dry-run"])
+        .success()
+        .stdout(predicate::str::contains("1 new file since"));
+
+    // --rescan ignores the history
+    ingest(card.path(), archive.path(), &["--dry-run", "--rescan"])
+        .success()
+        .stdout(predicate::str::contains("Found 2 media files"))
+        .stdout(predicate::str::contains("(0 imported before)"));
+}
+
+#[test]
+fn test_dry_run_records_nothing() {
+    let card = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        card.path().join("DSC_0001.jpg"),
+    )
+    .unwrap();
+
+    ingest(card.path(), archive.path(), &["--dry-run"]).success();
+    archive
+        .child(".folio/imports")
+        .assert(predicate::path::missing());
+}
+
+#[test]
+fn test_undone_files_are_read_again() {
+    let card = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        card.path().join("DSC_0001.jpg"),
+    )
+    .unwrap();
+    ingest(card.path(), archive.path(), &[]).success();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("undo")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+
+    // The photo is no longer archived, so it isn't skipped as imported before
+    ingest(card.path(), archive.path(), &[])
+        .success()
+        .stdout(predicate::str::contains("(0 imported before)"));
+    archive
+        .child("2024/11/04/20241104-140215-trip.jpg")
+        .assert(predicate::path::exists());
+}
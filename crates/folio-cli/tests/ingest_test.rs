@@ -1,5 +1,8 @@
+mod common;
+
 use assert_cmd::cmd::Command;
 use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
 use predicates::prelude::*;
 use std::fs;
 use walkdir::WalkDir;
@@ -8,15 +11,10 @@ use walkdir::WalkDir;
 fn test_ingest_mixed_media() {
     // Arrange: Create test environment
     let source = assert_fs::TempDir::new().unwrap();
-    let archive = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
 
     // Copy fixtures to source directory
-    let fixtures_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("test-data/fixtures");
+    let fixtures_dir = fixtures_dir();
 
     // Copy a photo and a video from fixtures
     fs::copy(
@@ -73,14 +71,9 @@ fn test_ingest_mixed_media() {
 fn test_ingest_filters_non_media() {
     // Arrange
     let source = assert_fs::TempDir::new().unwrap();
-    let archive = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
 
-    let fixtures_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("test-data/fixtures");
+    let fixtures_dir = fixtures_dir();
 
     // Copy one media file and one text file
     fs::copy(
@@ -132,14 +125,9 @@ fn test_ingest_filters_non_media() {
 fn test_ingest_dry_run() {
     // Arrange
     let source = assert_fs::TempDir::new().unwrap();
-    let archive = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
 
-    let fixtures_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("test-data/fixtures");
+    let fixtures_dir = fixtures_dir();
 
     fs::copy(
         fixtures_dir.join("minimal.jpg"),
@@ -168,14 +156,9 @@ fn test_ingest_dry_run() {
 fn test_ingest_organizes_by_date() {
     // Arrange: Create test environment
     let source = assert_fs::TempDir::new().unwrap();
-    let archive = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
 
-    let fixtures_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("test-data/fixtures");
+    let fixtures_dir = fixtures_dir();
 
     // Copy photo with EXIF timestamp: 2024-11-04 14:02:15
     fs::copy(
@@ -212,14 +195,9 @@ fn test_ingest_organizes_by_date() {
 fn test_ingest_with_batch_name() {
     // Arrange: Create test environment with two photos from different times
     let source = assert_fs::TempDir::new().unwrap();
-    let archive = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
 
-    let fixtures_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("test-data/fixtures");
+    let fixtures_dir = fixtures_dir();
 
     // Copy two photos with different EXIF timestamps
     // sample-with-exif.jpg: 2024:11:04 14:02:15
@@ -274,14 +252,9 @@ fn test_ingest_with_batch_name() {
 fn test_ingest_detects_temporal_batches_with_dry_run() {
     // Arrange: Create test environment with photos from two temporal batches
     let source = assert_fs::TempDir::new().unwrap();
-    let archive = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
 
-    let fixtures_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("test-data/fixtures");
+    let fixtures_dir = fixtures_dir();
 
     // Copy photos with timestamps 4+ hours apart (> 2-hour default gap threshold)
     // Batch 1: sample-with-exif.jpg at 2024:11:04 14:02:15
@@ -319,14 +292,9 @@ fn test_ingest_detects_temporal_batches_with_dry_run() {
 fn test_ingest_batch_name_disables_temporal_batching() {
     // Arrange: Same setup as above - photos 4+ hours apart
     let source = assert_fs::TempDir::new().unwrap();
-    let archive = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
 
-    let fixtures_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("test-data/fixtures");
+    let fixtures_dir = fixtures_dir();
 
     fs::copy(
         fixtures_dir.join("sample-with-exif.jpg"),
@@ -381,14 +349,9 @@ fn test_ingest_batch_name_disables_temporal_batching() {
 fn test_ingest_custom_gap_threshold() {
     // Arrange: Photos 4+ hours apart
     let source = assert_fs::TempDir::new().unwrap();
-    let archive = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
 
-    let fixtures_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("test-data/fixtures");
+    let fixtures_dir = fixtures_dir();
 
     // Copy photos with timestamps 4+ hours apart
     // Batch 1: sample-with-exif.jpg at 2024:11:04 14:02:15
@@ -428,14 +391,9 @@ fn test_ingest_custom_gap_threshold() {
 fn test_ingest_validates_batch_name() {
     // Arrange
     let source = assert_fs::TempDir::new().unwrap();
-    let archive = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
 
-    let fixtures_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("test-data/fixtures");
+    let fixtures_dir = fixtures_dir();
 
     fs::copy(
         fixtures_dir.join("minimal.jpg"),
@@ -454,7 +412,8 @@ fn test_ingest_validates_batch_name() {
         .arg("invalid batch name") // spaces not allowed
         .assert()
         .failure()
-        .stderr(predicate::str::contains("Invalid batch name"));
+        .stderr(predicate::str::contains("Invalid batch name"))
+        .stderr(predicate::str::contains("try 'invalid-batch-name'"));
 
     // Act: Try to use invalid batch name with special characters
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
@@ -487,14 +446,9 @@ fn test_ingest_validates_batch_name() {
 fn test_ingest_interactive_mode_with_valid_input() {
     // Arrange: Two photos from different temporal batches
     let source = assert_fs::TempDir::new().unwrap();
-    let archive = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
 
-    let fixtures_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("test-data/fixtures");
+    let fixtures_dir = fixtures_dir();
 
     // Copy photos with timestamps 4+ hours apart (> 2-hour default gap threshold)
     // Batch 1: sample-with-exif.jpg at 2024:11:04 14:02:15
@@ -551,30 +505,25 @@ fn test_ingest_interactive_mode_with_valid_input() {
 fn test_ingest_interactive_mode_with_invalid_then_valid_input() {
     // Arrange
     let source = assert_fs::TempDir::new().unwrap();
-    let archive = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
 
-    let fixtures_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("test-data/fixtures");
+    let fixtures_dir = fixtures_dir();
 
     fs::copy(
-        fixtures_dir.join("minimal.jpg"),
+        fixtures_dir.join("sample-with-exif.jpg"),
         source.path().join("photo.jpg"),
     )
     .unwrap();
 
-    // Act: Provide invalid input first (with space), then valid input
-    // stdin: "invalid name\nvalid-name\n"
+    // Act: Provide input with nothing to slugify first, then valid input
+    // stdin: "???\nvalid-name\n"
     let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
     cmd.arg("ingest")
         .arg("--source")
         .arg(source.path())
         .arg("--dest")
         .arg(archive.path())
-        .write_stdin("invalid name\nvalid-name\n")
+        .write_stdin("???\nvalid-name\n")
         .assert()
         .success()
         .stderr(predicate::str::contains("Invalid batch name"))
@@ -598,3 +547,341 @@ fn test_ingest_interactive_mode_with_invalid_then_valid_input() {
     }
     assert!(found_file, "File with valid-name should exist in archive");
 }
+
+#[test]
+fn test_ingest_slugifies_human_batch_name_and_keeps_title() {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+
+    let fixtures_dir = fixtures_dir();
+
+    fs::copy(
+        fixtures_dir.join("sample-with-exif.jpg"),
+        source.path().join("photo.jpg"),
+    )
+    .unwrap();
+
+    // Accept the suggested slug with Enter
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .write_stdin("Thanksgiving at Grandma's\n\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Use 'thanksgiving-at-grandmas'? [Y/n]",
+        ));
+
+    archive
+        .child("2024/11/04/20241104-140215-thanksgiving-at-grandmas.jpg")
+        .assert(predicate::path::exists());
+    archive
+        .child("2024/11/04/20241104-140215-thanksgiving-at-grandmas.xmp")
+        .assert(predicate::str::contains("Thanksgiving at Grandma's"));
+}
+
+#[test]
+fn test_ingest_declined_slug_prompts_again() {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+
+    let fixtures_dir = fixtures_dir();
+
+    fs::copy(
+        fixtures_dir.join("sample-with-exif.jpg"),
+        source.path().join("photo.jpg"),
+    )
+    .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .write_stdin("Noël 2024\nn\nnoel\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Use 'noel-2024'? [Y/n]"));
+
+    archive
+        .child("2024/11/04/20241104-140215-noel.jpg")
+        .assert(predicate::path::exists());
+    archive
+        .child("2024/11/04/20241104-140215-noel.xmp")
+        .assert(predicate::path::missing());
+}
+
+#[test]
+fn test_ingest_calendar_day_batching() {
+    // Arrange: Photos 4+ hours apart on the same day
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+
+    let fixtures_dir = fixtures_dir();
+
+    fs::copy(
+        fixtures_dir.join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+
+    fs::copy(
+        fixtures_dir.join("sample-different-time.jpg"),
+        source.path().join("photo2.jpg"),
+    )
+    .unwrap();
+
+    // Act: Calendar-day batching groups both into one batch despite the gap
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batching")
+        .arg("calendar-day")
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Detected 1 temporal batch"))
+        .stdout(predicate::str::contains("one batch per calendar day"));
+}
+
+#[test]
+fn test_ingest_time_shift_corrects_camera_clock() {
+    // Arrange: Nikon D800 photo at 2024:11:04 14:02:15 from a camera one hour behind
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+
+    let fixtures_dir = fixtures_dir();
+
+    fs::copy(
+        fixtures_dir.join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .arg("--time-shift")
+        .arg("Nikon D800=+1h")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Applied clock correction to 1 file",
+        ));
+
+    // Assert: Corrected time used for naming, and recorded in the XMP sidecar
+    let expected_path = archive
+        .path()
+        .join("2024/11/04/20241104-150215-test-batch.jpg");
+    assert!(
+        expected_path.exists(),
+        "Expected file at {:?}",
+        expected_path
+    );
+    let sidecar = fs::read_to_string(expected_path.with_extension("xmp")).unwrap();
+    assert!(sidecar.contains("exif:DateTimeOriginal=\"2024-11-04T15:02:15\""));
+}
+
+#[test]
+fn test_ingest_with_template_flag() {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+
+    let fixtures_dir = fixtures_dir();
+
+    fs::copy(
+        fixtures_dir.join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+
+    // Act: ADR layout with a per-batch counter
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("thanksgiving")
+        .arg("--template")
+        .arg("{year}/{date}_{batch}/{date}-{time}-{batch}-{seq}.{ext}")
+        .assert()
+        .success();
+
+    let expected_path = archive
+        .path()
+        .join("2024/2024-11-04_thanksgiving/2024-11-04-140215-thanksgiving-001.jpg");
+    assert!(
+        expected_path.exists(),
+        "Expected file at {:?}",
+        expected_path
+    );
+}
+
+#[test]
+fn test_ingest_uses_template_from_archive_config() {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+
+    let fixtures_dir = fixtures_dir();
+
+    fs::copy(
+        fixtures_dir.join("sample-with-exif.jpg"),
+        source.path().join("DSC_0042.jpg"),
+    )
+    .unwrap();
+    archive
+        .child("folio.toml")
+        .write_str("template = \"{camera}/{year}/{stem}.{ext}\"\n")
+        .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .assert()
+        .success();
+
+    assert!(archive.path().join("nikon-d800/2024/DSC_0042.jpg").exists());
+}
+
+#[test]
+fn test_ingest_rejects_invalid_template_before_copying() {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+
+    let fixtures_dir = fixtures_dir();
+
+    fs::copy(
+        fixtures_dir.join("minimal.jpg"),
+        source.path().join("photo.jpg"),
+    )
+    .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .arg("--template")
+        .arg("{year}/{batch}.{ext}")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("{seq}"));
+
+    // Nothing but the archive's own .folio/ and folio.toml
+    let entries = WalkDir::new(archive.path())
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".folio" && e.file_name() != "folio.toml")
+        .count();
+    assert_eq!(entries, 1);
+}
+
+#[test]
+fn test_ingest_continues_seq_of_existing_batch() {
+    let archive = new_archive();
+    let fixtures_dir = fixtures_dir();
+    archive
+        .child("folio.toml")
+        .write_str("template = \"{year}/{batch}/{batch}-{seq}.{ext}\"\n")
+        .unwrap();
+
+    for name in ["sample-with-exif.jpg", "sample-different-time.jpg"] {
+        let source = assert_fs::TempDir::new().unwrap();
+        fs::copy(fixtures_dir.join(name), source.path().join(name)).unwrap();
+        Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+            .arg("ingest")
+            .arg("--source")
+            .arg(source.path())
+            .arg("--dest")
+            .arg(archive.path())
+            .arg("--batch-name")
+            .arg("trip")
+            .assert()
+            .success();
+    }
+
+    let trip = archive.path().join("2024/trip");
+    assert_eq!(
+        fs::read(trip.join("trip-001.jpg")).unwrap(),
+        fs::read(fixtures_dir.join("sample-with-exif.jpg")).unwrap()
+    );
+    assert_eq!(
+        fs::read(trip.join("trip-002.jpg")).unwrap(),
+        fs::read(fixtures_dir.join("sample-different-time.jpg")).unwrap()
+    );
+}
+
+#[test]
+fn test_ingest_asks_how_to_date_files_without_capture_time() {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    let fixtures_dir = fixtures_dir();
+    fs::copy(
+        fixtures_dir.join("sample-with-exif.jpg"),
+        source.path().join("photo.jpg"),
+    )
+    .unwrap();
+    // No EXIF: its modification time is when it was copied, not when it was taken
+    fs::copy(
+        fixtures_dir.join("no-exif.jpg"),
+        source.path().join("scan.jpg"),
+    )
+    .unwrap();
+
+    let ingest = || {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+        cmd.arg("ingest")
+            .arg("--source")
+            .arg(source.path())
+            .arg("--dest")
+            .arg(archive.path());
+        cmd
+    };
+
+    ingest()
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Detected 1 temporal batch"))
+        .stdout(predicate::str::contains("Found 1 undated file"));
+
+    // Name the dated batch, then date the scan by hand and name its batch
+    ingest()
+        .write_stdin("trip\n1\n2024-12-25\nchristmas\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 file has no capture timestamp"))
+        .stdout(predicate::str::contains("Batch 2 of 2"))
+        .stdout(predicate::str::contains("Copied 2 files"));
+
+    assert!(archive
+        .path()
+        .join("2024/11/04/20241104-140215-trip.jpg")
+        .exists());
+    assert!(archive
+        .path()
+        .join("2024/12/25/20241225-000000-christmas.jpg")
+        .exists());
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use assert_fs::prelude::*;
+use common::folio;
+use predicates::prelude::*;
+use std::fs;
+
+#[test]
+fn test_init_marks_archive_once() {
+    let root = assert_fs::TempDir::new().unwrap();
+    let archive = root.child("photos");
+
+    folio()
+        .arg("init")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--template")
+        .arg("{year}/{month}/{seq}-{batch}.{ext}")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Initialized archive"));
+    let marker = fs::read_to_string(archive.path().join(".folio/archive.toml")).unwrap();
+    assert!(marker.starts_with("id = "));
+    assert!(marker.contains("schema = 1"));
+    archive
+        .child("folio.toml")
+        .assert("template = \"{year}/{month}/{seq}-{batch}.{ext}\"\n");
+
+    folio()
+        .arg("init")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is already a folio archive"));
+    assert_eq!(
+        fs::read_to_string(archive.path().join(".folio/archive.toml")).unwrap(),
+        marker
+    );
+}
+
+#[test]
+fn test_init_adopts_archive_from_before_markers() {
+    let archive = assert_fs::TempDir::new().unwrap();
+    archive.child(".folio/journal").create_dir_all().unwrap();
+    archive.child("2024/11/04/photo.jpg").write_str("").unwrap();
+    let config_dir = assert_fs::TempDir::new().unwrap();
+
+    folio()
+        .env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("init")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Adopted the existing archive"));
+    archive
+        .child(".folio/archive.toml")
+        .assert(predicate::path::exists());
+    archive
+        .child("folio.toml")
+        .assert(format!("template = {:?}\n", folio_core::DEFAULT_TEMPLATE));
+}
+
+#[test]
+fn test_init_records_the_layout_in_effect() {
+    let config_dir = assert_fs::TempDir::new().unwrap();
+    config_dir
+        .child("folio.toml")
+        .write_str("template = \"{year}/{seq}-{batch}.{ext}\"\n")
+        .unwrap();
+    let archive = assert_fs::TempDir::new().unwrap();
+    archive
+        .child("folio.toml")
+        .write_str("[[profile]]\nname = \"phone\"\nmake = \"Apple\"\nprefix = \"phone-\"\n")
+        .unwrap();
+
+    folio()
+        .env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("init")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+    archive.child("folio.toml").assert(
+        "template = \"{year}/{seq}-{batch}.{ext}\"\n[[profile]]\nname = \"phone\"\nmake = \"Apple\"\nprefix = \"phone-\"\n",
+    );
+}
+
+#[test]
+fn test_archive_from_newer_version_is_refused() {
+    let archive = assert_fs::TempDir::new().unwrap();
+    archive
+        .child(".folio/archive.toml")
+        .write_str("id = \"a\"\nschema = 99\ncreated = \"2024-11-01T00:00:00Z\"\n")
+        .unwrap();
+
+    folio()
+        .arg("undo")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("newer version of folio"));
+}
+
+#[test]
+fn test_commands_refuse_uninitialized_archive() {
+    let archive = assert_fs::TempDir::new().unwrap();
+    archive.child("2024/11/04/photo.jpg").write_str("").unwrap();
+
+    for args in [
+        &["retime", "--shift", "+1h", "--batch", "a"][..],
+        &["rename-batch", "--from", "a", "--to", "b"],
+        &["undo"],
+    ] {
+        folio()
+            .args(args)
+            .arg("--archive")
+            .arg(archive.path())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("folio init --archive"));
+    }
+
+    let source = assert_fs::TempDir::new().unwrap();
+    source.child("photo.jpg").write_str("").unwrap();
+    folio()
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("folio init --archive"));
+
+    // A dry run changes nothing, so it needs no archive
+    folio()
+        .args(["retime", "--shift", "+1h", "--batch", "a", "--dry-run"])
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+}
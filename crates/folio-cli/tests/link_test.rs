@@ -0,0 +1,138 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+
+#[test]
+fn test_link_hard_shares_data_with_source() {
+    // Arrange: Source and archive on the same filesystem
+    let root = assert_fs::TempDir::new().unwrap();
+    let source = root.path().join("incoming");
+    let archive = new_archive();
+    fs::create_dir_all(&source).unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.join("photo1.jpg"),
+    )
+    .unwrap();
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(&source)
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .arg("--link")
+        .arg("hard")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hardlinked"))
+        .stdout(predicate::str::contains("20241104-140215-test-batch.jpg"))
+        .stdout(predicate::str::contains(
+            "Archived 1 files (1 hardlinked, 0 reflinked, 0 copied)",
+        ));
+
+    // Assert: Both names point at the same inode
+    let archived = archive
+        .path()
+        .join("2024/11/04/20241104-140215-test-batch.jpg");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        assert_eq!(
+            fs::metadata(source.join("photo1.jpg")).unwrap().ino(),
+            fs::metadata(&archived).unwrap().ino()
+        );
+    }
+    assert!(archived.exists());
+}
+
+#[test]
+fn test_link_reflink_reports_method_per_file() {
+    // Arrange
+    let root = assert_fs::TempDir::new().unwrap();
+    let source = root.path().join("incoming");
+    let archive = new_archive();
+    fs::create_dir_all(&source).unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.join("photo1.jpg"),
+    )
+    .unwrap();
+
+    // Act: Clones where supported, copies elsewhere
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(&source)
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .arg("--link")
+        .arg("reflink")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Transfer methods:"))
+        .stdout(
+            predicate::str::is_match("(reflinked|copied) +.*20241104-140215-test-batch.jpg")
+                .unwrap(),
+        );
+
+    // Assert
+    assert_eq!(
+        fs::read(
+            archive
+                .path()
+                .join("2024/11/04/20241104-140215-test-batch.jpg")
+        )
+        .unwrap(),
+        fs::read(source.join("photo1.jpg")).unwrap()
+    );
+}
+
+#[test]
+fn test_link_hard_with_move_keeps_archive_copy() {
+    // Arrange
+    let root = assert_fs::TempDir::new().unwrap();
+    let source = root.path().join("incoming");
+    let archive = new_archive();
+    fs::create_dir_all(&source).unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.join("photo1.jpg"),
+    )
+    .unwrap();
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(&source)
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .arg("--link")
+        .arg("hard")
+        .arg("--move")
+        .assert()
+        .success();
+
+    // Assert: Removing the source name leaves the archived file intact
+    assert!(!source.join("photo1.jpg").exists());
+    assert_eq!(
+        fs::read(
+            archive
+                .path()
+                .join("2024/11/04/20241104-140215-test-batch.jpg")
+        )
+        .unwrap(),
+        fs::read(fixtures_dir().join("sample-with-exif.jpg")).unwrap()
+    );
+}
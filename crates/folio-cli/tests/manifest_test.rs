@@ -0,0 +1,134 @@
+mod common;
+
+use assert_fs::prelude::*;
+use common::{fixtures_dir, folio, new_archive};
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+
+/// Ingest the two fixture photos (both from 2024-11-04) as one batch
+fn ingest(archive: &Path) {
+    let source = assert_fs::TempDir::new().unwrap();
+    for name in ["sample-with-exif.jpg", "sample-different-time.jpg"] {
+        fs::copy(fixtures_dir().join(name), source.path().join(name)).unwrap();
+    }
+    folio()
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive)
+        .arg("--batch-name")
+        .arg("trip")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Updated manifest-blake3.txt in 1 folder",
+        ));
+}
+
+fn manifest_line(fixture: &str, name: &str) -> String {
+    let data = fs::read(fixtures_dir().join(fixture)).unwrap();
+    format!("{}  {}\n", blake3::hash(&data).to_hex(), name)
+}
+
+#[test]
+fn test_ingest_writes_manifest_per_date_folder() {
+    let archive = new_archive();
+    ingest(archive.path());
+
+    // BagIt / b3sum layout: "<hash>  <name>", sorted by name
+    let manifest =
+        fs::read_to_string(archive.path().join("2024/11/04/manifest-blake3.txt")).unwrap();
+    assert_eq!(
+        manifest,
+        manifest_line("sample-with-exif.jpg", "20241104-140215-trip.jpg")
+            + &manifest_line("sample-different-time.jpg", "20241104-181530-trip.jpg")
+    );
+}
+
+#[test]
+fn test_manifest_follows_renames_and_undo() {
+    let archive = new_archive();
+    ingest(archive.path());
+    let manifest = archive.child("2024/11/04/manifest-blake3.txt");
+
+    folio()
+        .arg("rename-batch")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--from")
+        .arg("trip")
+        .arg("--to")
+        .arg("thanksgiving")
+        .assert()
+        .success();
+    manifest.assert(predicate::str::contains("20241104-140215-thanksgiving.jpg"));
+    manifest.assert(predicate::str::contains("-trip.jpg").not());
+
+    folio()
+        .arg("retime")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--batch")
+        .arg("thanksgiving")
+        .arg("--shift")
+        .arg("+12h")
+        .assert()
+        .success();
+    archive
+        .child("2024/11/05/manifest-blake3.txt")
+        .assert(predicate::str::contains("20241105-061530-thanksgiving.jpg"));
+    // The catalog followed too, so the archive still verifies
+    folio()
+        .arg("verify")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+
+    // Undo takes the files out of the manifest, which goes once empty
+    folio()
+        .arg("undo")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+    archive
+        .child("2024/11/05/manifest-blake3.txt")
+        .assert(predicate::path::missing());
+    archive
+        .child("2024/11/04")
+        .assert(predicate::path::missing());
+}
+
+#[test]
+fn test_verify_uses_manifests_without_catalog() {
+    // An offsite copy: the date folders without .folio
+    let archive = new_archive();
+    ingest(archive.path());
+    fs::remove_dir_all(archive.path().join(".folio")).unwrap();
+
+    folio()
+        .arg("verify")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Checked 2 of 2 recorded files"));
+
+    fs::write(
+        archive.path().join("2024/11/04/20241104-181530-trip.jpg"),
+        b"bit rot",
+    )
+    .unwrap();
+    folio()
+        .arg("verify")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "corrupted: 2024/11/04/20241104-181530-trip.jpg",
+        ));
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+use std::io::Write;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+/// A card labelled `label` under `mounts`, with one photo in DCIM/100NIKON
+fn mount_card(mounts: &std::path::Path, label: &str) {
+    // Built next to the mount root and moved in, so it appears all at once
+    let staging = mounts.with_extension("staging");
+    let dcim = staging.join("DCIM/100NIKON");
+    fs::create_dir_all(&dcim).unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        dcim.join("DSC_0001.jpg"),
+    )
+    .unwrap();
+    fs::rename(&staging, mounts.join(label)).unwrap();
+}
+
+#[test]
+fn test_ingest_auto_uses_mounted_card_and_its_profile() {
+    let mounts = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    let config_dir = assert_fs::TempDir::new().unwrap();
+    mount_card(mounts.path(), "DAD_SD");
+    archive
+        .child("folio.toml")
+        .write_str("[[profile]]\nname = \"Dad's camera\"\nvolume = \"DAD_SD\"\nprefix = \"dad-\"\n")
+        .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("ingest")
+        .arg("--auto")
+        .arg("--mount-root")
+        .arg(mounts.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Found camera media: DAD_SD"))
+        .stdout(predicate::str::contains("Using profile 'Dad's camera'"));
+
+    archive
+        .child("2024/11/04/20241104-140215-dad-trip.jpg")
+        .assert(predicate::path::exists());
+}
+
+#[test]
+fn test_ingest_auto_without_camera_media_fails() {
+    let mounts = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    mounts.child("BACKUP/Photos").create_dir_all().unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--auto")
+        .arg("--mount-root")
+        .arg(mounts.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No camera media"));
+}
+
+#[test]
+fn test_watch_mounts_ingests_newly_mounted_card() {
+    let mounts = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    let config_dir = assert_fs::TempDir::new().unwrap();
+
+    let mut watcher = std::process::Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("watch-mounts")
+        .arg("--mount-root")
+        .arg(mounts.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--interval")
+        .arg("1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // The ingest session inherits the watcher's stdin for the batch name prompt
+    watcher
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all(b"trip\n")
+        .unwrap();
+
+    std::thread::sleep(Duration::from_millis(500));
+    mount_card(mounts.path(), "EOS_DIGITAL");
+
+    let archived = archive.child("2024/11/04/20241104-140215-trip.jpg");
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while !archived.path().exists() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    watcher.kill().unwrap();
+    watcher.wait().unwrap();
+
+    archived.assert(predicate::path::exists());
+}
@@ -0,0 +1,236 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+use walkdir::WalkDir;
+
+fn move_logs(archive: &std::path::Path) -> Vec<String> {
+    WalkDir::new(archive.join(".folio/logs"))
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| fs::read_to_string(e.path()).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_move_removes_verified_sources_and_logs_them() {
+    // Arrange
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-different-time.jpg"),
+        source.path().join("photo2.jpg"),
+    )
+    .unwrap();
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .arg("--move")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Copied 2 files"))
+        .stdout(predicate::str::contains("Removed 2 source files"));
+
+    // Assert: Archived, removed from source, and logged
+    assert!(archive
+        .path()
+        .join("2024/11/04/20241104-140215-test-batch.jpg")
+        .exists());
+    assert!(!source.child("photo1.jpg").exists());
+    assert!(!source.child("photo2.jpg").exists());
+
+    let logs = move_logs(archive.path());
+    assert_eq!(logs.len(), 1);
+    assert!(logs[0].contains("photo1.jpg"));
+    assert!(logs[0].contains("photo2.jpg"));
+}
+
+#[test]
+fn test_move_removes_sources_already_in_archive() {
+    // Arrange: The photo was already ingested by an earlier copy
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .assert()
+        .success();
+    assert!(source.child("photo1.jpg").exists());
+
+    // Act: Re-ingest with --move
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .arg("--move")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Skipped 1 duplicate files"))
+        .stdout(predicate::str::contains("Removed 1 source file"));
+
+    // Assert: Verified duplicate removed from the source
+    assert!(!source.child("photo1.jpg").exists());
+    let logs = move_logs(archive.path());
+    assert!(logs[0].contains("20241104-140215-test-batch.jpg"));
+}
+
+#[test]
+fn test_move_keeps_batch_sources_when_a_file_fails() {
+    // Arrange: A file named "2024" blocks the 2024/ date folder
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    fs::copy(
+        fixtures_dir().join("minimal.jpg"),
+        source.path().join("photo2.jpg"),
+    )
+    .unwrap();
+    archive.child("2024").write_str("not a folder").unwrap();
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .arg("--move")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Keeping source files of batch 'test-batch'",
+        ))
+        .stderr(predicate::str::contains("1 file could not be copied"));
+
+    // Assert: Nothing removed from the source, even the file that copied fine
+    assert!(source.child("photo1.jpg").exists());
+    assert!(source.child("photo2.jpg").exists());
+    assert!(move_logs(archive.path()).is_empty());
+}
+
+#[test]
+fn test_move_keeps_batch_sources_when_a_prefixed_file_fails() {
+    // Arrange: Dad's photo gets a profile prefix, the other one doesn't, and
+    // a file named "2024" blocks the date folder of Dad's photo
+    let source = assert_fs::TempDir::new().unwrap();
+    let config_dir = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    archive
+        .child("folio.toml")
+        .write_str("[[profile]]\nname = \"Dad\"\nmodel = \"D800\"\nprefix = \"dad-\"\n")
+        .unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    fs::copy(
+        fixtures_dir().join("minimal.jpg"),
+        source.path().join("photo2.jpg"),
+    )
+    .unwrap();
+    archive.child("2024").write_str("not a folder").unwrap();
+
+    // Act: Both files are one batch, named dad-trip and trip
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .arg("--move")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Keeping source files of batch"));
+
+    // Assert: The file that copied fine keeps its source too
+    assert!(source.child("photo1.jpg").exists());
+    assert!(source.child("photo2.jpg").exists());
+    assert!(move_logs(archive.path()).is_empty());
+}
+
+#[test]
+fn test_move_keeps_photos_taken_in_the_same_second_apart() {
+    // Two different photos with the same EXIF second, e.g. a burst
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    let photo = fs::read(fixtures_dir().join("sample-with-exif.jpg")).unwrap();
+    let mut burst = photo.clone();
+    burst.extend_from_slice(b"second frame");
+    source.child("a.jpg").write_binary(&photo).unwrap();
+    source.child("b.jpg").write_binary(&burst).unwrap();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("x")
+        .arg("--move")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 2 source files"));
+
+    // Both contents are archived, under different names
+    let day = archive.child("2024/11/04");
+    let mut archived = vec![
+        fs::read(day.path().join("20241104-140215-x.jpg")).unwrap(),
+        fs::read(day.path().join("20241104-140215-x.2.jpg")).unwrap(),
+    ];
+    archived.sort();
+    let mut expected = vec![photo, burst];
+    expected.sort();
+    assert_eq!(archived, expected);
+
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("verify")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+}
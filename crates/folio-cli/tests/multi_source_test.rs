@@ -0,0 +1,144 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+use walkdir::WalkDir;
+
+/// Two cards from the same day; the second also holds a copy of the first card's photo
+fn two_cards() -> (assert_fs::TempDir, assert_fs::TempDir) {
+    let card_a = assert_fs::TempDir::new().unwrap();
+    let card_b = assert_fs::TempDir::new().unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        card_a.path().join("DSC_0001.jpg"),
+    )
+    .unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-different-time.jpg"),
+        card_b.path().join("IMG_0001.jpg"),
+    )
+    .unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        card_b.path().join("IMG_0002.jpg"),
+    )
+    .unwrap();
+    (card_a, card_b)
+}
+
+fn archived_files(archive: &std::path::Path) -> Vec<String> {
+    let mut files: Vec<String> = WalkDir::new(archive)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| !e.path().starts_with(archive.join(".folio")))
+        .filter(|e| e.path() != archive.join("folio.toml"))
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_sources_are_batched_together() {
+    let (card_a, card_b) = two_cards();
+    let archive = new_archive();
+
+    // One event across both cards gets a single prompt
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(card_a.path())
+        .arg("--source")
+        .arg(card_b.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--gap-threshold")
+        .arg("5")
+        .write_stdin("trip\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Ignoring 1 copy found more than once",
+        ))
+        .stdout(predicate::str::contains("Found 2 media files"))
+        .stdout(predicate::str::contains("Detected 1 temporal batch"));
+
+    assert_eq!(
+        archived_files(archive.path()),
+        vec![
+            "20241104-140215-trip.jpg".to_string(),
+            "20241104-181530-trip.jpg".to_string(),
+            "manifest-blake3.txt".to_string()
+        ]
+    );
+}
+
+#[test]
+fn test_multi_source_ingest_journals_every_source() {
+    let (card_a, card_b) = two_cards();
+    let archive = new_archive();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(card_a.path())
+        .arg("--source")
+        .arg(card_b.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .assert()
+        .success();
+
+    let journal_dir = archive.child(".folio/journal");
+    let journal = fs::read_dir(journal_dir.path())
+        .unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .next()
+        .unwrap();
+    let card_b_root = fs::canonicalize(card_b.path()).unwrap();
+    assert!(journal.contains("extra_sources"));
+    assert!(journal.contains(card_b_root.to_str().unwrap()));
+}
+
+#[test]
+fn test_sources_with_photos_from_the_same_second_are_both_archived() {
+    // Two cameras fired in the same second: different photos, one timestamp
+    let card_a = assert_fs::TempDir::new().unwrap();
+    let card_b = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    let photo = fs::read(fixtures_dir().join("sample-with-exif.jpg")).unwrap();
+    let mut other = photo.clone();
+    other.extend_from_slice(b"other camera");
+    card_a.child("DSC_0001.jpg").write_binary(&photo).unwrap();
+    card_b.child("IMG_0001.jpg").write_binary(&other).unwrap();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("ingest")
+        .arg("--source")
+        .arg(card_a.path())
+        .arg("--source")
+        .arg(card_b.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Found 2 media files"));
+
+    let day = archive.child("2024/11/04");
+    let mut archived = vec![
+        fs::read(day.path().join("20241104-140215-trip.jpg")).unwrap(),
+        fs::read(day.path().join("20241104-140215-trip.2.jpg")).unwrap(),
+    ];
+    archived.sort();
+    let mut expected = vec![photo, other];
+    expected.sort();
+    assert_eq!(archived, expected);
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::fixtures_dir;
+use predicates::prelude::*;
+use std::fs;
+
+fn source_with_photo() -> assert_fs::TempDir {
+    let source = assert_fs::TempDir::new().unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo.jpg"),
+    )
+    .unwrap();
+    source
+}
+
+fn ingest(source: &std::path::Path, dest: &std::path::Path) -> Command {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source)
+        .arg("--dest")
+        .arg(dest)
+        .arg("--batch-name")
+        .arg("trip");
+    cmd
+}
+
+#[test]
+fn test_ingest_checks_space_before_copying() {
+    let source = source_with_photo();
+    let archive = assert_fs::TempDir::new().unwrap();
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("init")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+    let marker = fs::read_to_string(archive.path().join(".folio/archive.toml")).unwrap();
+
+    ingest(source.path(), archive.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Pre-flight checks passed: 821 B to copy",
+        ));
+
+    // Content already archived needs no space; the archive keeps its id
+    fs::copy(
+        fixtures_dir().join("sample-different-time.jpg"),
+        source.path().join("other.jpg"),
+    )
+    .unwrap();
+    ingest(source.path(), archive.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Pre-flight checks passed: 625 B to copy",
+        ));
+    assert_eq!(
+        fs::read_to_string(archive.path().join(".folio/archive.toml")).unwrap(),
+        marker
+    );
+}
+
+#[test]
+fn test_configured_destination_without_archive_is_refused() {
+    // An unmounted NAS share: the mountpoint is an empty local folder
+    let source = source_with_photo();
+    let mountpoint = assert_fs::TempDir::new().unwrap();
+    let config_dir = assert_fs::TempDir::new().unwrap();
+    config_dir
+        .child("folio.toml")
+        .write_str(&format!(
+            "destination = {:?}\n",
+            mountpoint.path().join("archive").display().to_string()
+        ))
+        .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("FOLIO_CONFIG_DIR", config_dir.path())
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Enter batch name").not())
+        .stderr(predicate::str::contains("is it mounted?"));
+    assert_eq!(fs::read_dir(mountpoint.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_broken_archive_marker_stops_before_any_prompt() {
+    let source = source_with_photo();
+    let archive = assert_fs::TempDir::new().unwrap();
+    archive
+        .child(".folio/archive.toml")
+        .write_str("not an archive marker")
+        .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .write_stdin("trip\n")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Enter batch name").not())
+        .stderr(predicate::str::contains("Invalid archive marker"));
+    archive.child("2024").assert(predicate::path::missing());
+}
+
+#[test]
+fn test_folder_with_other_files_needs_force() {
+    let source = source_with_photo();
+    let downloads = assert_fs::TempDir::new().unwrap();
+    downloads.child("invoice.pdf").write_str("%PDF").unwrap();
+
+    ingest(source.path(), downloads.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is not a folio archive"));
+    downloads.child("2024").assert(predicate::path::missing());
+
+    ingest(source.path(), downloads.path())
+        .arg("--force")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("continuing because of --force"));
+    downloads.child("2024").assert(predicate::path::exists());
+    downloads
+        .child(".folio/archive.toml")
+        .assert(predicate::path::missing());
+}
@@ -0,0 +1,258 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+
+fn ingest(source: &std::path::Path, archive: &std::path::Path, batch: &str) {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source)
+        .arg("--dest")
+        .arg(archive)
+        .arg("--batch-name")
+        .arg(batch)
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_rename_batch_renames_files_sidecars_and_journal() {
+    // Arrange
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    ingest(source.path(), archive.path(), "thanksgving");
+    archive
+        .child("2024/11/04/20241104-140215-thanksgving.xmp")
+        .write_str("<x:xmpmeta/>")
+        .unwrap();
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("rename-batch")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--from")
+        .arg("thanksgving")
+        .arg("--to")
+        .arg("thanksgiving")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Renamed 1 file"));
+
+    // Assert
+    let day = archive.path().join("2024/11/04");
+    assert!(day.join("20241104-140215-thanksgiving.jpg").exists());
+    assert!(day.join("20241104-140215-thanksgiving.xmp").exists());
+    assert!(!day.join("20241104-140215-thanksgving.jpg").exists());
+
+    // Undo still finds the file under its new name
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("undo")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Undid 1 file"));
+    assert!(!day.join("20241104-140215-thanksgiving.jpg").exists());
+}
+
+#[test]
+fn test_rename_batch_respects_date_range() {
+    // Arrange
+    let archive = new_archive();
+    archive
+        .child("2024/11/28/20241128-120000-thanksgving.jpg")
+        .write_str("a")
+        .unwrap();
+    archive
+        .child("2023/11/23/20231123-120000-thanksgving.jpg")
+        .write_str("b")
+        .unwrap();
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("rename-batch")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--from")
+        .arg("thanksgving")
+        .arg("--to")
+        .arg("thanksgiving")
+        .arg("--date-range")
+        .arg("2024-11-01..2024-11-30")
+        .assert()
+        .success();
+
+    // Assert
+    archive
+        .child("2024/11/28/20241128-120000-thanksgiving.jpg")
+        .assert(predicate::path::exists());
+    archive
+        .child("2023/11/23/20231123-120000-thanksgving.jpg")
+        .assert(predicate::path::exists());
+}
+
+#[test]
+fn test_rename_batch_rejects_invalid_name() {
+    // Arrange
+    let archive = new_archive();
+    archive
+        .child("2024/11/28/20241128-120000-thanksgving.jpg")
+        .write_str("a")
+        .unwrap();
+
+    // Act & Assert
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("rename-batch")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--from")
+        .arg("thanksgving")
+        .arg("--to")
+        .arg("thanks giving")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid batch name"));
+    archive
+        .child("2024/11/28/20241128-120000-thanksgving.jpg")
+        .assert(predicate::path::exists());
+}
+
+#[test]
+fn test_rename_batch_follows_archive_layout_and_profile_prefixes() {
+    let source = assert_fs::TempDir::new().unwrap();
+    // Batch folders, and a profile whose batches are named "dad-..."
+    let archive = assert_fs::TempDir::new().unwrap();
+    archive
+        .child("folio.toml")
+        .write_str(
+            "template = \"{year}/{date}_{batch}/{time}.{ext}\"\n\n\
+             [[profile]]\nname = \"Dad\"\nmodel = \"Pixel 8\"\nprefix = \"dad-\"\n",
+        )
+        .unwrap();
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("init")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    ingest(source.path(), archive.path(), "dad-thanksgving");
+
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("rename-batch")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--from")
+        .arg("thanksgving")
+        .arg("--to")
+        .arg("thanksgiving")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Renamed 1 file"));
+
+    archive
+        .child("2024/2024-11-04_dad-thanksgiving/140215.jpg")
+        .assert(predicate::path::exists());
+    archive
+        .child("2024/2024-11-04_dad-thanksgving")
+        .assert(predicate::path::missing());
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("verify")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_rename_batch_fails_when_nothing_matches() {
+    let archive = new_archive();
+    archive
+        .child("2024/11/28/20241128-120000-thanksgiving.jpg")
+        .write_str("a")
+        .unwrap();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("rename-batch")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--from")
+        .arg("thanksgving")
+        .arg("--to")
+        .arg("thanksgiving")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "No files of batch 'thanksgving' found",
+        ));
+}
+
+#[test]
+fn test_rename_batch_retitles_sidecars_and_journal() {
+    // Arrange
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .write_stdin("Thanksgving at Grandma's\n\n")
+        .assert()
+        .success();
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("rename-batch")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--from")
+        .arg("thanksgving-at-grandmas")
+        .arg("--to")
+        .arg("thanksgiving-at-grandmas")
+        .arg("--title")
+        .arg("Thanksgiving at Grandma's")
+        .assert()
+        .success();
+
+    // Assert
+    let sidecar = archive
+        .path()
+        .join("2024/11/04/20241104-140215-thanksgiving-at-grandmas.xmp");
+    let xmp = fs::read_to_string(&sidecar).unwrap();
+    assert!(xmp.contains("Thanksgiving at Grandma's"));
+    assert!(!xmp.contains("Thanksgving"));
+    let journal_dir = archive.path().join(".folio/journal");
+    let journal = fs::read_dir(&journal_dir).unwrap().next().unwrap().unwrap();
+    let journal = fs::read_to_string(journal.path()).unwrap();
+    assert!(journal.contains("\"title\":\"Thanksgiving at Grandma's\""));
+
+    // The rewritten sidecar is still the ingest's, so undo removes it
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("undo")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+    assert!(!sidecar.exists());
+}
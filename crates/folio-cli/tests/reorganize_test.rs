@@ -0,0 +1,206 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+
+/// A legacy tree: Pictures/Lake Tahoe (2024)/photo1.jpg plus a duplicate elsewhere
+fn legacy_tree() -> assert_fs::TempDir {
+    let root = assert_fs::TempDir::new().unwrap();
+    let tahoe = root.path().join("Pictures/Lake Tahoe (2024)");
+    let misc = root.path().join("Pictures/misc");
+    fs::create_dir_all(&tahoe).unwrap();
+    fs::create_dir_all(&misc).unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        tahoe.join("photo1.jpg"),
+    )
+    .unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        misc.join("copy-of-photo1.jpg"),
+    )
+    .unwrap();
+    root
+}
+
+#[test]
+fn test_reorganize_dry_run_reports_mapping() {
+    // Arrange
+    let root = legacy_tree();
+
+    // Act & Assert
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("reorganize")
+        .arg("--archive")
+        .arg(root.path().join("Pictures"))
+        .arg("--into")
+        .arg(root.path().join("archive"))
+        .arg("--batch-from-folders")
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "-> 2024/11/04/20241104-140215-lake-tahoe-2024.jpg",
+        ))
+        .stdout(predicate::str::contains("left in place"))
+        .stdout(predicate::str::contains("Would move 1 file"));
+
+    // Nothing moved
+    assert!(root
+        .path()
+        .join("Pictures/Lake Tahoe (2024)/photo1.jpg")
+        .exists());
+    assert!(!root.path().join("archive").exists());
+}
+
+#[test]
+fn test_reorganize_moves_files_and_rolls_back() {
+    // Arrange
+    let root = legacy_tree();
+    let archive = new_archive();
+
+    // Act
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("reorganize")
+        .arg("--archive")
+        .arg(root.path().join("Pictures"))
+        .arg("--into")
+        .arg(archive.path())
+        .arg("--batch-from-folders")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    // Assert: Moved into the layout, emptied legacy folder pruned
+    let organized = archive
+        .path()
+        .join("2024/11/04/20241104-140215-lake-tahoe-2024.jpg");
+    assert!(organized.exists());
+    assert!(!root.path().join("Pictures/Lake Tahoe (2024)").exists());
+    assert!(root
+        .path()
+        .join("Pictures/misc/copy-of-photo1.jpg")
+        .exists());
+    // The folders' manifests list the moved files
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("verify")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Checked 1 of 1 recorded file"));
+
+    // Roll back with the manifest the run reported
+    let manifest = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Rollback manifest: "))
+        .and_then(|rest| rest.split(" (").next())
+        .unwrap();
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("reorganize")
+        .arg("--rollback")
+        .arg(manifest)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Moved 1 file back"));
+
+    assert!(root
+        .path()
+        .join("Pictures/Lake Tahoe (2024)/photo1.jpg")
+        .exists());
+    assert!(!organized.exists());
+    assert!(!archive
+        .path()
+        .join("2024/11/04/manifest-blake3.txt")
+        .exists());
+}
+
+#[test]
+fn test_reorganize_numbers_files_that_would_share_a_name() {
+    // Two different clips without a capture time, modified in the same second
+    let root = assert_fs::TempDir::new().unwrap();
+    let clips = root.path().join("Pictures/Clips");
+    fs::create_dir_all(&clips).unwrap();
+    let modified =
+        std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_730_728_935);
+    for (fixture, name) in [("minimal.mov", "a.mov"), ("minimal2.mov", "b.mov")] {
+        fs::copy(fixtures_dir().join(fixture), clips.join(name)).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(clips.join(name))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+    let archive = new_archive();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("reorganize")
+        .arg("--archive")
+        .arg(root.path().join("Pictures"))
+        .arg("--into")
+        .arg(archive.path())
+        .arg("--batch-from-folders")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Moved 2 files"));
+
+    let unknown = archive.path().join("unknown-date");
+    assert!(unknown.join("20241104-140215-clips.mov").exists());
+    assert!(unknown.join("20241104-140215-clips.2.mov").exists());
+}
+
+#[test]
+fn test_reorganize_continues_seq_after_files_in_the_layout() {
+    let root = legacy_tree();
+    let archive = new_archive();
+    fs::create_dir_all(archive.path().join("2024")).unwrap();
+    fs::write(archive.path().join("2024/trip-001.jpg"), "ingested earlier").unwrap();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("reorganize")
+        .arg("--archive")
+        .arg(root.path().join("Pictures/Lake Tahoe (2024)"))
+        .arg("--into")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .arg("--template")
+        .arg("{year}/{batch}-{seq}.{ext}")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("-> 2024/trip-002.jpg"));
+
+    assert_eq!(
+        fs::read_to_string(archive.path().join("2024/trip-001.jpg")).unwrap(),
+        "ingested earlier"
+    );
+    assert!(archive.path().join("2024/trip-002.jpg").exists());
+}
+
+#[test]
+fn test_reorganize_keeps_the_title_typed_at_the_prompt() {
+    let root = legacy_tree();
+    let archive = new_archive();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .env("FOLIO_CONFIG_DIR", root.path())
+        .arg("reorganize")
+        .arg("--archive")
+        .arg(root.path().join("Pictures/Lake Tahoe (2024)"))
+        .arg("--into")
+        .arg(archive.path())
+        .write_stdin("Lake Tahoe Trip\ny\n")
+        .assert()
+        .success();
+
+    let sidecar = archive
+        .path()
+        .join("2024/11/04/20241104-140215-lake-tahoe-trip.xmp");
+    assert!(fs::read_to_string(sidecar)
+        .unwrap()
+        .contains("Lake Tahoe Trip"));
+}
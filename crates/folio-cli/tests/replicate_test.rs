@@ -0,0 +1,179 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+
+/// An archive holding the two fixture photos, ingested as one batch
+fn ingested_archive() -> assert_fs::TempDir {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    for name in ["sample-with-exif.jpg", "sample-different-time.jpg"] {
+        fs::copy(fixtures_dir().join(name), source.path().join(name)).unwrap();
+    }
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .assert()
+        .success();
+    archive
+}
+
+fn replicate(archive: &Path, target: &Path, args: &[&str]) -> Command {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("replicate")
+        .arg("--from")
+        .arg(archive)
+        .arg("--to")
+        .arg(target)
+        .args(args);
+    cmd
+}
+
+const PHOTO: &str = "2024/11/04/20241104-140215-trip.jpg";
+const OTHER_PHOTO: &str = "2024/11/04/20241104-181530-trip.jpg";
+
+#[test]
+fn test_replicate_copies_and_verifies_archive() {
+    let archive = ingested_archive();
+    let backup = assert_fs::TempDir::new().unwrap();
+    let target = backup.child("nas-copy");
+
+    replicate(archive.path(), target.path(), &["--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("copy   {}", PHOTO)));
+    target.assert(predicate::path::missing());
+
+    replicate(archive.path(), target.path(), &[])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Copied and verified"));
+    assert_eq!(
+        fs::read(target.path().join(PHOTO)).unwrap(),
+        fs::read(archive.path().join(PHOTO)).unwrap()
+    );
+    // The copy carries the catalog and manifests, so it verifies on its own
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("verify")
+        .arg("--archive")
+        .arg(target.path())
+        .assert()
+        .success();
+
+    // Nothing left to do the second time
+    replicate(archive.path(), target.path(), &[])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Copied and verified 0 files"))
+        .stdout(predicate::str::contains("Drift").not());
+}
+
+#[test]
+fn test_replicate_reports_drift_and_asks_before_deleting() {
+    let archive = ingested_archive();
+    let target = assert_fs::TempDir::new().unwrap();
+    replicate(archive.path(), target.path(), &[])
+        .assert()
+        .success();
+
+    fs::remove_file(archive.path().join(OTHER_PHOTO)).unwrap();
+    fs::write(target.path().join(PHOTO), b"bit rot on the backup").unwrap();
+    fs::write(target.path().join("2024/11/04/stray.jpg"), b"?").unwrap();
+
+    replicate(archive.path(), target.path(), &[])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "changed on target, kept: {}",
+            PHOTO
+        )))
+        .stdout(predicate::str::contains(
+            "only on target: 2024/11/04/stray.jpg",
+        ))
+        .stdout(predicate::str::contains(format!(
+            "deleted from archive: {}",
+            OTHER_PHOTO
+        )))
+        .stdout(predicate::str::contains(
+            "Drift: 1 changed on target, 1 only on target, 1 deleted from archive",
+        ));
+    // Nothing was overwritten or deleted
+    assert_eq!(
+        fs::read(target.path().join(PHOTO)).unwrap(),
+        b"bit rot on the backup"
+    );
+    assert!(target.path().join(OTHER_PHOTO).exists());
+
+    // --delete asks; anything but "y" keeps the file
+    replicate(archive.path(), target.path(), &["--delete"])
+        .write_stdin("n\n")
+        .assert()
+        .success();
+    assert!(target.path().join(OTHER_PHOTO).exists());
+
+    replicate(archive.path(), target.path(), &["--delete"])
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 1 file from the target"));
+    assert!(!target.path().join(OTHER_PHOTO).exists());
+    // Files that never came from the archive are never removed
+    assert!(target.path().join("2024/11/04/stray.jpg").exists());
+}
+
+#[test]
+fn test_replicate_resumes_after_interruption() {
+    let archive = ingested_archive();
+    let target = assert_fs::TempDir::new().unwrap();
+
+    // An earlier run got one photo across and died halfway through the next
+    let day = target.path().join("2024/11/04");
+    fs::create_dir_all(&day).unwrap();
+    fs::copy(archive.path().join(PHOTO), target.path().join(PHOTO)).unwrap();
+    fs::write(
+        day.join(".20241104-181530-trip.jpg.folio-partial"),
+        b"half a photo",
+    )
+    .unwrap();
+
+    replicate(archive.path(), target.path(), &[])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Removed 1 unfinished copy from an interrupted run",
+        ))
+        .stdout(predicate::str::contains(format!("copy   {}", OTHER_PHOTO)))
+        .stdout(predicate::str::contains(format!("copy   {}", PHOTO)).not());
+    assert_eq!(
+        fs::read(target.path().join(OTHER_PHOTO)).unwrap(),
+        fs::read(archive.path().join(OTHER_PHOTO)).unwrap()
+    );
+    assert!(!day.join(".20241104-181530-trip.jpg.folio-partial").exists());
+}
+
+#[test]
+fn test_replicate_refuses_uninitialized_source() {
+    let source = assert_fs::TempDir::new().unwrap();
+    source.child(PHOTO).write_str("photo").unwrap();
+    let target = assert_fs::TempDir::new().unwrap();
+
+    replicate(source.path(), target.path(), &[])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("folio init --archive"));
+    assert!(!target.child(PHOTO).exists());
+
+    replicate(source.path(), target.path(), &["--force"])
+        .assert()
+        .success();
+    target.child(PHOTO).assert("photo");
+}
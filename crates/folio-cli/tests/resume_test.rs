@@ -0,0 +1,238 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+
+fn journals(archive: &std::path::Path) -> Vec<String> {
+    fs::read_dir(archive.join(".folio/journal"))
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|e| fs::read_to_string(e.path()).unwrap())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_completed_ingest_keeps_journal_as_record() {
+    // Arrange
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match("Ingest [0-9-]+ complete").unwrap());
+
+    // Assert: Plan, batch name, finished file and completion are all recorded
+    let journals = journals(archive.path());
+    assert_eq!(journals.len(), 1);
+    assert!(journals[0].contains("\"batch\":\"test-batch\""));
+    assert!(journals[0].contains("\"event\":\"done\""));
+    assert!(journals[0].contains("\"event\":\"completed\""));
+}
+
+#[test]
+fn test_resume_finishes_interrupted_ingest_without_prompts() {
+    // Arrange: A file named "2024" blocks the 2024/ date folder, so the first run stops
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-different-time.jpg"),
+        source.path().join("photo2.jpg"),
+    )
+    .unwrap();
+    archive.child("2024").write_str("not a folder").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("test-batch")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("re-run with --resume"));
+
+    fs::remove_file(archive.path().join("2024")).unwrap();
+
+    // Act: No --batch-name and no stdin; the journal already has the names
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--resume")
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Resuming ingest"))
+        .stdout(predicate::str::contains("Copied 2 files"))
+        .stdout(predicate::str::contains("Enter batch name").not());
+
+    // Assert
+    assert!(archive
+        .path()
+        .join("2024/11/04/20241104-140215-test-batch.jpg")
+        .exists());
+    let journals = journals(archive.path());
+    assert_eq!(journals.len(), 1);
+    assert!(journals[0].contains("\"event\":\"completed\""));
+}
+
+#[test]
+fn test_resume_without_interrupted_ingest_fails() {
+    // Arrange
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+
+    // Act & Assert
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--resume")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No interrupted ingest"));
+}
+
+#[test]
+fn test_resume_keeps_only_its_own_leftovers() {
+    // Arrange: The first run stops before either photo is in place
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-different-time.jpg"),
+        source.path().join("photo2.jpg"),
+    )
+    .unwrap();
+    archive.child("2024").write_str("not a folder").unwrap();
+    let ingest = |resume: bool| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+        cmd.arg("ingest")
+            .arg("--source")
+            .arg(source.path())
+            .arg("--dest")
+            .arg(archive.path())
+            .arg("--batch-name")
+            .arg("test-batch");
+        if resume {
+            cmd.arg("--resume");
+        }
+        cmd
+    };
+    ingest(false).assert().failure();
+    fs::remove_file(archive.path().join("2024")).unwrap();
+
+    // A complete copy the first run left behind, and a file that appeared since
+    // under the other photo's name, as if from an older journal without progress
+    let day = archive.child("2024/11/04");
+    day.create_dir_all().unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        day.path().join("20241104-140215-test-batch.jpg"),
+    )
+    .unwrap();
+    day.child("20241104-181530-test-batch.jpg")
+        .write_str("someone else's photo")
+        .unwrap();
+    let journal = fs::read_dir(archive.path().join(".folio/journal"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let text = fs::read_to_string(&journal).unwrap();
+    // The plan lists files in order; drop the record of placing the 18:15 photo
+    let plan = text.lines().next().unwrap();
+    let index = usize::from(plan.find("181530").unwrap() > plan.find("140215").unwrap());
+    let started = format!("\"event\":\"started\",\"index\":{}}}", index);
+    let kept: String = text
+        .lines()
+        .filter(|line| !line.contains(&started))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    assert_ne!(kept, text);
+    fs::write(&journal, kept).unwrap();
+
+    // Act & Assert: The leftover is kept, the other file is a conflict
+    ingest(true)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+    day.child("20241104-181530-test-batch.jpg")
+        .assert("someone else's photo");
+    assert_eq!(
+        fs::read(day.path().join("20241104-140215-test-batch.jpg")).unwrap(),
+        fs::read(fixtures_dir().join("sample-with-exif.jpg")).unwrap()
+    );
+}
+
+#[test]
+fn test_second_ingest_never_replaces_archived_photo() {
+    // Arrange: Different content that renders to an archived photo's name
+    let archive = new_archive();
+    for content in [&b"first"[..], b"second"] {
+        let source = assert_fs::TempDir::new().unwrap();
+        let mut photo = fs::read(fixtures_dir().join("sample-with-exif.jpg")).unwrap();
+        photo.extend_from_slice(content);
+        source.child("photo.jpg").write_binary(&photo).unwrap();
+
+        Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+            .arg("ingest")
+            .arg("--source")
+            .arg(source.path())
+            .arg("--dest")
+            .arg(archive.path())
+            .arg("--batch-name")
+            .arg("trip")
+            .assert()
+            .success();
+    }
+
+    let day = archive.path().join("2024/11/04");
+    assert!(fs::read(day.join("20241104-140215-trip.jpg"))
+        .unwrap()
+        .ends_with(b"first"));
+    assert!(fs::read(day.join("20241104-140215-trip.2.jpg"))
+        .unwrap()
+        .ends_with(b"second"));
+}
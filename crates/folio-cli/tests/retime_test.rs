@@ -0,0 +1,75 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use common::new_archive;
+use predicates::prelude::*;
+use std::fs;
+
+#[test]
+fn test_retime_moves_batch_to_corrected_date() {
+    // Arrange: An archive with a batch shot just before midnight on a slow clock
+    let archive = new_archive();
+    let day = archive.path().join("2024/11/04");
+    fs::create_dir_all(&day).unwrap();
+    fs::write(day.join("20241104-233000-party.jpg"), b"photo").unwrap();
+    fs::write(day.join("20241104-120000-lunch.jpg"), b"other").unwrap();
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("retime")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--batch")
+        .arg("party")
+        .arg("--shift")
+        .arg("+1h")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Retimed 1 file"));
+
+    // Assert: Renamed into the next day's folder with an XMP sidecar
+    let moved = archive.path().join("2024/11/05/20241105-003000-party.jpg");
+    assert!(moved.exists(), "Expected file at {:?}", moved);
+    assert!(moved.with_extension("xmp").exists());
+    assert!(!day.join("20241104-233000-party.jpg").exists());
+    assert!(day.join("20241104-120000-lunch.jpg").exists());
+}
+
+#[test]
+fn test_retime_dry_run_and_negative_shift() {
+    let archive = new_archive();
+    let day = archive.path().join("2024/11/04");
+    fs::create_dir_all(&day).unwrap();
+    fs::write(day.join("20241104-140000-trip.jpg"), b"photo").unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("retime")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--batch")
+        .arg("trip")
+        .arg("--shift")
+        .arg("-30m")
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("20241104-133000-trip.jpg"))
+        .stdout(predicate::str::contains("Would retime 1 file"));
+
+    // Nothing changed
+    assert!(day.join("20241104-140000-trip.jpg").exists());
+}
+
+#[test]
+fn test_retime_requires_a_selection() {
+    let archive = new_archive();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("retime")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--shift")
+        .arg("+1h")
+        .assert()
+        .failure();
+}
@@ -0,0 +1,325 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Method, Response, Server};
+
+/// What the stand-in server holds and was asked
+#[derive(Default)]
+struct Bucket {
+    objects: HashMap<String, Vec<u8>>,
+    uploads: HashMap<String, BTreeMap<usize, Vec<u8>>>,
+    /// `METHOD /path?query` of every request
+    requests: Vec<String>,
+}
+
+impl Bucket {
+    fn puts(&self) -> usize {
+        self.requests
+            .iter()
+            .filter(|request| request.starts_with("PUT "))
+            .count()
+    }
+}
+
+/// A minimal S3 stand-in on a local port, speaking just what folio uses:
+/// objects, multipart uploads and signed requests with payload hashes
+fn s3_server() -> (String, Arc<Mutex<Bucket>>) {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", server.server_addr());
+    let bucket = Arc::new(Mutex::new(Bucket::default()));
+    let state = bucket.clone();
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = Vec::new();
+            request.as_reader().read_to_end(&mut body).unwrap();
+            let header = |name: &str| {
+                request
+                    .headers()
+                    .iter()
+                    .find(|header| header.field.as_str().as_str().eq_ignore_ascii_case(name))
+                    .map(|header| header.value.to_string())
+            };
+            let signed = header("Authorization")
+                .is_some_and(|auth| auth.starts_with("AWS4-HMAC-SHA256 Credential=test-key/"));
+            let payload_hash: String = Sha256::digest(&body)
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            let (path, query) = request
+                .url()
+                .split_once('?')
+                .map(|(path, query)| (path.to_string(), query.to_string()))
+                .unwrap_or_else(|| (request.url().to_string(), String::new()));
+            let param = |name: &str| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix(&format!("{}=", name)))
+                    .map(str::to_string)
+            };
+
+            let mut bucket = state.lock().unwrap();
+            bucket
+                .requests
+                .push(format!("{} {}", request.method(), request.url()));
+            let response = if !signed {
+                Response::from_string("<Error><Message>Unsigned</Message></Error>")
+                    .with_status_code(403)
+            } else if header("x-amz-content-sha256") != Some(payload_hash.clone()) {
+                Response::from_string("<Error><Message>Bad payload hash</Message></Error>")
+                    .with_status_code(400)
+            } else {
+                match (request.method(), param("uploadId")) {
+                    (Method::Post, None) => {
+                        let id = format!("upload-{}", bucket.uploads.len());
+                        bucket.uploads.insert(id.clone(), BTreeMap::new());
+                        Response::from_string(format!(
+                            "<InitiateMultipartUploadResult><UploadId>{}</UploadId></InitiateMultipartUploadResult>",
+                            id
+                        ))
+                    }
+                    (Method::Put, Some(id)) => {
+                        let number = param("partNumber").unwrap().parse().unwrap();
+                        bucket.uploads.get_mut(&id).unwrap().insert(number, body);
+                        Response::from_string("").with_header(
+                            Header::from_bytes(&b"ETag"[..], format!("\"{}\"", payload_hash))
+                                .unwrap(),
+                        )
+                    }
+                    (Method::Post, Some(id)) => {
+                        let parts = bucket.uploads.remove(&id).unwrap();
+                        bucket
+                            .objects
+                            .insert(path, parts.into_values().flatten().collect());
+                        Response::from_string(
+                            "<CompleteMultipartUploadResult></CompleteMultipartUploadResult>",
+                        )
+                    }
+                    (Method::Delete, Some(id)) => {
+                        bucket.uploads.remove(&id);
+                        Response::from_string("").with_status_code(204)
+                    }
+                    (Method::Put, None) => {
+                        bucket.objects.insert(path, body);
+                        Response::from_string("")
+                    }
+                    (Method::Get | Method::Head, None) => match bucket.objects.get(&path) {
+                        // Sizes are read from Content-Length, which chunked responses lack
+                        Some(data) => {
+                            Response::from_data(data.clone()).with_chunked_threshold(usize::MAX)
+                        }
+                        None => {
+                            Response::from_string("<Error><Message>NoSuchKey</Message></Error>")
+                                .with_status_code(404)
+                        }
+                    },
+                    _ => Response::from_string("").with_status_code(405),
+                }
+            };
+            drop(bucket);
+            let _ = request.respond(response);
+        }
+    });
+    (endpoint, bucket)
+}
+
+/// An archive holding the two fixture photos and the video
+fn ingested_archive() -> assert_fs::TempDir {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    for name in [
+        "sample-with-exif.jpg",
+        "sample-different-time.jpg",
+        "minimal.mov",
+    ] {
+        fs::copy(fixtures_dir().join(name), source.path().join(name)).unwrap();
+    }
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .assert()
+        .success();
+    archive
+}
+
+fn folio(endpoint: &str) -> Command {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.env("AWS_ENDPOINT_URL", endpoint)
+        .env("AWS_REGION", "us-east-1")
+        .env("AWS_ACCESS_KEY_ID", "test-key")
+        .env("AWS_SECRET_ACCESS_KEY", "test-secret")
+        .env_remove("AWS_SESSION_TOKEN");
+    cmd
+}
+
+fn replicate(endpoint: &str, archive: &Path, args: &[&str]) -> Command {
+    let mut cmd = folio(endpoint);
+    cmd.arg("replicate")
+        .arg("--from")
+        .arg(archive)
+        .arg("--to")
+        .arg("s3://backups/family")
+        .args(args);
+    cmd
+}
+
+/// Object key of a fixture's content
+fn object_key(name: &str) -> String {
+    content_key(&fs::read(fixtures_dir().join(name)).unwrap())
+}
+
+fn content_key(content: &[u8]) -> String {
+    let hash = blake3::hash(content).to_hex();
+    format!("/backups/family/blake3/{}/{}", &hash[..2], hash)
+}
+
+const PHOTO: &str = "2024/11/04/20241104-140215-trip.jpg";
+
+#[test]
+fn test_replicate_to_s3_uploads_each_content_once() {
+    let (endpoint, bucket) = s3_server();
+    let archive = ingested_archive();
+
+    replicate(&endpoint, archive.path(), &["--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("upload {}", PHOTO)));
+    assert_eq!(bucket.lock().unwrap().puts(), 0);
+
+    // Large enough for S3's smallest parts
+    let large: Vec<u8> = (0..11 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+    fs::write(archive.path().join("2024/11/04/large.mov"), &large).unwrap();
+    let video = content_key(&large);
+
+    replicate(&endpoint, archive.path(), &["--part-size", "5MB"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Uploaded and verified"));
+    {
+        let bucket = bucket.lock().unwrap();
+        let photo = object_key("sample-with-exif.jpg");
+        assert_eq!(
+            bucket.objects[&photo],
+            fs::read(fixtures_dir().join("sample-with-exif.jpg")).unwrap()
+        );
+        // The large video went up in 5 MB parts
+        assert_eq!(bucket.objects[&video], large);
+        assert!(bucket
+            .requests
+            .contains(&format!("POST {}?uploads=", video)));
+        let parts = bucket
+            .requests
+            .iter()
+            .filter(|request| request.starts_with(&format!("PUT {}?partNumber=", video)))
+            .count();
+        assert_eq!(parts, 3);
+        assert!(bucket.uploads.is_empty());
+        let manifest =
+            String::from_utf8(bucket.objects["/backups/family/manifest-blake3.txt"].clone())
+                .unwrap();
+        assert!(manifest.contains(PHOTO));
+    }
+
+    // Everything is stored already: not a single upload the second time
+    let puts = bucket.lock().unwrap().puts();
+    replicate(&endpoint, archive.path(), &[])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Uploaded and verified 0 files"));
+    assert_eq!(bucket.lock().unwrap().puts(), puts);
+}
+
+#[test]
+fn test_verify_s3_replica_detects_damaged_objects() {
+    let (endpoint, bucket) = s3_server();
+    let archive = ingested_archive();
+    replicate(&endpoint, archive.path(), &[]).assert().success();
+
+    folio(&endpoint)
+        .arg("verify")
+        .arg("--archive")
+        .arg("s3://backups/family")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("✓ Replica verified"));
+
+    bucket
+        .lock()
+        .unwrap()
+        .objects
+        .insert(object_key("sample-with-exif.jpg"), b"bit rot".to_vec());
+    bucket
+        .lock()
+        .unwrap()
+        .objects
+        .remove(&object_key("minimal.mov"));
+    folio(&endpoint)
+        .arg("verify")
+        .arg("--archive")
+        .arg("s3://backups/family")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(format!("corrupted: {}", PHOTO)))
+        .stdout(predicate::str::contains("missing: "))
+        .stderr(predicate::str::contains("Replica verification failed"));
+}
+
+#[test]
+fn test_damaged_archive_file_is_not_uploaded() {
+    let (endpoint, bucket) = s3_server();
+    let archive = ingested_archive();
+    // Same size, different content than recorded at ingest
+    let photo = archive.path().join(PHOTO);
+    let mut data = fs::read(&photo).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 0xff;
+    fs::write(&photo, data).unwrap();
+
+    replicate(&endpoint, archive.path(), &[])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "no longer matches its recorded hash",
+        ));
+    let bucket = bucket.lock().unwrap();
+    assert!(!bucket
+        .objects
+        .contains_key(&object_key("sample-with-exif.jpg")));
+    let manifest =
+        String::from_utf8(bucket.objects["/backups/family/manifest-blake3.txt"].clone()).unwrap();
+    assert!(!manifest.contains(PHOTO));
+    drop(bucket);
+
+    replicate(&endpoint, archive.path(), &["--delete"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--delete is not supported"));
+}
+
+#[test]
+fn test_replicate_refuses_parts_s3_would_reject() {
+    let archive = ingested_archive();
+
+    replicate(
+        "http://127.0.0.1:9",
+        archive.path(),
+        &["--part-size", "4KB"],
+    )
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains(
+        "S3 needs parts of at least 5.0 MB",
+    ));
+}
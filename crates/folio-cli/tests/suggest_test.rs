@@ -0,0 +1,73 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+
+/// An archive with a family birthday on 2015-11-04 and a batch from the same
+/// week a year earlier; sample-with-exif.jpg was taken on 2024-11-04
+fn setup() -> (assert_fs::TempDir, assert_fs::TempDir) {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo.jpg"),
+    )
+    .unwrap();
+    archive
+        .child("family.toml")
+        .write_str("[[birthday]]\nname = \"Emma\"\ndate = \"2015-11-04\"\n")
+        .unwrap();
+    archive
+        .child("2023/11/02/20231102-100000-pumpkin-farm.jpg")
+        .write_binary(b"older")
+        .unwrap();
+    (source, archive)
+}
+
+#[test]
+fn test_ingest_offers_suggestions_selectable_by_number() {
+    let (source, archive) = setup();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .write_stdin("2\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Suggested names:"))
+        .stdout(predicate::str::contains(
+            "[1] emma-birthday (Emma's birthday)",
+        ))
+        .stdout(predicate::str::contains(
+            "[2] pumpkin-farm (used on 2023-11-02)",
+        ));
+
+    archive
+        .child("2024/11/04/20241104-140215-pumpkin-farm.jpg")
+        .assert(predicate::path::exists());
+}
+
+#[test]
+fn test_ingest_typed_name_ignores_suggestions() {
+    let (source, archive) = setup();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .write_stdin("school-play\n")
+        .assert()
+        .success();
+
+    archive
+        .child("2024/11/04/20241104-140215-school-play.jpg")
+        .assert(predicate::path::exists());
+}
@@ -0,0 +1,203 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+
+fn ingest(source: &std::path::Path, archive: &std::path::Path, batch: &str, extra: &[&str]) {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("ingest")
+        .arg("--source")
+        .arg(source)
+        .arg("--dest")
+        .arg(archive)
+        .arg("--batch-name")
+        .arg(batch)
+        .args(extra)
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_undo_removes_last_ingest_and_prunes_folders() {
+    // Arrange: An older ingest that must survive, then a misnamed one
+    let older = assert_fs::TempDir::new().unwrap();
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-different-time.jpg"),
+        older.path().join("photo2.jpg"),
+    )
+    .unwrap();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    ingest(older.path(), archive.path(), "harvest", &[]);
+    let kept: Vec<_> = walkdir::WalkDir::new(archive.path())
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && e.path().extension().unwrap() == "jpg")
+        .map(|e| e.into_path())
+        .collect();
+    ingest(source.path(), archive.path(), "thanksgving", &[]);
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("undo")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Undid 1 file"));
+
+    // Assert: Only the misnamed file is gone, the older ingest is untouched
+    assert!(!archive
+        .path()
+        .join("2024/11/04/20241104-140215-thanksgving.jpg")
+        .exists());
+    assert_eq!(kept.len(), 1);
+    assert!(kept[0].exists());
+    assert!(source.path().join("photo1.jpg").exists());
+}
+
+#[test]
+fn test_undo_refuses_edited_files() {
+    // Arrange
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    ingest(source.path(), archive.path(), "thanksgving", &[]);
+    let archived = archive
+        .path()
+        .join("2024/11/04/20241104-140215-thanksgving.jpg");
+    fs::write(&archived, b"edited in an external editor").unwrap();
+
+    // Act & Assert
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("undo")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("changed since they were archived"));
+    assert!(archived.exists());
+}
+
+#[test]
+fn test_undo_move_ingest_restores_sources() {
+    // Arrange
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    ingest(source.path(), archive.path(), "thanksgving", &["--move"]);
+    assert!(!source.path().join("photo1.jpg").exists());
+
+    // Act
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("undo")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success();
+
+    // Assert
+    assert_eq!(
+        fs::read(source.path().join("photo1.jpg")).unwrap(),
+        fs::read(fixtures_dir().join("sample-with-exif.jpg")).unwrap()
+    );
+    assert!(!archive.path().join("2024").exists());
+}
+
+#[test]
+fn test_undo_unknown_id_fails() {
+    let archive = new_archive();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("undo")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("20200101-000000")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No ingest '20200101-000000'"));
+}
+
+#[test]
+fn test_undo_refuses_sidecars_edited_since_ingest() {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    fs::copy(
+        fixtures_dir().join("sample-with-exif.jpg"),
+        source.path().join("photo1.jpg"),
+    )
+    .unwrap();
+    // The clock correction is recorded in a sidecar
+    ingest(
+        source.path(),
+        archive.path(),
+        "trip",
+        &["--time-shift", "Nikon D800=+1h"],
+    );
+    let sidecar = archive.path().join("2024/11/04/20241104-150215-trip.xmp");
+    let mut xmp = fs::read_to_string(&sidecar).unwrap();
+    xmp.push_str("<!-- keywords added in an editor -->");
+    fs::write(&sidecar, &xmp).unwrap();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("undo")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("changed since"));
+    assert_eq!(fs::read_to_string(&sidecar).unwrap(), xmp);
+    assert!(sidecar.with_extension("jpg").exists());
+}
+
+#[test]
+fn test_files_from_the_same_second_get_their_own_sidecars() {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    let photo = fs::read(fixtures_dir().join("sample-with-exif.jpg")).unwrap();
+    fs::write(source.path().join("photo.jpg"), &photo).unwrap();
+    // Same capture time, different content and extension
+    let mut other = photo;
+    other.extend_from_slice(b"other");
+    fs::write(source.path().join("photo.jpeg"), &other).unwrap();
+    ingest(
+        source.path(),
+        archive.path(),
+        "trip",
+        &["--time-shift", "Nikon D800=+1h"],
+    );
+
+    let day = archive.path().join("2024/11/04");
+    for name in [
+        "20241104-150215-trip.jpg",
+        "20241104-150215-trip.xmp",
+        "20241104-150215-trip.2.jpeg",
+        "20241104-150215-trip.2.xmp",
+    ] {
+        assert!(day.join(name).exists(), "{} is missing", name);
+    }
+
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("undo")
+        .arg("--archive")
+        .arg(archive.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Undid 2 files"));
+    assert!(!archive.path().join("2024").exists());
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use assert_cmd::cmd::Command;
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+
+/// An archive holding the two fixture photos, ingested as one batch
+fn ingested_archive() -> assert_fs::TempDir {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    for name in ["sample-with-exif.jpg", "sample-different-time.jpg"] {
+        fs::copy(fixtures_dir().join(name), source.path().join(name)).unwrap();
+    }
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("ingest")
+        .arg("--source")
+        .arg(source.path())
+        .arg("--dest")
+        .arg(archive.path())
+        .arg("--batch-name")
+        .arg("trip")
+        .assert()
+        .success();
+    archive
+}
+
+fn verify(archive: &Path, args: &[&str]) -> assert_cmd::assert::Assert {
+    Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("verify")
+        .arg("--archive")
+        .arg(archive)
+        .args(args)
+        .assert()
+}
+
+#[test]
+fn test_verify_clean_archive() {
+    let archive = ingested_archive();
+
+    verify(archive.path(), &[])
+        .success()
+        .stdout(predicate::str::contains(
+            "Checked 2 of 2 recorded files: 0 corrupted, 0 missing, 0 unexpected",
+        ))
+        .stdout(predicate::str::contains("Archive verified"));
+    archive
+        .child(".folio/fixity.json")
+        .assert(predicate::path::exists());
+}
+
+#[test]
+fn test_verify_fails_on_bit_rot_missing_and_unexpected_files() {
+    let archive = ingested_archive();
+    let photo = archive.child("2024/11/04/20241104-140215-trip.jpg");
+    let mut data = fs::read(photo.path()).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 0x01;
+    fs::write(photo.path(), data).unwrap();
+    fs::remove_file(archive.path().join("2024/11/04/20241104-181530-trip.jpg")).unwrap();
+    fs::copy(
+        fixtures_dir().join("no-exif.jpg"),
+        archive.path().join("2024/11/04/stray.jpg"),
+    )
+    .unwrap();
+
+    verify(archive.path(), &[])
+        .failure()
+        .stdout(predicate::str::contains(
+            "corrupted: 2024/11/04/20241104-140215-trip.jpg",
+        ))
+        .stdout(predicate::str::contains(
+            "missing: 2024/11/04/20241104-181530-trip.jpg",
+        ))
+        .stdout(predicate::str::contains("unexpected: 2024/11/04/stray.jpg"))
+        .stderr(predicate::str::contains("Archive verification failed"));
+}
+
+#[test]
+fn test_verify_sample_checks_least_recently_verified() {
+    let archive = ingested_archive();
+
+    verify(archive.path(), &["--sample", "50%"])
+        .success()
+        .stdout(predicate::str::contains(
+            "Checked 1 of 2 recorded files (least recently verified first)",
+        ));
+    let first = fs::read_to_string(archive.path().join(".folio/fixity.json")).unwrap();
+
+    // The second run picks the file the first one left out
+    verify(archive.path(), &["--sample", "1"]).success();
+    let second = fs::read_to_string(archive.path().join(".folio/fixity.json")).unwrap();
+    assert_eq!(first.matches("trip.jpg").count(), 1);
+    assert_eq!(second.matches("trip.jpg").count(), 2);
+
+    verify(archive.path(), &["--sample", "lots"]).failure();
+}
+
+#[test]
+fn test_verify_skips_a_journal_with_a_torn_plan() {
+    let archive = ingested_archive();
+    archive
+        .child(".folio/journal/20200101-000000.jsonl")
+        .write_str("{\"event\":\"planned\",\"id\":\"20")
+        .unwrap();
+
+    verify(archive.path(), &[])
+        .success()
+        .stdout(predicate::str::contains("Checked 2 of 2 recorded files"))
+        .stderr(predicate::str::contains("Skipping unreadable journal"));
+}
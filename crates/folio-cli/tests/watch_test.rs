@@ -0,0 +1,188 @@
+mod common;
+
+use assert_fs::prelude::*;
+use common::{fixtures_dir, new_archive};
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant};
+
+fn start_watch(
+    source: &Path,
+    archive: &Path,
+    places: &Path,
+    config_dir: &Path,
+    extra: &[&str],
+) -> Child {
+    std::process::Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .env("FOLIO_PLACES", places)
+        .env("FOLIO_CONFIG_DIR", config_dir)
+        .arg("watch")
+        .arg("--source")
+        .arg(source)
+        .arg("--dest")
+        .arg(archive)
+        .arg("--interval")
+        .arg("1")
+        .args(extra)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+/// Wait until the daemon has logged `count` finished ingests
+fn wait_for_ingests(log: &Path, count: usize) {
+    let ingested =
+        || fs::read_to_string(log).is_ok_and(|text| text.matches("Ingest ").count() >= count);
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while !ingested() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Wait for the daemon to log a finished ingest, then stop it
+fn wait_for_ingest(mut daemon: Child, log: &Path) {
+    wait_for_ingests(log, 1);
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+}
+
+/// Copy a photo in the way sync tools do: written under a hidden name, then renamed
+fn sync_photo(source: &Path, name: &str) {
+    let partial = source.join(format!(".{}.part", name));
+    fs::copy(fixtures_dir().join("sample-with-exif.jpg"), &partial).unwrap();
+    fs::rename(&partial, source.join(name)).unwrap();
+}
+
+#[test]
+fn test_watch_ingests_new_file_under_placeholder_and_logs() {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    let config_dir = assert_fs::TempDir::new().unwrap();
+    let places = assert_fs::NamedTempFile::new("places.tsv").unwrap();
+    places.write_str("").unwrap();
+
+    let daemon = start_watch(
+        source.path(),
+        archive.path(),
+        places.path(),
+        config_dir.path(),
+        &[],
+    );
+    std::thread::sleep(Duration::from_millis(500));
+    sync_photo(source.path(), "PXL_0001.jpg");
+
+    let log = archive.child(".folio/logs/watch.log");
+    wait_for_ingest(daemon, log.path());
+
+    archive
+        .child("2024/11/04/20241104-140215-inbox-20241104.jpg")
+        .assert(predicate::path::exists());
+    archive
+        .child(".folio/rename-queue.tsv")
+        .assert(predicate::str::contains(
+            "inbox-20241104\t2024-11-04..2024-11-04",
+        ));
+    log.assert(predicate::str::contains(
+        "archived 1 file as inbox-20241104",
+    ));
+
+    // Naming the placeholder takes it off the queue
+    let mut cmd = assert_cmd::cmd::Command::new(assert_cmd::cargo::cargo_bin!("folio"));
+    cmd.arg("rename-batch")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--from")
+        .arg("inbox-20241104")
+        .arg("--to")
+        .arg("errands")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Removed 'inbox-20241104' from the rename queue",
+        ));
+    archive
+        .child(".folio/rename-queue.tsv")
+        .assert(predicate::str::contains("inbox").not());
+}
+
+#[test]
+fn test_watch_polling_names_batch_from_rules() {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = new_archive();
+    let config_dir = assert_fs::TempDir::new().unwrap();
+    let log = assert_fs::NamedTempFile::new("watch.log").unwrap();
+    // sample-with-exif.jpg carries GPS 40.7128, 74.0060
+    let places = assert_fs::NamedTempFile::new("places.tsv").unwrap();
+    places
+        .write_str("Testville\tTest Region\tTestland\t40.70\t74.01\n")
+        .unwrap();
+    // Files already in the folder are picked up too
+    sync_photo(source.path(), "PXL_0001.jpg");
+
+    let daemon = start_watch(
+        source.path(),
+        archive.path(),
+        places.path(),
+        config_dir.path(),
+        &["--poll", "--log", log.path().to_str().unwrap()],
+    );
+
+    wait_for_ingest(daemon, log.path());
+
+    archive
+        .child("2024/11/04/20241104-140215-testville.jpg")
+        .assert(predicate::path::exists());
+    log.assert(predicate::str::contains("polling every 1s"));
+    archive
+        .child(".folio/rename-queue.tsv")
+        .assert(predicate::path::missing());
+}
+
+#[test]
+fn test_watch_continues_seq_across_intervals() {
+    let source = assert_fs::TempDir::new().unwrap();
+    let archive = assert_fs::TempDir::new().unwrap();
+    let config_dir = assert_fs::TempDir::new().unwrap();
+    let places = assert_fs::NamedTempFile::new("places.tsv").unwrap();
+    places.write_str("").unwrap();
+    assert_cmd::cmd::Command::new(assert_cmd::cargo::cargo_bin!("folio"))
+        .arg("init")
+        .arg("--archive")
+        .arg(archive.path())
+        .arg("--template")
+        .arg("{year}/{batch}-{seq}.{ext}")
+        .assert()
+        .success();
+
+    let mut daemon = start_watch(
+        source.path(),
+        archive.path(),
+        places.path(),
+        config_dir.path(),
+        &["--poll"],
+    );
+    let log = archive.child(".folio/logs/watch.log");
+    sync_photo(source.path(), "PXL_0001.jpg");
+    wait_for_ingests(log.path(), 1);
+
+    // A different photo from the same day lands in the same batch later on
+    let mut photo = fs::read(fixtures_dir().join("sample-with-exif.jpg")).unwrap();
+    photo.extend_from_slice(b"second interval");
+    let partial = source.path().join(".PXL_0002.jpg.part");
+    fs::write(&partial, &photo).unwrap();
+    fs::rename(&partial, source.path().join("PXL_0002.jpg")).unwrap();
+    wait_for_ingests(log.path(), 2);
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+
+    archive
+        .child("2024/inbox-20241104-001.jpg")
+        .assert(predicate::path::exists());
+    assert_eq!(
+        fs::read(archive.path().join("2024/inbox-20241104-002.jpg")).unwrap(),
+        photo
+    );
+}
@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Marks a directory as a folio archive, relative to the archive root
+pub const ARCHIVE_MARKER: &str = ".folio/archive.toml";
+
+/// Version of the archive's layout of `.folio/`, bumped on incompatible changes
+pub const ARCHIVE_SCHEMA: u32 = 1;
+
+/// The identity of an archive, kept in `.folio/archive.toml` by `folio init`
+///
+/// Commands that change an archive check for it first, so an unmounted drive
+/// or a mistyped path isn't silently filled as if it were the archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveMarker {
+    /// Random UUID, stays the same when the archive is moved or copied
+    pub id: String,
+    #[serde(default = "first_schema")]
+    pub schema: u32,
+    pub created: DateTime<Utc>,
+}
+
+fn first_schema() -> u32 {
+    1
+}
+
+impl ArchiveMarker {
+    /// The marker of the archive at `root`, if it has one; an archive of a
+    /// newer schema than this version of folio knows is an error
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        let path = root.join(ARCHIVE_MARKER);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let marker: ArchiveMarker =
+            toml::from_str(&text).with_context(|| format!("Invalid archive marker {:?}", path))?;
+        if marker.schema > ARCHIVE_SCHEMA {
+            anyhow::bail!(
+                "{} was created by a newer version of folio (archive schema {}, this version knows {})",
+                root.display(),
+                marker.schema,
+                ARCHIVE_SCHEMA
+            );
+        }
+        Ok(Some(marker))
+    }
+
+    /// Mark `root` as a new archive with a fresh id
+    pub fn create(root: &Path) -> Result<Self> {
+        let marker = ArchiveMarker {
+            id: uuid::Uuid::new_v4().to_string(),
+            schema: ARCHIVE_SCHEMA,
+            created: Utc::now(),
+        };
+        let path = root.join(ARCHIVE_MARKER);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        }
+        // Written in full before it appears, so an archive is never marked
+        // with a torn marker
+        let partial = path.with_extension("toml.partial");
+        fs::write(&partial, toml::to_string(&marker)?)
+            .with_context(|| format!("Failed to write {:?}", partial))?;
+        fs::rename(&partial, &path).with_context(|| format!("Failed to write {:?}", path))?;
+        Ok(marker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_marker_round_trip() {
+        let root = TempDir::new().unwrap();
+        assert_eq!(ArchiveMarker::load(root.path()).unwrap(), None);
+
+        let marker = ArchiveMarker::create(root.path()).unwrap();
+        assert_eq!(marker.id.len(), 36);
+        assert_eq!(ArchiveMarker::load(root.path()).unwrap(), Some(marker));
+
+        fs::write(root.path().join(ARCHIVE_MARKER), "id = 5\n").unwrap();
+        assert!(ArchiveMarker::load(root.path()).is_err());
+
+        // Markers without a schema are the first one
+        let created = "created = \"2024-11-01T00:00:00Z\"\n";
+        fs::write(
+            root.path().join(ARCHIVE_MARKER),
+            format!("id = \"a\"\n{}", created),
+        )
+        .unwrap();
+        assert_eq!(ArchiveMarker::load(root.path()).unwrap().unwrap().schema, 1);
+        fs::write(
+            root.path().join(ARCHIVE_MARKER),
+            format!("id = \"a\"\nschema = 99\n{}", created),
+        )
+        .unwrap();
+        let error = ArchiveMarker::load(root.path()).unwrap_err();
+        assert!(error.to_string().contains("newer version of folio"));
+    }
+}
@@ -0,0 +1,569 @@
+use crate::media::{generate_folder_path, group_by_temporal_proximity, MediaItem, TemporalBatch};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::BTreeMap;
+
+/// A strategy for grouping media items into batches (one batch per event)
+///
+/// Implementations only see items that have a timestamp; items without one
+/// are left out of every batch and must be collected with `undated_items`.
+pub trait BatchingStrategy {
+    /// Short name used on the command line (e.g. "fixed-gap")
+    fn name(&self) -> &'static str;
+
+    /// Human-readable summary of the strategy settings
+    fn describe(&self) -> String;
+
+    /// Group items into batches, ordered by start time
+    fn group(&self, items: &[MediaItem]) -> Vec<TemporalBatch>;
+}
+
+/// Fixed gap: a new batch starts when consecutive items are further apart
+/// than the gap threshold (see `group_by_temporal_proximity`)
+#[derive(Debug, Clone)]
+pub struct FixedGap {
+    pub gap_threshold: Duration,
+}
+
+impl BatchingStrategy for FixedGap {
+    fn name(&self) -> &'static str {
+        "fixed-gap"
+    }
+
+    fn describe(&self) -> String {
+        format!("gap threshold: {:.1} hours", hours(self.gap_threshold))
+    }
+
+    fn group(&self, items: &[MediaItem]) -> Vec<TemporalBatch> {
+        group_by_temporal_proximity(items, self.gap_threshold)
+    }
+}
+
+/// Calendar day: one batch per date of capture
+#[derive(Debug, Clone, Default)]
+pub struct CalendarDay;
+
+impl BatchingStrategy for CalendarDay {
+    fn name(&self) -> &'static str {
+        "calendar-day"
+    }
+
+    fn describe(&self) -> String {
+        "one batch per calendar day".to_string()
+    }
+
+    fn group(&self, items: &[MediaItem]) -> Vec<TemporalBatch> {
+        split_by_timestamp(items, |prev, next| {
+            prev.timestamp.map(|t| t.date_naive()) != next.timestamp.map(|t| t.date_naive())
+        })
+    }
+}
+
+/// Adaptive gap: the split threshold follows the local shooting rate
+///
+/// A gap splits two items when it is larger than `factor` times the median
+/// of the surrounding gaps, so a burst of photos a few seconds apart is split
+/// by a pause of minutes, while a slow afternoon walk is not. Gaps shorter
+/// than `min_gap` never split and gaps longer than `max_gap` always do.
+#[derive(Debug, Clone)]
+pub struct AdaptiveGap {
+    pub min_gap: Duration,
+    pub max_gap: Duration,
+    pub factor: f64,
+}
+
+impl AdaptiveGap {
+    /// Number of neighbouring gaps on each side used to estimate the local rate
+    const WINDOW: usize = 5;
+}
+
+impl BatchingStrategy for AdaptiveGap {
+    fn name(&self) -> &'static str {
+        "adaptive"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "adaptive gap: {:.1}x local rate, between {:.1} and {:.1} hours",
+            self.factor,
+            hours(self.min_gap),
+            hours(self.max_gap)
+        )
+    }
+
+    fn group(&self, items: &[MediaItem]) -> Vec<TemporalBatch> {
+        let sorted = sorted_by_timestamp(items);
+
+        // Gap i is the time between item i and item i + 1
+        let gaps: Vec<Duration> = sorted
+            .windows(2)
+            .map(|pair| pair[1].timestamp.unwrap() - pair[0].timestamp.unwrap())
+            .collect();
+
+        let splits: Vec<bool> = (0..gaps.len())
+            .map(|i| {
+                let gap = gaps[i];
+                if gap <= self.min_gap {
+                    return false;
+                }
+                if gap > self.max_gap {
+                    return true;
+                }
+
+                let start = i.saturating_sub(Self::WINDOW);
+                let end = (i + Self::WINDOW + 1).min(gaps.len());
+                let mut neighbours: Vec<Duration> = gaps[start..end]
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| start + j != i)
+                    .map(|(_, g)| *g)
+                    .collect();
+                if neighbours.is_empty() {
+                    // A single gap has no local rate - only the bounds apply
+                    return false;
+                }
+                neighbours.sort();
+                let median = neighbours[neighbours.len() / 2];
+
+                gap.num_seconds() as f64 > median.num_seconds() as f64 * self.factor
+            })
+            .collect();
+
+        build_batches(sorted, |index| splits[index - 1])
+    }
+}
+
+/// GPS distance: a new batch starts when the camera moved further than
+/// `max_distance_km` from the previous located item, or when the time gap
+/// exceeds `gap_threshold`
+///
+/// Items without GPS stay in the current batch.
+#[derive(Debug, Clone)]
+pub struct GpsDistance {
+    pub max_distance_km: f64,
+    pub gap_threshold: Duration,
+}
+
+impl BatchingStrategy for GpsDistance {
+    fn name(&self) -> &'static str {
+        "gps"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "distance threshold: {:.1} km, gap threshold: {:.1} hours",
+            self.max_distance_km,
+            hours(self.gap_threshold)
+        )
+    }
+
+    fn group(&self, items: &[MediaItem]) -> Vec<TemporalBatch> {
+        let sorted = sorted_by_timestamp(items);
+
+        let mut last_position = None;
+        let mut splits = Vec::with_capacity(sorted.len());
+        for (index, item) in sorted.iter().enumerate() {
+            let mut split = false;
+            if index > 0 {
+                let gap = item.timestamp.unwrap() - sorted[index - 1].timestamp.unwrap();
+                split = gap > self.gap_threshold;
+            }
+            if let Some(position) = item.gps {
+                if let Some(previous) = last_position {
+                    split |= position.distance_km(&previous) > self.max_distance_km;
+                }
+                last_position = Some(position);
+            }
+            splits.push(split);
+        }
+
+        build_batches(sorted, |index| splits[index])
+    }
+}
+
+/// Per camera: items from different cameras never share a batch
+///
+/// Items are partitioned by camera label and each camera's items are grouped
+/// with the inner strategy. Items without camera metadata form their own group.
+pub struct PerCamera {
+    pub inner: Box<dyn BatchingStrategy>,
+}
+
+impl BatchingStrategy for PerCamera {
+    fn name(&self) -> &'static str {
+        "per-camera"
+    }
+
+    fn describe(&self) -> String {
+        format!("per camera, {}", self.inner.describe())
+    }
+
+    fn group(&self, items: &[MediaItem]) -> Vec<TemporalBatch> {
+        let mut by_camera: BTreeMap<Option<String>, Vec<MediaItem>> = BTreeMap::new();
+        for item in items {
+            let label = item.camera.as_ref().map(|camera| camera.label());
+            by_camera.entry(label).or_default().push(item.clone());
+        }
+
+        let mut batches: Vec<TemporalBatch> = by_camera
+            .values()
+            .flat_map(|camera_items| self.inner.group(camera_items))
+            .collect();
+        batches.sort_by_key(|batch| batch.start_time);
+        batches
+    }
+}
+
+/// How to place items that have no capture timestamp
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UndatedResolution {
+    /// Assign a user-supplied date
+    ManualDate(NaiveDate),
+    /// Borrow the timestamp of the nearest dated file in filename order
+    BorrowFromNeighbors,
+    /// Leave them undated, filed under `unknown-date`
+    UnknownDate,
+}
+
+/// Collect the items that batching strategies skip because they have no timestamp
+pub fn undated_items(items: &[MediaItem]) -> Vec<MediaItem> {
+    items
+        .iter()
+        .filter(|item| item.timestamp.is_none())
+        .cloned()
+        .collect()
+}
+
+/// Apply an undated resolution, returning the items with timestamps and folder paths updated
+///
+/// `ManualDate` gives the items consecutive seconds from midnight of that date
+/// (in filename order) so their generated filenames stay unique.
+/// `BorrowFromNeighbors` sorts undated and dated items together by filename
+/// and copies the timestamp of the closest dated neighbour, preferring the
+/// previous file; items with no dated neighbour at all stay undated.
+pub fn resolve_undated(
+    undated: &[MediaItem],
+    dated: &[MediaItem],
+    resolution: &UndatedResolution,
+) -> Vec<MediaItem> {
+    let mut resolved: Vec<MediaItem> = undated.to_vec();
+    resolved.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
+
+    match resolution {
+        UndatedResolution::ManualDate(date) => {
+            let midnight = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+            for (offset, item) in resolved.iter_mut().enumerate() {
+                set_timestamp(item, Some(midnight + Duration::seconds(offset as i64)));
+            }
+        }
+        UndatedResolution::BorrowFromNeighbors => {
+            let mut sequence: Vec<(&std::ffi::OsStr, DateTime<Utc>)> = dated
+                .iter()
+                .filter_map(|item| Some((item.path.file_name()?, item.timestamp?)))
+                .collect();
+            sequence.sort();
+
+            for item in resolved.iter_mut() {
+                let Some(name) = item.path.file_name() else {
+                    continue;
+                };
+                let position = sequence.partition_point(|(dated_name, _)| *dated_name < name);
+                let neighbour = position
+                    .checked_sub(1)
+                    .and_then(|previous| sequence.get(previous))
+                    .or_else(|| sequence.get(position))
+                    .map(|(_, timestamp)| *timestamp);
+                if neighbour.is_some() {
+                    set_timestamp(item, neighbour);
+                }
+            }
+        }
+        UndatedResolution::UnknownDate => {}
+    }
+
+    resolved
+}
+
+fn set_timestamp(item: &mut MediaItem, timestamp: Option<DateTime<Utc>>) {
+    item.timestamp = timestamp;
+    if let Some(timestamp) = timestamp {
+        item.folder_path = generate_folder_path(timestamp);
+    }
+}
+
+fn hours(duration: Duration) -> f64 {
+    duration.num_seconds() as f64 / 3600.0
+}
+
+/// Clone the items that have a timestamp, sorted by timestamp
+fn sorted_by_timestamp(items: &[MediaItem]) -> Vec<MediaItem> {
+    let mut sorted: Vec<MediaItem> = items
+        .iter()
+        .filter(|item| item.timestamp.is_some())
+        .cloned()
+        .collect();
+    sorted.sort_by_key(|item| item.timestamp);
+    sorted
+}
+
+/// Sort items by timestamp and split wherever `is_boundary(prev, next)` holds
+fn split_by_timestamp(
+    items: &[MediaItem],
+    is_boundary: impl Fn(&MediaItem, &MediaItem) -> bool,
+) -> Vec<TemporalBatch> {
+    let sorted = sorted_by_timestamp(items);
+    let boundaries: Vec<bool> = (0..sorted.len())
+        .map(|index| index > 0 && is_boundary(&sorted[index - 1], &sorted[index]))
+        .collect();
+    build_batches(sorted, |index| boundaries[index])
+}
+
+/// Build batches from sorted, timestamped items
+/// `starts_batch(index)` is consulted for every item after the first
+fn build_batches(
+    sorted: Vec<MediaItem>,
+    starts_batch: impl Fn(usize) -> bool,
+) -> Vec<TemporalBatch> {
+    let mut batches: Vec<TemporalBatch> = Vec::new();
+    let mut current: Vec<MediaItem> = Vec::new();
+
+    for (index, item) in sorted.into_iter().enumerate() {
+        if index > 0 && starts_batch(index) {
+            batches.extend(finish_batch(std::mem::take(&mut current)));
+        }
+        current.push(item);
+    }
+    batches.extend(finish_batch(current));
+
+    batches
+}
+
+fn finish_batch(items: Vec<MediaItem>) -> Option<TemporalBatch> {
+    let start_time = items.first()?.timestamp?;
+    let end_time = items.last()?.timestamp?;
+    Some(TemporalBatch {
+        start_time,
+        end_time,
+        items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::{generate_folder_path, CameraInfo, GpsCoordinates, MediaType, PhotoFormat};
+    use chrono::{DateTime, Utc};
+    use std::path::PathBuf;
+
+    fn item(name: &str, timestamp: &str) -> MediaItem {
+        let timestamp = DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc);
+        MediaItem {
+            path: PathBuf::from(name),
+            hash: blake3::hash(name.as_bytes()),
+            size: 1000,
+            media_type: MediaType::Photo(PhotoFormat::Jpeg),
+            timestamp: Some(timestamp),
+            folder_path: generate_folder_path(timestamp),
+            camera: None,
+            gps: None,
+        }
+    }
+
+    fn with_camera(mut item: MediaItem, model: &str) -> MediaItem {
+        item.camera = Some(CameraInfo {
+            make: None,
+            model: Some(model.to_string()),
+            serial: None,
+        });
+        item
+    }
+
+    fn with_gps(mut item: MediaItem, latitude: f64, longitude: f64) -> MediaItem {
+        item.gps = Some(GpsCoordinates {
+            latitude,
+            longitude,
+        });
+        item
+    }
+
+    fn undated(name: &str) -> MediaItem {
+        let mut item = item(name, "2024-01-01T00:00:00Z");
+        item.timestamp = None;
+        item.folder_path = PathBuf::from("unknown-date");
+        item
+    }
+
+    fn batch_sizes(batches: &[TemporalBatch]) -> Vec<usize> {
+        batches.iter().map(|b| b.items.len()).collect()
+    }
+
+    #[test]
+    fn test_fixed_gap_matches_temporal_proximity() {
+        let items = vec![
+            item("a.jpg", "2024-11-04T09:00:00Z"),
+            item("b.jpg", "2024-11-04T09:30:00Z"),
+            item("c.jpg", "2024-11-04T14:00:00Z"),
+        ];
+        let strategy = FixedGap {
+            gap_threshold: Duration::hours(2),
+        };
+
+        assert_eq!(batch_sizes(&strategy.group(&items)), vec![2, 1]);
+        assert_eq!(strategy.describe(), "gap threshold: 2.0 hours");
+    }
+
+    #[test]
+    fn test_calendar_day_splits_at_midnight() {
+        let items = vec![
+            item("a.jpg", "2024-11-04T23:50:00Z"),
+            item("b.jpg", "2024-11-05T00:10:00Z"),
+            item("c.jpg", "2024-11-05T18:00:00Z"),
+        ];
+
+        let batches = CalendarDay.group(&items);
+
+        assert_eq!(batch_sizes(&batches), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_adaptive_gap_splits_burst_on_pause() {
+        // A burst every 10 seconds, a 5 minute pause, then another burst
+        let items = vec![
+            item("a.jpg", "2024-11-04T14:00:00Z"),
+            item("b.jpg", "2024-11-04T14:00:10Z"),
+            item("c.jpg", "2024-11-04T14:00:20Z"),
+            item("d.jpg", "2024-11-04T14:05:20Z"),
+            item("e.jpg", "2024-11-04T14:05:30Z"),
+            item("f.jpg", "2024-11-04T14:05:40Z"),
+        ];
+        let strategy = AdaptiveGap {
+            min_gap: Duration::minutes(1),
+            max_gap: Duration::hours(4),
+            factor: 5.0,
+        };
+
+        assert_eq!(batch_sizes(&strategy.group(&items)), vec![3, 3]);
+    }
+
+    #[test]
+    fn test_adaptive_gap_respects_min_and_max() {
+        let items = vec![
+            item("a.jpg", "2024-11-04T14:00:00Z"),
+            item("b.jpg", "2024-11-04T14:00:01Z"),
+            item("c.jpg", "2024-11-04T14:00:30Z"),
+            item("d.jpg", "2024-11-04T20:00:00Z"),
+        ];
+        let strategy = AdaptiveGap {
+            min_gap: Duration::minutes(1),
+            max_gap: Duration::hours(4),
+            factor: 5.0,
+        };
+
+        // 29s jump is below min_gap, 6h jump is above max_gap
+        assert_eq!(batch_sizes(&strategy.group(&items)), vec![3, 1]);
+    }
+
+    #[test]
+    fn test_gps_distance_splits_on_travel() {
+        // Lake Tahoe, then Reno (~40 km away) an hour later
+        let items = vec![
+            with_gps(item("a.jpg", "2024-07-04T10:00:00Z"), 39.0968, -120.0324),
+            item("b.jpg", "2024-07-04T10:15:00Z"),
+            with_gps(item("c.jpg", "2024-07-04T10:30:00Z"), 39.0970, -120.0330),
+            with_gps(item("d.jpg", "2024-07-04T11:30:00Z"), 39.5296, -119.8138),
+        ];
+        let strategy = GpsDistance {
+            max_distance_km: 5.0,
+            gap_threshold: Duration::hours(4),
+        };
+
+        assert_eq!(batch_sizes(&strategy.group(&items)), vec![3, 1]);
+    }
+
+    #[test]
+    fn test_per_camera_separates_devices() {
+        let items = vec![
+            with_camera(item("a.jpg", "2024-11-04T14:00:00Z"), "D800"),
+            with_camera(item("b.jpg", "2024-11-04T14:05:00Z"), "iPhone 15"),
+            with_camera(item("c.jpg", "2024-11-04T14:10:00Z"), "D800"),
+            item("d.jpg", "2024-11-04T14:15:00Z"),
+        ];
+        let strategy = PerCamera {
+            inner: Box::new(FixedGap {
+                gap_threshold: Duration::hours(2),
+            }),
+        };
+
+        let batches = strategy.group(&items);
+
+        assert_eq!(batch_sizes(&batches), vec![2, 1, 1]);
+        assert_eq!(batches[0].items[0].path, PathBuf::from("a.jpg"));
+    }
+
+    #[test]
+    fn test_undated_items_are_collected_not_dropped() {
+        let items = vec![
+            item("DSC_0001.jpg", "2024-11-04T14:00:00Z"),
+            undated("DSC_0002.jpg"),
+        ];
+
+        let batches = CalendarDay.group(&items);
+        let undated = undated_items(&items);
+
+        assert_eq!(batch_sizes(&batches), vec![1]);
+        assert_eq!(undated.len(), 1);
+        assert_eq!(undated[0].path, PathBuf::from("DSC_0002.jpg"));
+    }
+
+    #[test]
+    fn test_resolve_undated_manual_date() {
+        let undated = vec![undated("b.jpg"), undated("a.jpg")];
+        let date = NaiveDate::from_ymd_opt(2019, 6, 1).unwrap();
+
+        let resolved = resolve_undated(&undated, &[], &UndatedResolution::ManualDate(date));
+
+        assert_eq!(resolved[0].path, PathBuf::from("a.jpg"));
+        assert_eq!(
+            resolved[0].timestamp.unwrap().to_rfc3339(),
+            "2019-06-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            resolved[1].timestamp.unwrap().to_rfc3339(),
+            "2019-06-01T00:00:01+00:00"
+        );
+        assert_eq!(resolved[1].folder_path, PathBuf::from("2019/06/01"));
+    }
+
+    #[test]
+    fn test_resolve_undated_borrows_neighbor_by_filename() {
+        let dated = vec![
+            item("DSC_0001.jpg", "2024-11-04T14:00:00Z"),
+            item("DSC_0005.jpg", "2024-11-05T09:00:00Z"),
+        ];
+        let undated = vec![
+            undated("DSC_0000.jpg"),
+            undated("DSC_0003.jpg"),
+            undated("DSC_0007.jpg"),
+        ];
+
+        let resolved = resolve_undated(&undated, &dated, &UndatedResolution::BorrowFromNeighbors);
+
+        // No previous file: borrow from the next one
+        assert_eq!(resolved[0].folder_path, PathBuf::from("2024/11/04"));
+        // Previous file wins
+        assert_eq!(resolved[1].folder_path, PathBuf::from("2024/11/04"));
+        assert_eq!(resolved[2].folder_path, PathBuf::from("2024/11/05"));
+    }
+
+    #[test]
+    fn test_resolve_undated_unknown_date_keeps_items() {
+        let undated = vec![undated("a.jpg")];
+
+        let resolved = resolve_undated(&undated, &[], &UndatedResolution::UnknownDate);
+
+        assert_eq!(resolved.len(), 1);
+        assert!(resolved[0].timestamp.is_none());
+        assert_eq!(resolved[0].folder_path, PathBuf::from("unknown-date"));
+    }
+}
@@ -0,0 +1,427 @@
+use crate::filter::{deserialize_size, ScanFilter};
+use crate::media::{validate_batch_name, CameraInfo};
+use crate::template::PathTemplate;
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Name of the configuration file looked up in the archive root
+pub const CONFIG_FILE_NAME: &str = "folio.toml";
+
+/// Name of the family configuration file looked up in the archive root
+pub const FAMILY_FILE_NAME: &str = "family.toml";
+
+/// Environment variable naming the user configuration directory, overriding
+/// the platform default (e.g. `~/.config/folio`)
+pub const CONFIG_DIR_ENV: &str = "FOLIO_CONFIG_DIR";
+
+/// Folio configuration (`folio.toml`), from the archive root or the user
+/// configuration directory
+///
+/// ```toml
+/// destination = "/archive"
+/// template = "{year}/{date}_{batch}/{date}-{time}-{batch}-{seq}.{ext}"
+/// gap_threshold = 3.0
+/// timezone = "America/Los_Angeles"
+///
+/// [scan]
+/// exclude = ["Screenshots", "*.tmp"]
+/// min_size = "20k"
+///
+/// [[profile]]
+/// name = "Dad's phone"
+/// model = "iPhone 13"
+/// timezone = "America/Chicago"
+/// prefix = "dad-"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FolioConfig {
+    /// Archive to ingest into when no destination is given
+    pub destination: Option<PathBuf>,
+    /// Folder and filename layout for ingested files
+    pub template: Option<PathTemplate>,
+    /// Hours between shots that start a new batch
+    pub gap_threshold: Option<f64>,
+    /// Time zone archive file names are in; device times are converted to it
+    pub timezone: Option<Tz>,
+    /// Where removable media gets mounted, searched by `ingest --auto`
+    pub mount_root: Option<PathBuf>,
+    /// Which files scans pick up
+    #[serde(default)]
+    pub scan: ScanConfig,
+    /// Per-device settings, the first matching profile applies
+    #[serde(default, rename = "profile")]
+    pub profiles: Vec<DeviceProfile>,
+}
+
+/// Include/exclude globs and a minimum size for scans, see `ScanFilter`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScanConfig {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Bytes, or a size like "20k"
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub min_size: Option<u64>,
+    /// Set to false to also scan trash folders, thumbnail caches and the like
+    pub default_excludes: Option<bool>,
+}
+
+impl ScanConfig {
+    /// The filter these settings describe, plus extra globs (e.g. from the command line)
+    pub fn filter(&self, include: &[String], exclude: &[String]) -> Result<ScanFilter> {
+        let include: Vec<String> = self.include.iter().chain(include).cloned().collect();
+        let exclude: Vec<String> = self.exclude.iter().chain(exclude).cloned().collect();
+        let mut filter =
+            ScanFilter::new(&include, &exclude, self.default_excludes.unwrap_or(true))?;
+        filter.min_size = self.min_size.unwrap_or(0);
+        Ok(filter)
+    }
+}
+
+/// Settings for one device, matched by camera make/model/serial or by the
+/// label of the volume it is ingested from
+///
+/// Every criterion given must match; at least one is required.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceProfile {
+    pub name: String,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    /// Volume label of the card or drive, e.g. "DAD_SD"
+    pub volume: Option<String>,
+    /// Time zone the device's clock is set to
+    pub timezone: Option<Tz>,
+    /// Prepended to batch names of the device's files, e.g. "dad-"
+    pub prefix: Option<String>,
+}
+
+impl DeviceProfile {
+    /// Whether the profile applies to a file from `camera` ingested from `volume`
+    pub fn matches(&self, camera: Option<&CameraInfo>, volume: Option<&str>) -> bool {
+        let same = |expected: &Option<String>, actual: Option<&String>| match expected {
+            Some(expected) => actual.is_some_and(|actual| actual.eq_ignore_ascii_case(expected)),
+            None => true,
+        };
+
+        same(&self.make, camera.and_then(|c| c.make.as_ref()))
+            && same(&self.model, camera.and_then(|c| c.model.as_ref()))
+            && same(&self.serial, camera.and_then(|c| c.serial.as_ref()))
+            && same(&self.volume, volume.map(str::to_string).as_ref())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.make.is_none()
+            && self.model.is_none()
+            && self.serial.is_none()
+            && self.volume.is_none()
+        {
+            anyhow::bail!(
+                "Profile '{}' needs a make, model, serial or volume to match",
+                self.name
+            );
+        }
+        if let Some(prefix) = &self.prefix {
+            validate_batch_name(&format!("{}batch", prefix))
+                .with_context(|| format!("Invalid prefix in profile '{}'", self.name))?;
+        }
+        Ok(())
+    }
+}
+
+impl FolioConfig {
+    /// Parse configuration text, validating templates and profiles up front
+    pub fn parse(text: &str) -> Result<Self> {
+        let config: FolioConfig = toml::from_str(text).context("Invalid folio configuration")?;
+        if config.gap_threshold.is_some_and(|hours| hours <= 0.0) {
+            anyhow::bail!("gap_threshold must be a positive number of hours");
+        }
+        for profile in &config.profiles {
+            profile.validate()?;
+        }
+        config.scan.filter(&[], &[])?;
+        Ok(config)
+    }
+
+    /// Load `folio.toml` from an archive root; a missing file means defaults
+    pub fn load(archive_root: &Path) -> Result<Self> {
+        FolioConfig::load_file(&archive_root.join(CONFIG_FILE_NAME))
+    }
+
+    /// Load `folio.toml` from the user configuration directory
+    pub fn load_user() -> Result<Self> {
+        match FolioConfig::user_config_dir() {
+            Some(dir) => FolioConfig::load_file(&dir.join(CONFIG_FILE_NAME)),
+            None => Ok(FolioConfig::default()),
+        }
+    }
+
+    /// The archive's configuration, falling back to the user's for anything it leaves out
+    pub fn load_layered(archive_root: &Path) -> Result<Self> {
+        Ok(FolioConfig::load(archive_root)?.with_fallback(FolioConfig::load_user()?))
+    }
+
+    /// `FOLIO_CONFIG_DIR`, or `folio` in the platform configuration directory
+    pub fn user_config_dir() -> Option<PathBuf> {
+        match std::env::var_os(CONFIG_DIR_ENV) {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => dirs::config_dir().map(|dir| dir.join("folio")),
+        }
+    }
+
+    /// Fill unset values from `fallback`; its profiles are tried after ours
+    pub fn with_fallback(mut self, fallback: FolioConfig) -> Self {
+        self.destination = self.destination.or(fallback.destination);
+        self.template = self.template.or(fallback.template);
+        self.gap_threshold = self.gap_threshold.or(fallback.gap_threshold);
+        self.timezone = self.timezone.or(fallback.timezone);
+        self.mount_root = self.mount_root.or(fallback.mount_root);
+        self.scan.include.extend(fallback.scan.include);
+        self.scan.exclude.extend(fallback.scan.exclude);
+        self.scan.min_size = self.scan.min_size.or(fallback.scan.min_size);
+        self.scan.default_excludes = self
+            .scan
+            .default_excludes
+            .or(fallback.scan.default_excludes);
+        self.profiles.extend(fallback.profiles);
+        self
+    }
+
+    /// The first profile matching a file from `camera` ingested from `volume`
+    pub fn profile_for(
+        &self,
+        camera: Option<&CameraInfo>,
+        volume: Option<&str>,
+    ) -> Option<&DeviceProfile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.matches(camera, volume))
+    }
+
+    fn load_file(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(FolioConfig::default());
+        }
+
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        FolioConfig::parse(&text).with_context(|| format!("Failed to load {:?}", path))
+    }
+}
+
+/// Family configuration (`family.toml`), used for batch name suggestions
+///
+/// ```toml
+/// [[birthday]]
+/// name = "Emma"
+/// date = "2016-03-14"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FamilyConfig {
+    #[serde(default, rename = "birthday")]
+    pub birthdays: Vec<Birthday>,
+}
+
+/// A family member's birthday; only the month and day matter for suggestions
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Birthday {
+    pub name: String,
+    pub date: NaiveDate,
+}
+
+impl FamilyConfig {
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).context("Invalid family configuration")
+    }
+
+    /// Load `family.toml` from an archive root; a missing file means no family
+    pub fn load(archive_root: &Path) -> Result<Self> {
+        let path = archive_root.join(FAMILY_FILE_NAME);
+        if !path.exists() {
+            return Ok(FamilyConfig::default());
+        }
+
+        let text =
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        FamilyConfig::parse(&text).with_context(|| format!("Failed to load {:?}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_template() {
+        let config =
+            FolioConfig::parse(r#"template = "{year}/{date}_{batch}/{stem}.{ext}""#).unwrap();
+
+        assert_eq!(
+            config.template.unwrap().as_str(),
+            "{year}/{date}_{batch}/{stem}.{ext}"
+        );
+    }
+
+    #[test]
+    fn test_invalid_template_fails_at_load() {
+        let error = FolioConfig::parse(r#"template = "{year}/{nope}.{ext}""#).unwrap_err();
+
+        assert!(format!("{:#}", error).contains("Unknown template token"));
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        assert!(FolioConfig::parse(r#"tempalte = "{stem}.{ext}""#).is_err());
+    }
+
+    #[test]
+    fn test_missing_file_is_default() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let config = FolioConfig::load(dir.path()).unwrap();
+
+        assert!(config.template.is_none());
+    }
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = FolioConfig::parse(
+            r#"
+destination = "/archive"
+gap_threshold = 3.5
+timezone = "America/Los_Angeles"
+mount_root = "/mnt/cards"
+
+[scan]
+exclude = ["Screenshots"]
+min_size = "20k"
+
+[[profile]]
+name = "Dad's phone"
+make = "Apple"
+model = "iPhone 13"
+timezone = "America/Chicago"
+prefix = "dad-"
+
+[[profile]]
+name = "Kids' camera"
+volume = "KIDS_SD"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.destination, Some(PathBuf::from("/archive")));
+        assert_eq!(config.gap_threshold, Some(3.5));
+        assert_eq!(config.timezone, Some(chrono_tz::America::Los_Angeles));
+        assert_eq!(config.mount_root, Some(PathBuf::from("/mnt/cards")));
+        assert_eq!(config.scan.min_size, Some(20 * 1024));
+        assert_eq!(config.scan.exclude, vec!["Screenshots".to_string()]);
+        assert_eq!(config.profiles.len(), 2);
+        assert_eq!(
+            config.profiles[0].timezone,
+            Some(chrono_tz::America::Chicago)
+        );
+    }
+
+    #[test]
+    fn test_invalid_profiles_are_rejected() {
+        assert!(FolioConfig::parse("[[profile]]\nname = \"Any\"\nprefix = \"x-\"").is_err());
+        assert!(FolioConfig::parse(
+            "[[profile]]\nname = \"Dad\"\nmodel = \"iPhone\"\nprefix = \"dad's \""
+        )
+        .is_err());
+        assert!(FolioConfig::parse("timezone = \"Mars/Olympus_Mons\"").is_err());
+        assert!(FolioConfig::parse("gap_threshold = 0").is_err());
+        assert!(FolioConfig::parse("[scan]\nexclude = [\"[\"]").is_err());
+        assert!(FolioConfig::parse("[scan]\nmin_size = \"huge\"").is_err());
+    }
+
+    #[test]
+    fn test_profile_matching() {
+        let config = FolioConfig::parse(
+            r#"
+[[profile]]
+name = "Dad's phone"
+make = "Apple"
+model = "iPhone 13"
+
+[[profile]]
+name = "Card"
+volume = "DAD_SD"
+"#,
+        )
+        .unwrap();
+        let iphone = CameraInfo {
+            make: Some("apple".to_string()),
+            model: Some("iPhone 13".to_string()),
+            serial: None,
+        };
+        let ipad = CameraInfo {
+            make: Some("Apple".to_string()),
+            model: Some("iPad".to_string()),
+            serial: None,
+        };
+
+        assert_eq!(
+            config.profile_for(Some(&iphone), None).unwrap().name,
+            "Dad's phone"
+        );
+        assert!(config.profile_for(Some(&ipad), None).is_none());
+        assert_eq!(
+            config
+                .profile_for(Some(&ipad), Some("DAD_SD"))
+                .unwrap()
+                .name,
+            "Card"
+        );
+        assert!(config.profile_for(None, None).is_none());
+    }
+
+    #[test]
+    fn test_archive_config_overrides_user_config() {
+        let archive =
+            FolioConfig::parse("gap_threshold = 1.0\n[[profile]]\nname = \"A\"\nvolume = \"A\"")
+                .unwrap();
+        let user = FolioConfig::parse(
+            "destination = \"/archive\"\ngap_threshold = 4.0\n[[profile]]\nname = \"U\"\nvolume = \"U\"",
+        )
+        .unwrap();
+
+        let config = archive.with_fallback(user);
+
+        assert_eq!(config.gap_threshold, Some(1.0));
+        assert_eq!(config.destination, Some(PathBuf::from("/archive")));
+        let names: Vec<_> = config.profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "U"]);
+    }
+
+    #[test]
+    fn test_parse_family_birthdays() {
+        let family = FamilyConfig::parse(
+            r#"
+[[birthday]]
+name = "Emma"
+date = "2016-03-14"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            family.birthdays,
+            vec![Birthday {
+                name: "Emma".to_string(),
+                date: NaiveDate::from_ymd_opt(2016, 3, 14).unwrap(),
+            }]
+        );
+        assert!(FamilyConfig::parse("[[birthday]]\nname = \"Emma\"\ndate = \"March\"").is_err());
+    }
+}
@@ -0,0 +1,316 @@
+use crate::media::MediaType;
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Deserializer};
+use std::path::Path;
+
+/// OS and NAS housekeeping that never holds real media: trash folders,
+/// thumbnail caches, search indexes and macOS resource forks (`._IMG_0001.JPG`)
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    ".Trashes",
+    ".Trash-*",
+    "$RECYCLE.BIN",
+    "#recycle",
+    "@eaDir",
+    ".thumbnails",
+    ".Spotlight-V100",
+    ".fseventsd",
+    "System Volume Information",
+    "._*",
+];
+
+/// Which kinds of media a scan picks up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MediaKinds {
+    #[default]
+    All,
+    Photos,
+    Videos,
+}
+
+impl MediaKinds {
+    pub fn accepts(self, media_type: &MediaType) -> bool {
+        match self {
+            MediaKinds::All => true,
+            MediaKinds::Photos => media_type.is_photo(),
+            MediaKinds::Videos => media_type.is_video(),
+        }
+    }
+}
+
+/// Rules deciding which files a scan picks up, checked during the walk
+///
+/// Glob patterns without a `/` match the name of any file or folder on the way
+/// (`@eaDir`, `*.tmp`); patterns with one match the path relative to the scan
+/// root (`Screenshots/**`). Excluded folders are not descended into.
+#[derive(Debug, Clone)]
+pub struct ScanFilter {
+    include: Patterns,
+    exclude: Patterns,
+    /// Smaller files are skipped, in bytes
+    pub min_size: u64,
+    /// First capture date to pick up
+    pub since: Option<NaiveDate>,
+    /// Last capture date to pick up
+    pub until: Option<NaiveDate>,
+    pub kinds: MediaKinds,
+    /// How deep below the root to look; 1 means only files directly in it
+    pub max_depth: Option<usize>,
+}
+
+impl Default for ScanFilter {
+    /// Everything except the built-in junk excludes
+    fn default() -> Self {
+        ScanFilter::new(&[], &[], true).expect("built-in exclude patterns are valid")
+    }
+}
+
+impl ScanFilter {
+    /// Filter with the given include and exclude globs; an empty include list
+    /// includes everything
+    pub fn new(include: &[String], exclude: &[String], default_excludes: bool) -> Result<Self> {
+        let mut excludes: Vec<String> = exclude.to_vec();
+        if default_excludes {
+            excludes.extend(DEFAULT_EXCLUDES.iter().map(|p| p.to_string()));
+        }
+        Ok(ScanFilter {
+            include: Patterns::new(include).context("Invalid include pattern")?,
+            exclude: Patterns::new(&excludes).context("Invalid exclude pattern")?,
+            min_size: 0,
+            since: None,
+            until: None,
+            kinds: MediaKinds::All,
+            max_depth: None,
+        })
+    }
+
+    /// Whether a file or folder (relative to the scan root) is excluded
+    pub fn excludes(&self, relative: &Path) -> bool {
+        self.exclude.matches_path(relative)
+            || relative
+                .components()
+                .any(|component| self.exclude.matches_name(component.as_os_str()))
+    }
+
+    /// The checks that need no file contents: patterns, size and kind
+    pub fn accepts_file(&self, relative: &Path, size: u64, media_type: &MediaType) -> bool {
+        !self.excludes(relative)
+            && (self.include.is_empty()
+                || self.include.matches_path(relative)
+                || relative
+                    .file_name()
+                    .is_some_and(|name| self.include.matches_name(name)))
+            && size >= self.min_size
+            && self.kinds.accepts(media_type)
+    }
+
+    /// Whether a capture time falls in `since..=until`; with a date range set,
+    /// files without one are skipped
+    pub fn accepts_date(&self, timestamp: Option<DateTime<Utc>>) -> bool {
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        let Some(date) = timestamp.map(|ts| ts.date_naive()) else {
+            return false;
+        };
+        self.since.map_or(true, |since| since <= date)
+            && self.until.map_or(true, |until| date <= until)
+    }
+}
+
+/// Glob patterns, split into name patterns and relative path patterns
+#[derive(Debug, Clone)]
+struct Patterns {
+    names: GlobSet,
+    paths: GlobSet,
+    count: usize,
+}
+
+impl Patterns {
+    fn new(patterns: &[String]) -> Result<Self> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(pattern.trim_matches('/'))
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid glob '{}'", pattern))?;
+            if pattern.trim_matches('/').contains('/') {
+                paths.add(glob);
+            } else {
+                names.add(glob);
+            }
+        }
+        Ok(Patterns {
+            names: names.build()?,
+            paths: paths.build()?,
+            count: patterns.len(),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    fn matches_name(&self, name: &std::ffi::OsStr) -> bool {
+        self.names.is_match(name)
+    }
+
+    fn matches_path(&self, relative: &Path) -> bool {
+        self.paths.is_match(relative)
+    }
+}
+
+/// Parse a size like `500`, `20k`, `1.5MB` or `2G` (binary units) into bytes
+pub fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("Invalid size '{}'", text))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        _ => anyhow::bail!(
+            "Invalid size '{}', expected e.g. 500, 20k, 1.5M or 2G",
+            text
+        ),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// A byte count for people, e.g. `820 B`, `12.2 KB` or `1.5 GB` (binary units)
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1 << 10 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{:.1} {}", size, unit)
+}
+
+/// Sizes in configuration files may be byte counts or strings like "20k"
+pub(crate) fn deserialize_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+
+    match Option::<Size>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Size::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(Size::Text(text)) => parse_size(&text)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::{PhotoFormat, VideoFormat};
+
+    const JPEG: MediaType = MediaType::Photo(PhotoFormat::Jpeg);
+
+    #[test]
+    fn test_default_excludes_nas_and_os_junk() {
+        let filter = ScanFilter::default();
+        assert!(filter.excludes(Path::new("@eaDir")));
+        assert!(filter.excludes(Path::new("2024/@eaDir/IMG_0001.JPG/SYNOFILE_THUMB_XL.jpg")));
+        assert!(filter.excludes(Path::new(".Trashes/501/IMG_0001.JPG")));
+        assert!(filter.excludes(Path::new("DCIM/._IMG_0001.JPG")));
+        assert!(!filter.excludes(Path::new("DCIM/100APPLE/IMG_0001.JPG")));
+
+        let everything = ScanFilter::new(&[], &[], false).unwrap();
+        assert!(!everything.excludes(Path::new("@eaDir")));
+    }
+
+    #[test]
+    fn test_include_and_exclude_patterns() {
+        let filter = ScanFilter::new(
+            &["*.jpg".to_string(), "Camera/**".to_string()],
+            &["Screenshots".to_string(), "*-edited.*".to_string()],
+            true,
+        )
+        .unwrap();
+
+        assert!(filter.accepts_file(Path::new("Trip/a.jpg"), 10, &JPEG));
+        assert!(filter.accepts_file(Path::new("Camera/2024/a.heic"), 10, &JPEG));
+        assert!(!filter.accepts_file(Path::new("Trip/a.png"), 10, &JPEG));
+        assert!(!filter.accepts_file(Path::new("Screenshots/a.jpg"), 10, &JPEG));
+        assert!(!filter.accepts_file(Path::new("Trip/a-edited.jpg"), 10, &JPEG));
+        // `*` doesn't cross folders in path patterns
+        assert!(ScanFilter::new(&[], &["Camera/*.jpg".to_string()], false)
+            .unwrap()
+            .accepts_file(Path::new("Camera/2024/a.jpg"), 10, &JPEG));
+        assert!(ScanFilter::new(&["[".to_string()], &[], true).is_err());
+    }
+
+    #[test]
+    fn test_size_kind_and_date_checks() {
+        let mut filter = ScanFilter {
+            min_size: 1000,
+            kinds: MediaKinds::Photos,
+            ..ScanFilter::default()
+        };
+        assert!(filter.accepts_file(Path::new("a.jpg"), 1000, &JPEG));
+        assert!(!filter.accepts_file(Path::new("a.jpg"), 999, &JPEG));
+        assert!(!filter.accepts_file(
+            Path::new("a.mov"),
+            5000,
+            &MediaType::Video(VideoFormat::Mov)
+        ));
+
+        let at = |date: &str| {
+            Some(
+                DateTime::parse_from_rfc3339(&format!("{}T12:00:00Z", date))
+                    .unwrap()
+                    .with_timezone(&Utc),
+            )
+        };
+        assert!(filter.accepts_date(None));
+        filter.since = NaiveDate::from_ymd_opt(2024, 11, 1);
+        filter.until = NaiveDate::from_ymd_opt(2024, 11, 30);
+        assert!(filter.accepts_date(at("2024-11-01")));
+        assert!(filter.accepts_date(at("2024-11-30")));
+        assert!(!filter.accepts_date(at("2024-12-01")));
+        assert!(!filter.accepts_date(None));
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("500").unwrap(), 500);
+        assert_eq!(parse_size("20k").unwrap(), 20 * 1024);
+        assert_eq!(parse_size("1.5MB").unwrap(), 3 * 512 * 1024);
+        assert_eq!(parse_size("2 G").unwrap(), 2 << 30);
+        assert!(parse_size("big").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(821), "821 B");
+        assert_eq!(format_size(12480), "12.2 KB");
+        assert_eq!(format_size(3 * 512 * 1024), "1.5 MB");
+        assert_eq!(format_size(2 << 40), "2.0 TB");
+    }
+}
@@ -0,0 +1,278 @@
+use crate::media::hash_file;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Where removable media is mounted when no mount root is configured
+pub const DEFAULT_MOUNT_ROOTS: &[&str] = &["/media", "/run/media"];
+
+/// Move a file, falling back to copy + delete when a rename isn't possible
+/// (e.g. across filesystems). Parent directories of the destination are created as needed
+pub fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {:?}", parent))?;
+    }
+
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_and_remove(from, to)
+}
+
+/// Copy `from` to `to` and remove `from` once the copy's hash matches it;
+/// otherwise the copy is removed and the original kept
+fn copy_and_remove(from: &Path, to: &Path) -> Result<()> {
+    fs::copy(from, to).with_context(|| format!("Failed to move {:?} to {:?}", from, to))?;
+    let verified = match (hash_file(from), hash_file(to)) {
+        (Ok(original), Ok(copy)) => original == copy,
+        _ => false,
+    };
+    if !verified {
+        let _ = fs::remove_file(to);
+        anyhow::bail!(
+            "Copy of {:?} at {:?} doesn't match the original; the original was kept",
+            from,
+            to
+        );
+    }
+    fs::remove_file(from).with_context(|| format!("Failed to remove {:?} after copying", from))
+}
+
+/// Remove empty directories from `dir` upwards, stopping at (and keeping) `root`
+pub fn prune_empty_parents(dir: &Path, root: &Path) -> Result<()> {
+    let mut current = dir;
+
+    while current.starts_with(root) && current != root {
+        let is_empty = match fs::read_dir(current) {
+            Ok(mut entries) => entries.next().is_none(),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                current = match current.parent() {
+                    Some(parent) => parent,
+                    None => break,
+                };
+                continue;
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", current)),
+        };
+        if !is_empty {
+            break;
+        }
+
+        fs::remove_dir(current).with_context(|| format!("Failed to remove {:?}", current))?;
+        current = match current.parent() {
+            Some(parent) => parent,
+            None => break,
+        };
+    }
+
+    Ok(())
+}
+
+/// Label of the removable volume a path is on, taken from its mount point:
+/// `/Volumes/<label>` (macOS), `/run/media/<user>/<label>`, `/media/<user>/<label>`
+/// or `/media/<label>` (Linux); elsewhere, the folder holding a `DCIM` folder
+pub fn volume_label(path: &Path) -> Option<String> {
+    let parts: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            std::path::Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    let parts: Vec<&str> = parts.iter().map(String::as_str).collect();
+
+    // DCIM always sits at the root of a camera's volume
+    let dcim_parent = parts
+        .iter()
+        .position(|part| part.eq_ignore_ascii_case("DCIM"))
+        .filter(|&index| index > 0)
+        .map(|index| parts[index - 1].to_string());
+
+    match parts.as_slice() {
+        ["Volumes", label, ..] => Some(label.to_string()),
+        ["run", "media", _user, label, ..] => Some(label.to_string()),
+        // `/media/<label>` or udisks' `/media/<user>/<label>`: the DCIM folder
+        // tells them apart even when the card belongs to another user
+        ["media", ..] if dcim_parent.is_some() => dcim_parent,
+        ["media", user, label, ..]
+            if std::env::var("USER").is_ok_and(|current| current == *user) =>
+        {
+            Some(label.to_string())
+        }
+        ["media", label, ..] => Some(label.to_string()),
+        _ => dcim_parent,
+    }
+}
+
+/// A mounted volume with a camera's DCIM folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CameraVolume {
+    pub label: String,
+    pub dcim: PathBuf,
+}
+
+/// Mounted volumes with a DCIM folder, directly under one of `roots`
+/// (`/media/<label>`) or one level down (`/run/media/<user>/<label>`)
+pub fn find_camera_volumes(roots: &[PathBuf]) -> Vec<CameraVolume> {
+    let mut volumes = Vec::new();
+    for root in roots {
+        for volume in subdirectories(root) {
+            match dcim_folder(&volume) {
+                Some(dcim) => volumes.push(dcim),
+                None => volumes.extend(
+                    subdirectories(&volume)
+                        .into_iter()
+                        .filter_map(|user_volume| dcim_folder(&user_volume)),
+                ),
+            }
+        }
+    }
+    volumes.sort();
+    volumes.dedup();
+    volumes
+        .into_iter()
+        .map(|dcim| CameraVolume {
+            label: dcim
+                .parent()
+                .and_then(|volume| volume.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            dcim,
+        })
+        .collect()
+}
+
+/// Directories directly inside `path`, skipping hidden ones; none if unreadable
+fn subdirectories(path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(path) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir() && !is_hidden(path))
+        .collect()
+}
+
+/// The volume's DCIM folder, whatever its case (FAT cards may show `dcim`)
+fn dcim_folder(volume: &Path) -> Option<PathBuf> {
+    subdirectories(volume).into_iter().find(|dir| {
+        dir.file_name()
+            .is_some_and(|name| name.eq_ignore_ascii_case("DCIM"))
+    })
+}
+
+/// Dot-directories (e.g. `.folio`) hold metadata, not media
+pub(crate) fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_and_remove_moves_verified_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("a.jpg");
+        let to = dir.path().join("b.jpg");
+        fs::write(&from, b"photo").unwrap();
+
+        copy_and_remove(&from, &to).unwrap();
+
+        assert!(!from.exists());
+        assert_eq!(fs::read(&to).unwrap(), b"photo");
+        // A failed copy leaves the destination alone
+        assert!(copy_and_remove(&from, &to).is_err());
+        assert!(to.exists());
+    }
+
+    #[test]
+    fn test_volume_label_from_mount_point() {
+        assert_eq!(
+            volume_label(Path::new("/Volumes/DAD_SD/DCIM/100APPLE")).as_deref(),
+            Some("DAD_SD")
+        );
+        assert_eq!(
+            volume_label(Path::new("/run/media/alice/EOS_DIGITAL/DCIM")).as_deref(),
+            Some("EOS_DIGITAL")
+        );
+        assert_eq!(
+            volume_label(Path::new("/media/EOS_DIGITAL/DCIM")).as_deref(),
+            Some("EOS_DIGITAL")
+        );
+        assert_eq!(
+            volume_label(Path::new("/mnt/cards/DAD_SD/DCIM/100APPLE")).as_deref(),
+            Some("DAD_SD")
+        );
+        assert_eq!(volume_label(Path::new("/home/alice/Pictures")), None);
+    }
+
+    #[test]
+    fn test_volume_label_under_another_users_media_folder() {
+        // Not the user running folio, e.g. under sudo or a service account
+        assert_eq!(
+            volume_label(Path::new(
+                "/media/folio-test-nobody/EOS_DIGITAL/DCIM/100CANON"
+            ))
+            .as_deref(),
+            Some("EOS_DIGITAL")
+        );
+        assert_eq!(
+            volume_label(Path::new("/media/folio-test-nobody/EOS_DIGITAL/dcim")).as_deref(),
+            Some("EOS_DIGITAL")
+        );
+    }
+
+    #[test]
+    fn test_find_camera_volumes() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("EOS_DIGITAL/DCIM/100CANON")).unwrap();
+        fs::create_dir_all(root.path().join("alice/DAD_SD/dcim")).unwrap();
+        fs::create_dir_all(root.path().join("BACKUP/Photos")).unwrap();
+        fs::create_dir_all(root.path().join(".hidden/DCIM")).unwrap();
+
+        let volumes = find_camera_volumes(&[root.path().to_path_buf()]);
+
+        let labels: Vec<&str> = volumes.iter().map(|v| v.label.as_str()).collect();
+        assert_eq!(labels, vec!["EOS_DIGITAL", "DAD_SD"]);
+        assert_eq!(volumes[1].dcim, root.path().join("alice/DAD_SD/dcim"));
+        assert!(find_camera_volumes(&[root.path().join("missing")]).is_empty());
+    }
+
+    #[test]
+    fn test_move_file_creates_parents() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("a.jpg");
+        let to = dir.path().join("2024/11/04/b.jpg");
+        fs::write(&from, b"photo").unwrap();
+
+        move_file(&from, &to).unwrap();
+
+        assert!(!from.exists());
+        assert_eq!(fs::read(&to).unwrap(), b"photo");
+    }
+
+    #[test]
+    fn test_prune_empty_parents_stops_at_content_and_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("2024/11/04")).unwrap();
+        fs::create_dir_all(root.join("2024/12/01")).unwrap();
+        fs::write(root.join("2024/12/01/keep.jpg"), b"photo").unwrap();
+
+        prune_empty_parents(&root.join("2024/11/04"), root).unwrap();
+
+        assert!(!root.join("2024/11").exists());
+        assert!(root.join("2024/12/01/keep.jpg").exists());
+
+        fs::remove_file(root.join("2024/12/01/keep.jpg")).unwrap();
+        prune_empty_parents(&root.join("2024/12/01"), root).unwrap();
+
+        assert!(!root.join("2024").exists());
+        assert!(root.exists());
+    }
+}
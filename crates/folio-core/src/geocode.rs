@@ -0,0 +1,273 @@
+use crate::media::{GpsCoordinates, MediaItem};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Environment variable naming the place dataset to use instead of the bundled one
+pub const PLACES_ENV: &str = "FOLIO_PLACES";
+
+/// The bundled place dataset, compiled into the binary
+///
+/// A hand-picked sample of 86 large cities and 16 landmarks, half of them in
+/// the United States. Photos more than 50 km from one of its cities get no
+/// city, and most lakes, parks and towns aren't in it; `FOLIO_PLACES` can name
+/// a full extract such as GeoNames cities15000 instead.
+const BUNDLED_PLACES: &str = include_str!("../data/places.tsv");
+
+/// Cities further away than this are not considered the photo's city
+const MAX_CITY_DISTANCE_KM: f64 = 50.0;
+
+/// Where a photo was taken, as far as the place dataset knows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    /// Named area covering the position, e.g. a lake or national park
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub landmark: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+}
+
+impl Location {
+    /// "Lake Tahoe (South Lake Tahoe, California, United States)"
+    pub fn describe(&self) -> String {
+        let parts: Vec<&str> = [&self.city, &self.region, &self.country]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        match &self.landmark {
+            Some(landmark) if parts.is_empty() => landmark.clone(),
+            Some(landmark) => format!("{} ({})", landmark, parts.join(", ")),
+            None => parts.join(", "),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Place {
+    name: String,
+    region: Option<String>,
+    country: String,
+    position: GpsCoordinates,
+}
+
+/// Offline reverse geocoder over a tab-separated place dataset
+///
+/// Each line is `name, region, country, latitude, longitude, radius_km`.
+/// Lines with a radius are landmarks (lakes, parks) covering that area, the
+/// others are cities; `#` starts a comment.
+#[derive(Debug, Clone, Default)]
+pub struct Gazetteer {
+    cities: Vec<Place>,
+    landmarks: Vec<(Place, f64)>,
+}
+
+impl Gazetteer {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut gazetteer = Gazetteer::default();
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            gazetteer
+                .add_line(line)
+                .with_context(|| format!("Invalid place on line {}: {}", number + 1, line))?;
+        }
+        Ok(gazetteer)
+    }
+
+    fn add_line(&mut self, line: &str) -> Result<()> {
+        let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+        let [name, region, country, latitude, longitude, rest @ ..] = fields.as_slice() else {
+            anyhow::bail!("expected name, region, country, latitude and longitude");
+        };
+        if name.is_empty() || country.is_empty() {
+            anyhow::bail!("name and country are required");
+        }
+
+        let place = Place {
+            name: name.to_string(),
+            region: (!region.is_empty()).then(|| region.to_string()),
+            country: country.to_string(),
+            position: GpsCoordinates {
+                latitude: latitude.parse().context("Invalid latitude")?,
+                longitude: longitude.parse().context("Invalid longitude")?,
+            },
+        };
+        match rest.first().filter(|radius| !radius.is_empty()) {
+            Some(radius) => {
+                let radius: f64 = radius.parse().context("Invalid radius")?;
+                self.landmarks.push((place, radius));
+            }
+            None => self.cities.push(place),
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read place dataset {:?}", path))?;
+        Gazetteer::parse(&text).with_context(|| format!("Failed to load {:?}", path))
+    }
+
+    /// Load the dataset named by `FOLIO_PLACES`, or the bundled one
+    pub fn load_default() -> Result<Self> {
+        if let Some(path) = std::env::var_os(PLACES_ENV) {
+            return Gazetteer::load(Path::new(&path));
+        }
+        Gazetteer::parse(BUNDLED_PLACES).context("Failed to load the bundled place dataset")
+    }
+
+    /// The nearest city and any landmark covering a position
+    pub fn lookup(&self, position: &GpsCoordinates) -> Option<Location> {
+        let city = self
+            .cities
+            .iter()
+            .map(|place| (place, place.position.distance_km(position)))
+            .filter(|(_, distance)| *distance <= MAX_CITY_DISTANCE_KM)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(place, _)| place);
+        // The landmark the position is most central to
+        let landmark = self
+            .landmarks
+            .iter()
+            .map(|(place, radius)| (place, place.position.distance_km(position) / radius))
+            .filter(|(_, ratio)| *ratio <= 1.0)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(place, _)| place);
+
+        let area = city.or(landmark)?;
+        Some(Location {
+            landmark: landmark.map(|place| place.name.clone()),
+            city: city.map(|place| place.name.clone()),
+            region: area.region.clone(),
+            country: Some(area.country.clone()),
+        })
+    }
+
+    /// The location most of the located items share (the first seen on a tie)
+    pub fn dominant_location(&self, items: &[MediaItem]) -> Option<Location> {
+        let mut counts: Vec<(Location, usize)> = Vec::new();
+        for location in items
+            .iter()
+            .filter_map(|item| item.gps.as_ref())
+            .filter_map(|position| self.lookup(position))
+        {
+            match counts.iter_mut().find(|(seen, _)| *seen == location) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((location, 1)),
+            }
+        }
+
+        let mut dominant: Option<(Location, usize)> = None;
+        for (location, count) in counts {
+            if dominant.as_ref().map_or(true, |(_, best)| count > *best) {
+                dominant = Some((location, count));
+            }
+        }
+        dominant.map(|(location, _)| location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::{MediaType, PhotoFormat};
+    use std::path::PathBuf;
+
+    const PLACES: &str = "\
+# name\tregion\tcountry\tlatitude\tlongitude\tradius_km
+South Lake Tahoe\tCalifornia\tUnited States\t38.9332\t-119.9844
+Reno\tNevada\tUnited States\t39.5296\t-119.8138
+Lake Tahoe\tCalifornia\tUnited States\t39.0968\t-120.0324\t25
+";
+
+    fn item(latitude: f64, longitude: f64) -> MediaItem {
+        MediaItem {
+            path: PathBuf::from("photo.jpg"),
+            media_type: MediaType::Photo(PhotoFormat::Jpeg),
+            timestamp: None,
+            hash: blake3::hash(b"photo"),
+            size: 1000,
+            folder_path: PathBuf::from("unknown-date"),
+            camera: None,
+            gps: Some(GpsCoordinates {
+                latitude,
+                longitude,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_lookup_finds_city_and_landmark() {
+        let gazetteer = Gazetteer::parse(PLACES).unwrap();
+
+        let location = gazetteer
+            .lookup(&GpsCoordinates {
+                latitude: 38.94,
+                longitude: -119.97,
+            })
+            .unwrap();
+
+        assert_eq!(location.landmark.as_deref(), Some("Lake Tahoe"));
+        assert_eq!(location.city.as_deref(), Some("South Lake Tahoe"));
+        assert_eq!(
+            location.describe(),
+            "Lake Tahoe (South Lake Tahoe, California, United States)"
+        );
+
+        let reno = gazetteer
+            .lookup(&GpsCoordinates {
+                latitude: 39.53,
+                longitude: -119.81,
+            })
+            .unwrap();
+        assert_eq!(reno.landmark, None);
+        assert_eq!(reno.region.as_deref(), Some("Nevada"));
+
+        assert!(gazetteer
+            .lookup(&GpsCoordinates {
+                latitude: 48.85,
+                longitude: 2.35,
+            })
+            .is_none());
+    }
+
+    #[test]
+    fn test_dominant_location_is_most_common() {
+        let gazetteer = Gazetteer::parse(PLACES).unwrap();
+        let items = vec![
+            item(39.53, -119.81),
+            item(38.94, -119.97),
+            item(38.93, -119.98),
+            item(48.85, 2.35),
+        ];
+
+        let location = gazetteer.dominant_location(&items).unwrap();
+
+        assert_eq!(location.city.as_deref(), Some("South Lake Tahoe"));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_lines() {
+        assert!(Gazetteer::parse("Nowhere\t\tUnited States\tnorth\t-120").is_err());
+        assert!(Gazetteer::parse("Nowhere\tUnited States").is_err());
+    }
+
+    #[test]
+    fn test_bundled_dataset_loads() {
+        let gazetteer = Gazetteer::parse(BUNDLED_PLACES).unwrap();
+
+        let location = gazetteer
+            .lookup(&GpsCoordinates {
+                latitude: 38.94,
+                longitude: -119.97,
+            })
+            .unwrap();
+        assert_eq!(location.landmark.as_deref(), Some("Lake Tahoe"));
+    }
+}
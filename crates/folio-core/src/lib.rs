@@ -1,7 +1,49 @@
+pub mod archive;
+pub mod batching;
+pub mod config;
+pub mod filter;
+pub mod fs_ops;
+pub mod geocode;
+pub mod manifest;
 pub mod media;
+pub mod rename;
+pub mod reorganize;
+pub mod suggest;
+pub mod template;
+pub mod timeshift;
+pub mod xmp;
 
+pub use archive::{ArchiveMarker, ARCHIVE_MARKER, ARCHIVE_SCHEMA};
+pub use batching::{
+    resolve_undated, undated_items, AdaptiveGap, BatchingStrategy, CalendarDay, FixedGap,
+    GpsDistance, PerCamera, UndatedResolution,
+};
+pub use config::{Birthday, DeviceProfile, FamilyConfig, FolioConfig, ScanConfig};
+pub use filter::{format_size, parse_size, MediaKinds, ScanFilter, DEFAULT_EXCLUDES};
+pub use geocode::{Gazetteer, Location, PLACES_ENV};
+pub use manifest::{
+    format_manifest, manifest_hashes, parse_manifest, read_manifest, relocate_in_manifests,
+    update_manifests, MANIFEST_FILE,
+};
 pub use media::{
-    detect_media_type, generate_filename, generate_folder_path, get_capture_timestamp,
-    get_file_modified_date, group_by_temporal_proximity, hash_file, scan_directory,
-    validate_batch_name, MediaItem, MediaType, TemporalBatch,
+    dedupe_by_hash, detect_media_type, generate_filename, generate_folder_path, get_camera_info,
+    get_capture_timestamp, get_file_modified_date, get_gps_coordinates,
+    group_by_temporal_proximity, hash_file, parse_archive_filename, scan_directory,
+    scan_directory_filtered, scan_directory_skipping, scan_file, slugify_batch_name,
+    validate_batch_name, CameraInfo, GpsCoordinates, MediaItem, MediaType, TemporalBatch,
+};
+pub use rename::{
+    apply_batch_rename, dequeue_rename, pending_renames, plan_batch_rename, queue_rename,
+    revert_batch_rename, DateRange, RenameMove, RENAME_QUEUE_FILE,
+};
+pub use reorganize::{
+    apply_reorganize, check_reorganize, rollback_reorganize, write_reorganize_manifest,
+    ReorganizeMove, REORGANIZE_DIR,
+};
+pub use suggest::{BatchHistory, NameSuggester, NameSuggestion, MAX_SUGGESTIONS};
+pub use template::{with_counter, PathFields, PathTemplate, TemplateContext, DEFAULT_TEMPLATE};
+pub use timeshift::{
+    apply_device_timezones, apply_retime, apply_time_shifts, device_timezone, format_offset,
+    parse_offset, plan_retime, RetimeFilter, RetimeMove, TimeShift,
 };
+pub use xmp::{format_xmp_date, parse_xmp_date, sidecar_path, XmpDocument, XmpField};
@@ -0,0 +1,228 @@
+use anyhow::{Context, Result};
+use blake3::Hash as Blake3Hash;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Checksum manifest kept in every folder holding archived files
+pub const MANIFEST_FILE: &str = "manifest-blake3.txt";
+
+/// Read a folder's manifest: file name → hash; empty if it has none
+///
+/// Lines are `<hash>  <name>` as in BagIt manifests (and `b3sum` output), with
+/// `%`, CR and LF in names percent-encoded.
+pub fn read_manifest(dir: &Path) -> Result<BTreeMap<String, Blake3Hash>> {
+    let path = dir.join(MANIFEST_FILE);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let text = fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+    parse_manifest(&text).with_context(|| format!("Invalid manifest {:?}", path))
+}
+
+/// Parse manifest lines into name → hash
+pub fn parse_manifest(text: &str) -> Result<BTreeMap<String, Blake3Hash>> {
+    let mut entries = BTreeMap::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let parsed = line
+            .split_once(char::is_whitespace)
+            .and_then(|(hash, name)| {
+                let hash = Blake3Hash::from_hex(hash).ok()?;
+                Some((decode_name(name.trim_start()), hash))
+            });
+        let (name, hash) = parsed.with_context(|| format!("Invalid line {}", number + 1))?;
+        entries.insert(name, hash);
+    }
+    Ok(entries)
+}
+
+/// Manifest lines for name → hash entries, in name order
+pub fn format_manifest(entries: &BTreeMap<String, Blake3Hash>) -> String {
+    entries
+        .iter()
+        .map(|(name, hash)| format!("{}  {}\n", hash.to_hex(), encode_name(name)))
+        .collect()
+}
+
+/// Add and remove archive files (paths relative to the archive root) in the
+/// manifests of their folders; returns how many manifests changed
+///
+/// A manifest left without entries is deleted, so emptied folders can be pruned.
+pub fn update_manifests(
+    archive: &Path,
+    added: &[(PathBuf, Blake3Hash)],
+    removed: &[PathBuf],
+) -> Result<usize> {
+    let mut changes: BTreeMap<PathBuf, Vec<(String, Option<Blake3Hash>)>> = BTreeMap::new();
+    for (path, hash) in added {
+        let (dir, name) = split(path)?;
+        changes.entry(dir).or_default().push((name, Some(*hash)));
+    }
+    for path in removed {
+        let (dir, name) = split(path)?;
+        changes.entry(dir).or_default().push((name, None));
+    }
+    // Removals are applied first, so a file moved within a folder keeps its entry
+    for names in changes.values_mut() {
+        names.sort_by_key(|(_, hash)| hash.is_some());
+    }
+
+    let count = changes.len();
+    for (dir, names) in changes {
+        let dir = archive.join(dir);
+        let mut entries = read_manifest(&dir)?;
+        for (name, hash) in names {
+            match hash {
+                Some(hash) => entries.insert(name, hash),
+                None => entries.remove(&name),
+            };
+        }
+        write_manifest(&dir, &entries)?;
+    }
+    Ok(count)
+}
+
+/// Move manifest entries along with renamed or moved files (relative paths)
+pub fn relocate_in_manifests(archive: &Path, moves: &[(PathBuf, PathBuf)]) -> Result<usize> {
+    let mut cache: HashMap<PathBuf, BTreeMap<String, Blake3Hash>> = HashMap::new();
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    for (from, to) in moves {
+        let (dir, name) = split(from)?;
+        if !cache.contains_key(&dir) {
+            cache.insert(dir.clone(), read_manifest(&archive.join(&dir))?);
+        }
+        // Files the manifests never listed stay unlisted
+        if let Some(hash) = cache[&dir].get(&name) {
+            added.push((to.clone(), *hash));
+            removed.push(from.clone());
+        }
+    }
+    update_manifests(archive, &added, &removed)
+}
+
+/// Every manifest entry in the archive, by path relative to the archive root
+pub fn manifest_hashes(archive: &Path) -> Result<BTreeMap<PathBuf, Blake3Hash>> {
+    let mut hashes = BTreeMap::new();
+    let walker = WalkDir::new(archive)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || entry.file_name() != ".folio");
+    for entry in walker {
+        let entry = entry.context("Failed to read directory entry")?;
+        if !entry.file_type().is_file() || entry.file_name() != MANIFEST_FILE {
+            continue;
+        }
+        let dir = entry.path().parent().unwrap_or(archive);
+        let relative = dir.strip_prefix(archive).unwrap_or(dir);
+        for (name, hash) in read_manifest(dir)? {
+            hashes.insert(relative.join(name), hash);
+        }
+    }
+    Ok(hashes)
+}
+
+/// Folder (relative) and file name of an archive path
+fn split(path: &Path) -> Result<(PathBuf, String)> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("Not a file path: {:?}", path))?;
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    Ok((dir, name.to_string_lossy().into_owned()))
+}
+
+fn write_manifest(dir: &Path, entries: &BTreeMap<String, Blake3Hash>) -> Result<()> {
+    let path = dir.join(MANIFEST_FILE);
+    if entries.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {:?}", path))?;
+        }
+        return Ok(());
+    }
+
+    let text = format_manifest(entries);
+    let partial = dir.join(format!(".{}.partial", MANIFEST_FILE));
+    fs::write(&partial, text).with_context(|| format!("Failed to write {:?}", partial))?;
+    fs::rename(&partial, &path).with_context(|| format!("Failed to write {:?}", path))
+}
+
+fn encode_name(name: &str) -> String {
+    name.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn decode_name(name: &str) -> String {
+    name.replace("%0A", "\n")
+        .replace("%0a", "\n")
+        .replace("%0D", "\r")
+        .replace("%0d", "\r")
+        .replace("%25", "%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifests_follow_added_moved_and_removed_files() {
+        let archive = TempDir::new().unwrap();
+        let day = archive.path().join("2024/11/04");
+        fs::create_dir_all(&day).unwrap();
+        let a = PathBuf::from("2024/11/04/a-trip.jpg");
+        let b = PathBuf::from("2024/11/04/b 100%.jpg");
+
+        let changed = update_manifests(
+            archive.path(),
+            &[
+                (a.clone(), blake3::hash(b"a")),
+                (b.clone(), blake3::hash(b"b")),
+            ],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(changed, 1);
+        let text = fs::read_to_string(day.join(MANIFEST_FILE)).unwrap();
+        assert_eq!(
+            text,
+            format!(
+                "{}  a-trip.jpg\n{}  b 100%25.jpg\n",
+                blake3::hash(b"a").to_hex(),
+                blake3::hash(b"b").to_hex()
+            )
+        );
+        assert_eq!(
+            read_manifest(&day).unwrap()["b 100%.jpg"],
+            blake3::hash(b"b")
+        );
+
+        // A rename within the folder and a move to another one
+        let renamed = PathBuf::from("2024/11/04/a-thanksgiving.jpg");
+        let moved = PathBuf::from("2024/11/05/b.jpg");
+        fs::create_dir_all(archive.path().join("2024/11/05")).unwrap();
+        relocate_in_manifests(archive.path(), &[(a, renamed.clone()), (b, moved.clone())]).unwrap();
+        let hashes = manifest_hashes(archive.path()).unwrap();
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(hashes[&renamed], blake3::hash(b"a"));
+        assert_eq!(hashes[&moved], blake3::hash(b"b"));
+
+        // The last entry gone, the manifest goes too
+        update_manifests(archive.path(), &[], &[moved]).unwrap();
+        assert!(!archive
+            .path()
+            .join("2024/11/05")
+            .join(MANIFEST_FILE)
+            .exists());
+    }
+
+    #[test]
+    fn test_invalid_manifest_line_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(MANIFEST_FILE), "not-a-hash  a.jpg\n").unwrap();
+        assert!(read_manifest(dir.path()).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::filter::ScanFilter;
 use anyhow::{Context, Result};
 use blake3::Hash as Blake3Hash;
 use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
@@ -41,6 +42,58 @@ pub struct MediaItem {
     pub media_type: MediaType,
     pub timestamp: Option<DateTime<Utc>>,
     pub folder_path: PathBuf,
+    pub camera: Option<CameraInfo>,
+    pub gps: Option<GpsCoordinates>,
+}
+
+/// Camera identity read from EXIF (make, model and body serial number)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CameraInfo {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+}
+
+impl CameraInfo {
+    /// Human-readable camera label, e.g. "Nikon D800"
+    /// The make is omitted when the model already starts with it ("NIKON D800")
+    pub fn label(&self) -> String {
+        match (&self.make, &self.model) {
+            (Some(make), Some(model)) => {
+                let make_word = make.split_whitespace().next().unwrap_or(make);
+                if model.to_lowercase().starts_with(&make_word.to_lowercase()) {
+                    model.clone()
+                } else {
+                    format!("{} {}", make, model)
+                }
+            }
+            (None, Some(model)) => model.clone(),
+            (Some(make), None) => make.clone(),
+            (None, None) => "unknown camera".to_string(),
+        }
+    }
+}
+
+/// GPS position in decimal degrees (negative for south/west)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsCoordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl GpsCoordinates {
+    /// Great-circle distance to another position in kilometres (haversine)
+    pub fn distance_km(&self, other: &GpsCoordinates) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+
+        let lat1 = self.latitude.to_radians();
+        let lat2 = other.latitude.to_radians();
+        let dlat = (other.latitude - self.latitude).to_radians();
+        let dlon = (other.longitude - self.longitude).to_radians();
+
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
 }
 
 /// Represents a temporal batch of media items
@@ -100,11 +153,151 @@ pub fn get_capture_timestamp(path: &Path, media_type: &MediaType) -> Result<Opti
             Ok(None)
         }
         MediaType::Video(_) => {
-            // For videos, we'll use file creation/modification date as fallback
-            // TODO: Extract video metadata in future enhancement
-            Ok(None)
+            let file = File::open(path).context("Failed to open video for metadata extraction")?;
+            Ok(read_movie_creation_time(file))
+        }
+    }
+}
+
+/// Creation time in the `mvhd` box of a QuickTime/MP4 file; None when the
+/// file can't be read as one or leaves it at zero (1904-01-01)
+fn read_movie_creation_time(mut file: impl Read + std::io::Seek) -> Option<DateTime<Utc>> {
+    use std::io::SeekFrom;
+
+    // Seconds from 1904-01-01, the QuickTime epoch, to 1970-01-01
+    const QUICKTIME_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+    // Walk the top-level boxes to `moov`, then its children to `mvhd`
+    let mut end = file.seek(SeekFrom::End(0)).ok()?;
+    let mut position = 0;
+    for wanted in [b"moov", b"mvhd"] {
+        loop {
+            if position + 8 > end {
+                return None;
+            }
+            file.seek(SeekFrom::Start(position)).ok()?;
+            let mut header = [0u8; 16];
+            file.read_exact(&mut header[..8]).ok()?;
+            let mut size = u64::from(u32::from_be_bytes(header[..4].try_into().ok()?));
+            let mut header_len = 8;
+            if size == 1 {
+                file.read_exact(&mut header[8..]).ok()?;
+                size = u64::from_be_bytes(header[8..].try_into().ok()?);
+                header_len = 16;
+            } else if size == 0 {
+                size = end - position;
+            }
+            if size < header_len {
+                return None;
+            }
+            if &header[4..8] == wanted {
+                end = position + size;
+                position += header_len;
+                break;
+            }
+            position += size;
         }
     }
+
+    // mvhd: version, flags, then the creation time (64 bits in version 1)
+    file.seek(SeekFrom::Start(position)).ok()?;
+    let mut version = [0u8; 4];
+    file.read_exact(&mut version).ok()?;
+    let seconds = if version[0] == 1 {
+        let mut time = [0u8; 8];
+        file.read_exact(&mut time).ok()?;
+        u64::from_be_bytes(time)
+    } else {
+        let mut time = [0u8; 4];
+        file.read_exact(&mut time).ok()?;
+        u64::from(u32::from_be_bytes(time))
+    };
+    if seconds == 0 {
+        return None;
+    }
+    DateTime::from_timestamp(i64::try_from(seconds).ok()? - QUICKTIME_EPOCH_OFFSET, 0)
+}
+
+/// Read the EXIF block of a photo, if it has one
+fn read_exif(path: &Path) -> Result<Option<exif::Exif>> {
+    let file = File::open(path).context("Failed to open file for EXIF extraction")?;
+    let mut bufreader = std::io::BufReader::new(file);
+    Ok(exif::Reader::new().read_from_container(&mut bufreader).ok())
+}
+
+/// Read an ASCII EXIF field as a trimmed string
+fn exif_string(exif: &exif::Exif, tag: exif::Tag) -> Option<String> {
+    let field = exif.get_field(tag, exif::In::PRIMARY)?;
+    let exif::Value::Ascii(ref vec) = field.value else {
+        return None;
+    };
+    let value = String::from_utf8_lossy(vec.first()?)
+        .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+        .to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+/// Read a GPS degrees/minutes/seconds EXIF field as decimal degrees
+fn exif_gps_degrees(exif: &exif::Exif, tag: exif::Tag, ref_tag: exif::Tag) -> Option<f64> {
+    let field = exif.get_field(tag, exif::In::PRIMARY)?;
+    let exif::Value::Rational(ref dms) = field.value else {
+        return None;
+    };
+    let degrees = dms.first()?.to_f64()
+        + dms.get(1).map_or(0.0, |m| m.to_f64()) / 60.0
+        + dms.get(2).map_or(0.0, |s| s.to_f64()) / 3600.0;
+
+    // South and west references flip the sign
+    match exif_string(exif, ref_tag).as_deref() {
+        Some("S") | Some("W") => Some(-degrees),
+        _ => Some(degrees),
+    }
+}
+
+/// Extract camera make, model and serial number from a media file
+/// Returns None if the file carries no camera metadata
+pub fn get_camera_info(path: &Path, media_type: &MediaType) -> Result<Option<CameraInfo>> {
+    if media_type.is_video() {
+        // TODO: Extract video metadata in future enhancement
+        return Ok(None);
+    }
+
+    let Some(exif) = read_exif(path)? else {
+        return Ok(None);
+    };
+
+    let camera = CameraInfo {
+        make: exif_string(&exif, exif::Tag::Make),
+        model: exif_string(&exif, exif::Tag::Model),
+        serial: exif_string(&exif, exif::Tag::BodySerialNumber),
+    };
+
+    if camera.make.is_none() && camera.model.is_none() && camera.serial.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(camera))
+}
+
+/// Extract GPS position from a media file
+/// Returns None if the file carries no GPS metadata
+pub fn get_gps_coordinates(path: &Path, media_type: &MediaType) -> Result<Option<GpsCoordinates>> {
+    if media_type.is_video() {
+        return Ok(None);
+    }
+
+    let Some(exif) = read_exif(path)? else {
+        return Ok(None);
+    };
+
+    let latitude = exif_gps_degrees(&exif, exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef);
+    let longitude = exif_gps_degrees(&exif, exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef);
+
+    Ok(latitude
+        .zip(longitude)
+        .map(|(latitude, longitude)| GpsCoordinates {
+            latitude,
+            longitude,
+        }))
 }
 
 /// Get file modification timestamp as fallback
@@ -147,6 +340,35 @@ pub fn generate_filename(
     )
 }
 
+/// Parse a filename produced by `generate_filename`
+/// Returns the timestamp, batch name and extension, or None for other names
+///
+/// # Examples
+/// ```
+/// use folio_core::parse_archive_filename;
+///
+/// let (timestamp, batch, ext) = parse_archive_filename("20241104-140215-thanksgiving.jpg").unwrap();
+/// assert_eq!(timestamp.to_rfc3339(), "2024-11-04T14:02:15+00:00");
+/// assert_eq!(batch, "thanksgiving");
+/// assert_eq!(ext, "jpg");
+/// assert!(parse_archive_filename("IMG_1234.jpg").is_none());
+/// ```
+pub fn parse_archive_filename(filename: &str) -> Option<(DateTime<Utc>, String, String)> {
+    let (stem, extension) = filename.rsplit_once('.')?;
+    let (date_time, batch_name) = stem.get(..15).zip(stem.get(15..))?;
+    let batch_name = batch_name.strip_prefix('-')?;
+    if batch_name.is_empty() {
+        return None;
+    }
+
+    let naive = chrono::NaiveDateTime::parse_from_str(date_time, "%Y%m%d-%H%M%S").ok()?;
+    Some((
+        DateTime::<Utc>::from_naive_utc_and_offset(naive, Utc),
+        batch_name.to_string(),
+        extension.to_string(),
+    ))
+}
+
 /// Validate batch name format
 /// Batch names must be alphanumeric with hyphens and underscores only
 /// No spaces or special characters allowed
@@ -197,6 +419,32 @@ pub fn validate_batch_name(name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Turn a human batch name into one that passes `validate_batch_name`
+/// Accents are transliterated, everything is lowercased, apostrophes are dropped
+/// and other runs of spaces and punctuation become a single hyphen.
+/// Returns an empty string if nothing usable is left
+///
+/// # Examples
+/// ```
+/// use folio_core::slugify_batch_name;
+///
+/// assert_eq!(slugify_batch_name("Thanksgiving at Grandma's"), "thanksgiving-at-grandmas");
+/// assert_eq!(slugify_batch_name("Crème brûlée -- Noël!"), "creme-brulee-noel");
+/// ```
+pub fn slugify_batch_name(name: &str) -> String {
+    let mut slug = String::new();
+    for c in deunicode::deunicode(name).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if c == '\'' {
+            continue;
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
 /// Group media items by temporal proximity
 /// Items are grouped into batches based on time gaps between consecutive items
 /// A new batch starts when the gap between items exceeds the threshold
@@ -231,6 +479,8 @@ pub fn validate_batch_name(name: &str) -> Result<()> {
 ///         media_type: MediaType::Photo(PhotoFormat::Jpeg),
 ///         timestamp: Some(timestamp1),
 ///         folder_path: generate_folder_path(timestamp1),
+///         camera: None,
+///         gps: None,
 ///     },
 ///     MediaItem {
 ///         path: PathBuf::from("photo2.jpg"),
@@ -239,6 +489,8 @@ pub fn validate_batch_name(name: &str) -> Result<()> {
 ///         media_type: MediaType::Photo(PhotoFormat::Jpeg),
 ///         timestamp: Some(timestamp2),
 ///         folder_path: generate_folder_path(timestamp2),
+///         camera: None,
+///         gps: None,
 ///     },
 /// ];
 ///
@@ -268,7 +520,7 @@ pub fn group_by_temporal_proximity(
         .collect();
 
     // Sort by timestamp
-    sorted_items.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    sorted_items.sort_by_key(|item| item.timestamp);
 
     if sorted_items.is_empty() {
         return Vec::new();
@@ -341,53 +593,135 @@ pub fn hash_file(path: &Path) -> Result<Blake3Hash> {
     Ok(hasher.finalize())
 }
 
-/// Scan directory recursively and return all media items
+/// Scan directory recursively and return all media items, skipping OS and
+/// NAS junk (see `DEFAULT_EXCLUDES`)
 pub fn scan_directory(path: &Path) -> Result<Vec<MediaItem>> {
+    scan_directory_filtered(path, &ScanFilter::default())
+}
+
+/// Scan directory recursively for the media items `filter` accepts
+///
+/// Excluded folders are pruned from the walk, and files are only hashed once
+/// their name, size, kind and capture date pass.
+pub fn scan_directory_filtered(path: &Path, filter: &ScanFilter) -> Result<Vec<MediaItem>> {
+    scan_directory_skipping(path, filter, |_, _| false)
+}
+
+/// Like `scan_directory_filtered`, leaving out the files `skip` picks by their
+/// relative path and metadata (e.g. ones imported before) without reading them
+pub fn scan_directory_skipping(
+    path: &Path,
+    filter: &ScanFilter,
+    mut skip: impl FnMut(&Path, &std::fs::Metadata) -> bool,
+) -> Result<Vec<MediaItem>> {
     let mut items = Vec::new();
+    let relative = |entry: &walkdir::DirEntry| {
+        entry
+            .path()
+            .strip_prefix(path)
+            .unwrap_or(entry.path())
+            .to_path_buf()
+    };
+
+    let mut walker = WalkDir::new(path).follow_links(false);
+    if let Some(depth) = filter.max_depth {
+        walker = walker.max_depth(depth);
+    }
+    let entries = walker
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !filter.excludes(&relative(entry)));
 
-    for entry in WalkDir::new(path).follow_links(false) {
+    for entry in entries {
         let entry = entry.context("Failed to read directory entry")?;
 
         if !entry.file_type().is_file() {
             continue;
         }
 
-        let file_path = entry.path();
-
         // Check if it's a media file
-        let Some(media_type) = detect_media_type(file_path) else {
+        let Some(media_type) = detect_media_type(entry.path()) else {
             continue;
         };
 
         // Get file size
         let metadata = entry.metadata().context("Failed to read file metadata")?;
-        let size = metadata.len();
+        let relative = relative(&entry);
+        if !filter.accepts_file(&relative, metadata.len(), &media_type)
+            || skip(&relative, &metadata)
+        {
+            continue;
+        }
 
-        // Calculate hash
-        let hash = hash_file(file_path)?;
+        if let Some(item) = read_media(entry.path(), media_type, metadata.len(), Some(filter))? {
+            items.push(item);
+        }
+    }
 
-        // Extract timestamp (with fallback to modified date)
-        let timestamp = get_capture_timestamp(file_path, &media_type)?
-            .or_else(|| get_file_modified_date(file_path).ok());
+    Ok(items)
+}
 
-        // Generate folder path from timestamp
-        let folder_path = if let Some(ts) = timestamp {
-            generate_folder_path(ts)
-        } else {
-            PathBuf::from("unknown-date")
-        };
+/// Read one file's metadata and hash; `None` if it isn't a media file
+pub fn scan_file(file_path: &Path) -> Result<Option<MediaItem>> {
+    // Check if it's a media file
+    let Some(media_type) = detect_media_type(file_path) else {
+        return Ok(None);
+    };
 
-        items.push(MediaItem {
-            path: file_path.to_path_buf(),
-            hash,
-            size,
-            media_type,
-            timestamp,
-            folder_path,
-        });
+    // Get file size
+    let metadata = std::fs::metadata(file_path).context("Failed to read file metadata")?;
+    read_media(file_path, media_type, metadata.len(), None)
+}
+
+/// Build the item for a media file; `None` if it is outside the filter's dates
+fn read_media(
+    file_path: &Path,
+    media_type: MediaType,
+    size: u64,
+    filter: Option<&ScanFilter>,
+) -> Result<Option<MediaItem>> {
+    // Only a capture time dates a file: copies and downloads reset the
+    // modification time, so files without one are left for the undated prompt
+    let timestamp = get_capture_timestamp(file_path, &media_type)?;
+    // Date filters still go by the modification time of undated files
+    if filter.is_some_and(|filter| {
+        !filter.accepts_date(timestamp.or_else(|| get_file_modified_date(file_path).ok()))
+    }) {
+        return Ok(None);
     }
 
-    Ok(items)
+    // Calculate hash
+    let hash = hash_file(file_path)?;
+
+    // Generate folder path from timestamp
+    let folder_path = if let Some(ts) = timestamp {
+        generate_folder_path(ts)
+    } else {
+        PathBuf::from("unknown-date")
+    };
+
+    // Camera and GPS metadata (used by batching strategies)
+    let camera = get_camera_info(file_path, &media_type)?;
+    let gps = get_gps_coordinates(file_path, &media_type)?;
+
+    Ok(Some(MediaItem {
+        path: file_path.to_path_buf(),
+        hash,
+        size,
+        media_type,
+        timestamp,
+        folder_path,
+        camera,
+        gps,
+    }))
+}
+
+/// Drop items whose content already appeared earlier in the list (e.g. the same
+/// photo on two cards), keeping the first copy; returns how many were dropped
+pub fn dedupe_by_hash(items: &mut Vec<MediaItem>) -> usize {
+    let before = items.len();
+    let mut seen = std::collections::HashSet::new();
+    items.retain(|item| seen.insert(item.hash));
+    before - items.len()
 }
 
 #[cfg(test)]
@@ -461,6 +795,62 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_scan_directory_skipping() {
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("test-data/fixtures");
+
+        let mut asked = Vec::new();
+        let items = scan_directory_skipping(&test_dir, &ScanFilter::default(), |relative, _| {
+            asked.push(relative.to_path_buf());
+            relative.extension().is_some_and(|ext| ext == "mov")
+        })
+        .unwrap();
+
+        assert!(items.iter().all(|i| i.path.extension().unwrap() != "mov"));
+        assert!(asked.contains(&PathBuf::from("minimal.mov")));
+        // Non-media files are never offered
+        assert!(!asked.contains(&PathBuf::from("test.txt")));
+    }
+
+    #[test]
+    fn test_movie_creation_time() {
+        fn atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+            let mut atom = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+            atom.extend_from_slice(kind);
+            atom.extend_from_slice(body);
+            atom
+        }
+        // 2024-11-04 14:02:15 UTC in seconds since 1904
+        let mut mvhd = vec![0, 0, 0, 0];
+        mvhd.extend_from_slice(&3_813_573_735u32.to_be_bytes());
+        mvhd.extend_from_slice(&[0; 12]);
+        let mut movie = atom(b"ftyp", b"qt  ");
+        movie.extend(atom(b"mdat", &[0; 32]));
+        movie.extend(atom(b"moov", &atom(b"mvhd", &mvhd)));
+
+        assert_eq!(
+            read_movie_creation_time(std::io::Cursor::new(movie)).map(|t| t.to_rfc3339()),
+            Some("2024-11-04T14:02:15+00:00".to_string())
+        );
+
+        // The fixtures leave the creation time at zero
+        let fixture =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-data/fixtures/minimal.mov");
+        assert_eq!(
+            get_capture_timestamp(&fixture, &MediaType::Video(VideoFormat::Mov)).unwrap(),
+            None
+        );
+        assert_eq!(
+            read_movie_creation_time(std::io::Cursor::new(b"not a movie")),
+            None
+        );
+    }
+
     #[test]
     fn test_hash_file() {
         let test_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -477,6 +867,29 @@ mod tests {
         assert_eq!(hash1, hash2);
     }
 
+    #[test]
+    fn test_dedupe_by_hash_keeps_first_copy() {
+        let item = |path: &str, content: &[u8]| MediaItem {
+            path: PathBuf::from(path),
+            hash: blake3::hash(content),
+            size: content.len() as u64,
+            media_type: MediaType::Photo(PhotoFormat::Jpeg),
+            timestamp: None,
+            folder_path: PathBuf::from("unknown-date"),
+            camera: None,
+            gps: None,
+        };
+        let mut items = vec![
+            item("card-a/1.jpg", b"one"),
+            item("card-a/2.jpg", b"two"),
+            item("card-b/1-copy.jpg", b"one"),
+        ];
+
+        assert_eq!(dedupe_by_hash(&mut items), 1);
+        let paths: Vec<_> = items.iter().map(|i| i.path.to_str().unwrap()).collect();
+        assert_eq!(paths, vec!["card-a/1.jpg", "card-a/2.jpg"]);
+    }
+
     #[test]
     fn test_group_by_temporal_proximity_single_batch() {
         // All items within 2-hour gap should be in same batch
@@ -495,6 +908,8 @@ mod tests {
                 media_type: MediaType::Photo(PhotoFormat::Jpeg),
                 timestamp: Some(timestamp1),
                 folder_path: generate_folder_path(timestamp1),
+                camera: None,
+                gps: None,
             },
             MediaItem {
                 path: PathBuf::from("photo2.jpg"),
@@ -503,6 +918,8 @@ mod tests {
                 media_type: MediaType::Photo(PhotoFormat::Jpeg),
                 timestamp: Some(timestamp2),
                 folder_path: generate_folder_path(timestamp2),
+                camera: None,
+                gps: None,
             },
         ];
 
@@ -533,6 +950,8 @@ mod tests {
                 media_type: MediaType::Photo(PhotoFormat::Jpeg),
                 timestamp: Some(timestamp1),
                 folder_path: generate_folder_path(timestamp1),
+                camera: None,
+                gps: None,
             },
             MediaItem {
                 path: PathBuf::from("photo2.jpg"),
@@ -541,6 +960,8 @@ mod tests {
                 media_type: MediaType::Photo(PhotoFormat::Jpeg),
                 timestamp: Some(timestamp2),
                 folder_path: generate_folder_path(timestamp2),
+                camera: None,
+                gps: None,
             },
         ];
 
@@ -576,6 +997,8 @@ mod tests {
                 media_type: MediaType::Photo(PhotoFormat::Jpeg),
                 timestamp: Some(timestamp1),
                 folder_path: generate_folder_path(timestamp1),
+                camera: None,
+                gps: None,
             },
             MediaItem {
                 path: PathBuf::from("photo2.jpg"),
@@ -584,6 +1007,8 @@ mod tests {
                 media_type: MediaType::Photo(PhotoFormat::Jpeg),
                 timestamp: Some(timestamp2),
                 folder_path: generate_folder_path(timestamp2),
+                camera: None,
+                gps: None,
             },
             MediaItem {
                 path: PathBuf::from("photo3.jpg"),
@@ -592,6 +1017,8 @@ mod tests {
                 media_type: MediaType::Photo(PhotoFormat::Jpeg),
                 timestamp: Some(timestamp3),
                 folder_path: generate_folder_path(timestamp3),
+                camera: None,
+                gps: None,
             },
             MediaItem {
                 path: PathBuf::from("photo4.jpg"),
@@ -600,6 +1027,8 @@ mod tests {
                 media_type: MediaType::Photo(PhotoFormat::Jpeg),
                 timestamp: Some(timestamp4),
                 folder_path: generate_folder_path(timestamp4),
+                camera: None,
+                gps: None,
             },
         ];
 
@@ -682,4 +1111,76 @@ mod tests {
     fn test_validate_batch_name_invalid_only_underscores() {
         assert!(validate_batch_name("___").is_err());
     }
+
+    #[test]
+    fn test_slugify_batch_name() {
+        assert_eq!(slugify_batch_name("vacation-2024"), "vacation-2024");
+        assert_eq!(
+            slugify_batch_name("  Lake   Tahoe (2019) "),
+            "lake-tahoe-2019"
+        );
+        assert_eq!(
+            slugify_batch_name("Zoë’s 5th Birthday"),
+            "zoes-5th-birthday"
+        );
+        assert_eq!(slugify_batch_name("Straße"), "strasse");
+        assert_eq!(slugify_batch_name("!!!"), "");
+
+        for name in ["Thanksgiving at Grandma's", "Ärger & Ölmühle", "a_b c"] {
+            assert!(validate_batch_name(&slugify_batch_name(name)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_camera_label_omits_repeated_make() {
+        let camera = CameraInfo {
+            make: Some("NIKON CORPORATION".to_string()),
+            model: Some("NIKON D800".to_string()),
+            serial: None,
+        };
+        assert_eq!(camera.label(), "NIKON D800");
+
+        let camera = CameraInfo {
+            make: Some("Nikon".to_string()),
+            model: Some("D800".to_string()),
+            serial: None,
+        };
+        assert_eq!(camera.label(), "Nikon D800");
+    }
+
+    #[test]
+    fn test_camera_and_gps_from_fixture() {
+        let test_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("test-data/fixtures/sample-with-exif.jpg");
+        let media_type = MediaType::Photo(PhotoFormat::Jpeg);
+
+        let camera = get_camera_info(&test_file, &media_type).unwrap().unwrap();
+        assert_eq!(camera.label(), "Nikon D800");
+
+        let gps = get_gps_coordinates(&test_file, &media_type)
+            .unwrap()
+            .unwrap();
+        assert!((gps.latitude - 40.7128).abs() < 0.001);
+        assert!((gps.longitude.abs() - 74.0060).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_gps_distance_km() {
+        let new_york = GpsCoordinates {
+            latitude: 40.7128,
+            longitude: -74.0060,
+        };
+        let philadelphia = GpsCoordinates {
+            latitude: 39.9526,
+            longitude: -75.1652,
+        };
+
+        let distance = new_york.distance_km(&philadelphia);
+        assert!((distance - 129.6).abs() < 1.0, "got {}", distance);
+        assert_eq!(new_york.distance_km(&new_york), 0.0);
+    }
 }
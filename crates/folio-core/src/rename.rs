@@ -0,0 +1,454 @@
+use crate::fs_ops::is_hidden;
+use crate::media::{detect_media_type, validate_batch_name};
+use crate::template::PathTemplate;
+use crate::xmp::sidecar_path;
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use walkdir::WalkDir;
+
+/// Placeholder batches waiting for a real name, one `<batch>\t<start>..<end>` per line
+pub const RENAME_QUEUE_FILE: &str = ".folio/rename-queue.tsv";
+
+/// An inclusive range of capture dates, e.g. `2024-11-01..2024-11-30` or a single `2024-11-28`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl DateRange {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end
+    }
+}
+
+impl FromStr for DateRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parse = |date: &str| {
+            NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .with_context(|| format!("Invalid date '{}', expected YYYY-MM-DD", date))
+        };
+
+        let (start, end) = match s.split_once("..") {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(s)?, parse(s)?),
+        };
+        if start > end {
+            anyhow::bail!("Date range '{}' ends before it starts", s);
+        }
+        Ok(DateRange { start, end })
+    }
+}
+
+impl std::fmt::Display for DateRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// Placeholder batches of an archive waiting to be renamed, in the order queued
+pub fn pending_renames(archive: &Path) -> Result<Vec<(String, DateRange)>> {
+    let path = archive.join(RENAME_QUEUE_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    };
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (batch, dates) = line
+                .split_once('\t')
+                .with_context(|| format!("Invalid line in {:?}: {}", path, line))?;
+            Ok((batch.to_string(), dates.parse()?))
+        })
+        .collect()
+}
+
+/// Queue a placeholder batch for renaming; a batch already queued grows to cover `dates`
+pub fn queue_rename(archive: &Path, batch: &str, dates: DateRange) -> Result<()> {
+    let mut pending = pending_renames(archive)?;
+    match pending.iter_mut().find(|(name, _)| name == batch) {
+        Some((_, range)) => {
+            range.start = range.start.min(dates.start);
+            range.end = range.end.max(dates.end);
+        }
+        None => pending.push((batch.to_string(), dates)),
+    }
+    write_rename_queue(archive, &pending)
+}
+
+/// Drop a batch from the rename queue; returns whether it was queued
+pub fn dequeue_rename(archive: &Path, batch: &str) -> Result<bool> {
+    let mut pending = pending_renames(archive)?;
+    let before = pending.len();
+    pending.retain(|(name, _)| name != batch);
+    if pending.len() == before {
+        return Ok(false);
+    }
+    write_rename_queue(archive, &pending)?;
+    Ok(true)
+}
+
+fn write_rename_queue(archive: &Path, pending: &[(String, DateRange)]) -> Result<()> {
+    let path = archive.join(RENAME_QUEUE_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+    }
+    let text: String = pending
+        .iter()
+        .map(|(batch, dates)| format!("{}\t{}\n", batch, dates))
+        .collect();
+    fs::write(&path, text).with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// A planned rename of one archived file (its sidecar follows it)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameMove {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// Batch the file belongs to before and after, profile prefix included
+    pub old_batch: String,
+    pub new_batch: String,
+}
+
+/// Plan renaming every file of batch `from` to batch `to`
+///
+/// Files are recognised by reading their archive path with `template`, so
+/// batch folders are renamed too. A batch carrying one of the profiles'
+/// `prefixes` (e.g. `dad-thanksgving`) keeps its prefix. Fails without
+/// planning anything if the new name is invalid or a new path is taken.
+pub fn plan_batch_rename(
+    archive: &Path,
+    template: &PathTemplate,
+    prefixes: &[&str],
+    from: &str,
+    to: &str,
+    dates: Option<DateRange>,
+) -> Result<Vec<RenameMove>> {
+    validate_batch_name(to).context("Invalid batch name")?;
+    if from == to {
+        anyhow::bail!("The batch is already named '{}'", to);
+    }
+
+    let mut moves = Vec::new();
+
+    let walker = WalkDir::new(archive)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !is_hidden(entry.path()));
+    for entry in walker {
+        let entry = entry.context("Failed to read directory entry")?;
+        if !entry.file_type().is_file() || detect_media_type(entry.path()).is_none() {
+            continue;
+        }
+
+        let path = entry.path();
+        let Some(fields) = template.read(path.strip_prefix(archive)?) else {
+            continue;
+        };
+        let Some(batch) = fields.batch() else {
+            continue;
+        };
+        let Some(prefix) = std::iter::once("")
+            .chain(prefixes.iter().copied())
+            .find(|prefix| batch.strip_prefix(prefix) == Some(from))
+        else {
+            continue;
+        };
+        if dates.is_some_and(|range| !fields.date().is_some_and(|date| range.contains(date))) {
+            continue;
+        }
+
+        let new_batch = format!("{}{}", prefix, to);
+        moves.push(RenameMove {
+            from: path.to_path_buf(),
+            to: archive.join(template.rerender(&fields, None, &new_batch)),
+            old_batch: batch.to_string(),
+            new_batch,
+        });
+    }
+    moves.sort_by(|a, b| a.from.cmp(&b.from));
+
+    let mut targets = HashSet::new();
+    for m in &moves {
+        let sidecar_taken = sidecar_path(&m.from).exists() && sidecar_path(&m.to).exists();
+        if !targets.insert(&m.to) || m.to.exists() || sidecar_taken {
+            anyhow::bail!(
+                "Renaming {:?} would overwrite {:?}; nothing was changed",
+                m.from,
+                m.to
+            );
+        }
+    }
+
+    Ok(moves)
+}
+
+/// Carry out planned renames, moving sidecars along
+///
+/// All or nothing: if any rename fails, the ones already made are reverted.
+pub fn apply_batch_rename(moves: &[RenameMove]) -> Result<()> {
+    // Every (from, to) pair actually renamed, media and sidecars
+    let mut done: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+    let result = (|| -> Result<()> {
+        for m in moves {
+            // A template with batch folders moves files to new folders
+            if let Some(parent) = m.to.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {:?}", parent))?;
+            }
+            rename(&m.from, &m.to)?;
+            done.push((m.from.clone(), m.to.clone()));
+
+            let sidecar = sidecar_path(&m.from);
+            if sidecar.exists() {
+                let new_sidecar = sidecar_path(&m.to);
+                rename(&sidecar, &new_sidecar)?;
+                done.push((sidecar, new_sidecar));
+            }
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        revert(&done);
+        return Err(e.context("Batch rename failed; all renamed files were restored"));
+    }
+    Ok(())
+}
+
+/// Undo renames made by `apply_batch_rename`, e.g. when a later step fails
+pub fn revert_batch_rename(moves: &[RenameMove]) -> Result<()> {
+    let mut pairs = Vec::new();
+    for m in moves {
+        pairs.push((m.from.clone(), m.to.clone()));
+        if sidecar_path(&m.to).exists() {
+            pairs.push((sidecar_path(&m.from), sidecar_path(&m.to)));
+        }
+    }
+    let failed = revert(&pairs);
+    if failed > 0 {
+        anyhow::bail!("{} file(s) could not be renamed back", failed);
+    }
+    Ok(())
+}
+
+/// Rename pairs back in reverse order, returning how many couldn't be
+fn revert(done: &[(PathBuf, PathBuf)]) -> usize {
+    let mut failed = 0;
+    for (from, to) in done.iter().rev() {
+        if let Err(e) = fs::rename(to, from) {
+            tracing::error!("Failed to restore {:?} from {:?}: {}", from, to, e);
+            failed += 1;
+        }
+    }
+    failed
+}
+
+fn rename(from: &Path, to: &Path) -> Result<()> {
+    fs::rename(from, to).with_context(|| format!("Failed to rename {:?} to {:?}", from, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date_range() {
+        let range: DateRange = "2024-11-01..2024-11-30".parse().unwrap();
+        assert!(range.contains(NaiveDate::from_ymd_opt(2024, 11, 28).unwrap()));
+        assert!(!range.contains(NaiveDate::from_ymd_opt(2024, 12, 1).unwrap()));
+
+        let day: DateRange = "2024-11-28".parse().unwrap();
+        assert_eq!(day.start, day.end);
+
+        assert!("2024-11-30..2024-11-01".parse::<DateRange>().is_err());
+        assert!("last week".parse::<DateRange>().is_err());
+    }
+
+    #[test]
+    fn test_rename_queue() {
+        let archive = tempfile::tempdir().unwrap();
+        assert!(pending_renames(archive.path()).unwrap().is_empty());
+
+        queue_rename(
+            archive.path(),
+            "inbox-20241104",
+            "2024-11-04".parse().unwrap(),
+        )
+        .unwrap();
+        queue_rename(
+            archive.path(),
+            "inbox-20241110",
+            "2024-11-10".parse().unwrap(),
+        )
+        .unwrap();
+        queue_rename(
+            archive.path(),
+            "inbox-20241104",
+            "2024-11-05".parse().unwrap(),
+        )
+        .unwrap();
+
+        let pending = pending_renames(archive.path()).unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].0, "inbox-20241104");
+        assert_eq!(pending[0].1.to_string(), "2024-11-04..2024-11-05");
+
+        assert!(dequeue_rename(archive.path(), "inbox-20241104").unwrap());
+        assert!(!dequeue_rename(archive.path(), "inbox-20241104").unwrap());
+        assert_eq!(pending_renames(archive.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_batch_rename_moves_sidecars_and_respects_dates() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("2024/11/28")).unwrap();
+        fs::create_dir_all(root.join("2023/11/23")).unwrap();
+        fs::write(
+            root.join("2024/11/28/20241128-120000-thanksgving.jpg"),
+            b"a",
+        )
+        .unwrap();
+        fs::write(
+            root.join("2024/11/28/20241128-120000-thanksgving.xmp"),
+            b"x",
+        )
+        .unwrap();
+        fs::write(
+            root.join("2023/11/23/20231123-120000-thanksgving.jpg"),
+            b"b",
+        )
+        .unwrap();
+        fs::write(root.join("2024/11/28/20241128-130000-other.jpg"), b"c").unwrap();
+
+        let moves = plan_batch_rename(
+            root,
+            &PathTemplate::default(),
+            &[],
+            "thanksgving",
+            "thanksgiving",
+            Some("2024-11-01..2024-11-30".parse().unwrap()),
+        )
+        .unwrap();
+        assert_eq!(moves.len(), 1);
+        apply_batch_rename(&moves).unwrap();
+
+        assert!(root
+            .join("2024/11/28/20241128-120000-thanksgiving.jpg")
+            .exists());
+        assert!(root
+            .join("2024/11/28/20241128-120000-thanksgiving.xmp")
+            .exists());
+        assert!(root
+            .join("2023/11/23/20231123-120000-thanksgving.jpg")
+            .exists());
+        assert!(root.join("2024/11/28/20241128-130000-other.jpg").exists());
+
+        revert_batch_rename(&moves).unwrap();
+        assert!(root
+            .join("2024/11/28/20241128-120000-thanksgving.jpg")
+            .exists());
+        assert!(root
+            .join("2024/11/28/20241128-120000-thanksgving.xmp")
+            .exists());
+    }
+
+    #[test]
+    fn test_batch_rename_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let day = root.join("2024/11/28");
+        fs::create_dir_all(&day).unwrap();
+        fs::write(day.join("20241128-120000-thanksgving.jpg"), b"a").unwrap();
+        fs::write(day.join("20241128-120000-thanksgiving.jpg"), b"b").unwrap();
+
+        let template = PathTemplate::default();
+        let plan = |to| plan_batch_rename(root, &template, &[], "thanksgving", to, None);
+        assert!(plan("thanksgiving").is_err());
+        assert!(plan("thanks giving").is_err());
+    }
+
+    #[test]
+    fn test_batch_rename_follows_template_and_prefixes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let template: PathTemplate = "{year}/{date}_{batch}/{time}-{seq}.{ext}".parse().unwrap();
+        for path in [
+            "2024/2024-11-28_thanksgving/120000-001.jpg",
+            "2024/2024-11-28_dad-thanksgving/120500-001.jpg",
+            "2024/2024-11-28_mom-thanksgving/121000-001.jpg",
+            "2024/2024-11-28_other/130000-001.jpg",
+        ] {
+            fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            fs::write(root.join(path), b"a").unwrap();
+        }
+
+        let moves = plan_batch_rename(
+            root,
+            &template,
+            &["dad-"],
+            "thanksgving",
+            "thanksgiving",
+            None,
+        )
+        .unwrap();
+        let renamed: Vec<_> = moves
+            .iter()
+            .map(|m| (m.to.strip_prefix(root).unwrap(), m.new_batch.as_str()))
+            .collect();
+        assert_eq!(
+            renamed,
+            vec![
+                (
+                    Path::new("2024/2024-11-28_dad-thanksgiving/120500-001.jpg"),
+                    "dad-thanksgiving"
+                ),
+                (
+                    Path::new("2024/2024-11-28_thanksgiving/120000-001.jpg"),
+                    "thanksgiving"
+                ),
+            ]
+        );
+
+        apply_batch_rename(&moves).unwrap();
+        assert!(root
+            .join("2024/2024-11-28_dad-thanksgiving/120500-001.jpg")
+            .exists());
+    }
+
+    #[test]
+    fn test_apply_batch_rename_is_all_or_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("20241128-120000-old.jpg"), b"a").unwrap();
+        let moves = vec![
+            RenameMove {
+                from: root.join("20241128-120000-old.jpg"),
+                to: root.join("20241128-120000-new.jpg"),
+                old_batch: "old".to_string(),
+                new_batch: "new".to_string(),
+            },
+            RenameMove {
+                from: root.join("20241128-130000-old.jpg"),
+                to: root.join("20241128-130000-new.jpg"),
+                old_batch: "old".to_string(),
+                new_batch: "new".to_string(),
+            },
+        ];
+
+        // The second file vanished after planning
+        assert!(apply_batch_rename(&moves).is_err());
+
+        assert!(root.join("20241128-120000-old.jpg").exists());
+        assert!(!root.join("20241128-120000-new.jpg").exists());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::fs_ops::{move_file, prune_empty_parents};
+use crate::manifest::update_manifests;
+use crate::xmp::sidecar_path;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Manifest directory inside the reorganized archive
+pub const REORGANIZE_DIR: &str = ".folio/reorganize";
+
+/// A planned move of one file into the folio layout (its sidecar follows it)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReorganizeMove {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Check a reorganize plan before anything is moved: no two files may land on
+/// the same name and nothing already on disk may be overwritten
+pub fn check_reorganize(moves: &[ReorganizeMove]) -> Result<()> {
+    let mut targets = HashSet::new();
+    for m in moves {
+        if !targets.insert(&m.to) {
+            anyhow::bail!("More than one file would be named {:?}", m.to);
+        }
+        if m.to.exists() {
+            anyhow::bail!("Moving {:?} would overwrite {:?}", m.from, m.to);
+        }
+    }
+    Ok(())
+}
+
+/// Write the rollback manifest for a plan, one tab-separated `from`/`to` line per
+/// file (sidecars included), before anything is moved
+pub fn write_reorganize_manifest(into: &Path, moves: &[ReorganizeMove]) -> Result<PathBuf> {
+    let dir = into.join(REORGANIZE_DIR);
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    let path = dir.join(format!("{}.tsv", Utc::now().format("%Y%m%d-%H%M%S%.3f")));
+
+    let mut file =
+        fs::File::create(&path).with_context(|| format!("Failed to create manifest {:?}", path))?;
+    writeln!(file, "# folio reorganize manifest: from\tto")?;
+    for (from, to) in with_sidecars(moves) {
+        writeln!(file, "{}\t{}", from.display(), to.display())?;
+    }
+    file.sync_all()?;
+    Ok(path)
+}
+
+/// Move every file (and sidecar) of the plan, rolling back on the first failure
+pub fn apply_reorganize(archive: &Path, moves: &[ReorganizeMove]) -> Result<()> {
+    let pairs = with_sidecars(moves);
+    for (i, (from, to)) in pairs.iter().enumerate() {
+        if let Err(e) = move_file(from, to) {
+            let failed = roll_back(&pairs[..i]);
+            if failed > 0 {
+                anyhow::bail!("{:#}; {} file(s) could not be moved back", e, failed);
+            }
+            return Err(e.context("Reorganize failed; all moved files were put back"));
+        }
+        if let Some(parent) = from.parent() {
+            prune_empty_parents(parent, archive)?;
+        }
+    }
+    Ok(())
+}
+
+/// Put files back where a manifest says they came from
+///
+/// Entries whose file is no longer at its new location are skipped, so a
+/// manifest of an interrupted reorganize can be rolled back too.
+/// Returns the number of files moved back.
+pub fn rollback_reorganize(manifest: &Path) -> Result<usize> {
+    let text = fs::read_to_string(manifest)
+        .with_context(|| format!("Failed to read manifest {:?}", manifest))?;
+
+    let mut pairs = Vec::new();
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+        let (from, to) = line
+            .split_once('\t')
+            .with_context(|| format!("Invalid manifest line: {}", line))?;
+        pairs.push((PathBuf::from(from), PathBuf::from(to)));
+    }
+
+    let present: Vec<_> = pairs.into_iter().filter(|(_, to)| to.exists()).collect();
+    let failed = roll_back(&present);
+    if failed > 0 {
+        anyhow::bail!("{} file(s) could not be moved back", failed);
+    }
+
+    // The manifest lives in <archive>/.folio/reorganize; its folders' manifests
+    // drop the files moved back out
+    if let Some(archive) = manifest.ancestors().nth(3) {
+        let removed: Vec<PathBuf> = present
+            .iter()
+            .filter_map(|(_, to)| to.strip_prefix(archive).ok().map(Path::to_path_buf))
+            .collect();
+        update_manifests(archive, &[], &removed)?;
+    }
+    Ok(present.len())
+}
+
+/// The moves plus their sidecars; a sidecar that `IMG_1.jpg` and `IMG_1.mov`
+/// share goes with the first of them
+fn with_sidecars(moves: &[ReorganizeMove]) -> Vec<(PathBuf, PathBuf)> {
+    let mut pairs = Vec::new();
+    let mut claimed = HashSet::new();
+    for m in moves {
+        pairs.push((m.from.clone(), m.to.clone()));
+        let sidecar = sidecar_path(&m.from);
+        if sidecar.exists() && sidecar != m.from && claimed.insert(sidecar.clone()) {
+            pairs.push((sidecar, sidecar_path(&m.to)));
+        }
+    }
+    pairs
+}
+
+/// Move files back in reverse order, returning how many couldn't be
+fn roll_back(pairs: &[(PathBuf, PathBuf)]) -> usize {
+    let mut failed = 0;
+    for (from, to) in pairs.iter().rev() {
+        if let Err(e) = move_file(to, from) {
+            tracing::error!("Failed to move {:?} back to {:?}: {:#}", to, from, e);
+            failed += 1;
+        }
+    }
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy_tree() -> (tempfile::TempDir, Vec<ReorganizeMove>) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("Pictures/Lake Tahoe")).unwrap();
+        fs::write(root.join("Pictures/Lake Tahoe/IMG_1.jpg"), b"a").unwrap();
+        fs::write(root.join("Pictures/Lake Tahoe/IMG_1.xmp"), b"x").unwrap();
+        fs::write(root.join("Pictures/Lake Tahoe/IMG_2.jpg"), b"b").unwrap();
+
+        let moves = vec![
+            ReorganizeMove {
+                from: root.join("Pictures/Lake Tahoe/IMG_1.jpg"),
+                to: root.join("archive/2019/07/04/20190704-100000-lake-tahoe.jpg"),
+            },
+            ReorganizeMove {
+                from: root.join("Pictures/Lake Tahoe/IMG_2.jpg"),
+                to: root.join("archive/2019/07/04/20190704-100500-lake-tahoe.jpg"),
+            },
+        ];
+        (dir, moves)
+    }
+
+    #[test]
+    fn test_reorganize_and_roll_back_from_manifest() {
+        let (dir, moves) = legacy_tree();
+        let root = dir.path();
+        let into = root.join("archive");
+
+        check_reorganize(&moves).unwrap();
+        let manifest = write_reorganize_manifest(&into, &moves).unwrap();
+        apply_reorganize(&root.join("Pictures"), &moves).unwrap();
+
+        assert!(into
+            .join("2019/07/04/20190704-100000-lake-tahoe.xmp")
+            .exists());
+        assert!(!root.join("Pictures/Lake Tahoe").exists());
+
+        assert_eq!(rollback_reorganize(&manifest).unwrap(), 3);
+        assert_eq!(
+            fs::read(root.join("Pictures/Lake Tahoe/IMG_1.jpg")).unwrap(),
+            b"a"
+        );
+        assert!(root.join("Pictures/Lake Tahoe/IMG_1.xmp").exists());
+        assert!(!into
+            .join("2019/07/04/20190704-100000-lake-tahoe.jpg")
+            .exists());
+    }
+
+    #[test]
+    fn test_check_reorganize_rejects_collisions() {
+        let (dir, mut moves) = legacy_tree();
+        moves[1].to = moves[0].to.clone();
+        assert!(check_reorganize(&moves).is_err());
+
+        let (_, mut moves) = legacy_tree();
+        moves[0].to = dir.path().join("Pictures/Lake Tahoe/IMG_2.jpg");
+        assert!(check_reorganize(&moves).is_err());
+    }
+
+    #[test]
+    fn test_apply_reorganize_puts_files_back_on_failure() {
+        let (dir, mut moves) = legacy_tree();
+        let root = dir.path();
+        moves.push(ReorganizeMove {
+            from: root.join("Pictures/Lake Tahoe/missing.jpg"),
+            to: root.join("archive/2019/07/04/20190704-101000-lake-tahoe.jpg"),
+        });
+
+        assert!(apply_reorganize(&root.join("Pictures"), &moves).is_err());
+
+        assert!(root.join("Pictures/Lake Tahoe/IMG_1.jpg").exists());
+        assert!(root.join("Pictures/Lake Tahoe/IMG_2.jpg").exists());
+        assert!(!root
+            .join("archive/2019/07/04/20190704-100000-lake-tahoe.jpg")
+            .exists());
+    }
+}
@@ -0,0 +1,456 @@
+use crate::config::{Birthday, FamilyConfig, FolioConfig};
+use crate::fs_ops::is_hidden;
+use crate::geocode::Location;
+use crate::media::{slugify_batch_name, validate_batch_name};
+use crate::template::PathTemplate;
+use anyhow::{Context, Result};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// At most this many suggestions are offered, so each is picked with one digit
+pub const MAX_SUGGESTIONS: usize = 9;
+
+/// Birthday parties rarely fall on the day itself
+const BIRTHDAY_WINDOW_DAYS: i64 = 3;
+
+/// How far the same dates in earlier years may drift (moving holidays, weekends)
+const PREVIOUS_YEARS_WINDOW_DAYS: i64 = 7;
+
+/// How close an archived batch must be to count as nearby
+const NEARBY_WINDOW_DAYS: i64 = 2;
+
+/// A suggested batch name and why it was suggested
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameSuggestion {
+    pub name: String,
+    pub reason: String,
+}
+
+enum HolidayRule {
+    Fixed {
+        month: u32,
+        day: u32,
+    },
+    /// The n-th given weekday of a month
+    NthWeekday {
+        month: u32,
+        weekday: Weekday,
+        n: u8,
+    },
+    Easter,
+}
+
+struct Holiday {
+    name: &'static str,
+    title: &'static str,
+    rule: HolidayRule,
+}
+
+const HOLIDAYS: &[Holiday] = &[
+    Holiday {
+        name: "new-year",
+        title: "New Year's Day",
+        rule: HolidayRule::Fixed { month: 1, day: 1 },
+    },
+    Holiday {
+        name: "valentines-day",
+        title: "Valentine's Day",
+        rule: HolidayRule::Fixed { month: 2, day: 14 },
+    },
+    Holiday {
+        name: "easter",
+        title: "Easter",
+        rule: HolidayRule::Easter,
+    },
+    Holiday {
+        name: "mothers-day",
+        title: "Mother's Day",
+        rule: HolidayRule::NthWeekday {
+            month: 5,
+            weekday: Weekday::Sun,
+            n: 2,
+        },
+    },
+    Holiday {
+        name: "fathers-day",
+        title: "Father's Day",
+        rule: HolidayRule::NthWeekday {
+            month: 6,
+            weekday: Weekday::Sun,
+            n: 3,
+        },
+    },
+    Holiday {
+        name: "independence-day",
+        title: "Independence Day",
+        rule: HolidayRule::Fixed { month: 7, day: 4 },
+    },
+    Holiday {
+        name: "halloween",
+        title: "Halloween",
+        rule: HolidayRule::Fixed { month: 10, day: 31 },
+    },
+    Holiday {
+        name: "thanksgiving",
+        title: "Thanksgiving",
+        rule: HolidayRule::NthWeekday {
+            month: 11,
+            weekday: Weekday::Thu,
+            n: 4,
+        },
+    },
+    Holiday {
+        name: "christmas-eve",
+        title: "Christmas Eve",
+        rule: HolidayRule::Fixed { month: 12, day: 24 },
+    },
+    Holiday {
+        name: "christmas",
+        title: "Christmas",
+        rule: HolidayRule::Fixed { month: 12, day: 25 },
+    },
+    Holiday {
+        name: "new-years-eve",
+        title: "New Year's Eve",
+        rule: HolidayRule::Fixed { month: 12, day: 31 },
+    },
+];
+
+impl Holiday {
+    fn date(&self, year: i32) -> Option<NaiveDate> {
+        match self.rule {
+            HolidayRule::Fixed { month, day } => NaiveDate::from_ymd_opt(year, month, day),
+            HolidayRule::NthWeekday { month, weekday, n } => {
+                NaiveDate::from_weekday_of_month_opt(year, month, weekday, n)
+            }
+            HolidayRule::Easter => easter_sunday(year),
+        }
+    }
+}
+
+/// Western Easter Sunday (anonymous Gregorian algorithm)
+fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+/// Dates and batch names already in an archive, read from archive paths
+#[derive(Debug, Clone, Default)]
+pub struct BatchHistory {
+    entries: BTreeSet<(NaiveDate, String)>,
+}
+
+impl BatchHistory {
+    /// Collect every (date, batch) pair `template` reads from the archive's
+    /// paths; a missing archive has no history
+    pub fn scan(archive: &Path, template: &PathTemplate) -> Result<Self> {
+        let mut history = BatchHistory::default();
+        if !archive.exists() {
+            return Ok(history);
+        }
+
+        let walker = WalkDir::new(archive)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !is_hidden(entry.path()));
+        for entry in walker {
+            let entry = entry.context("Failed to read directory entry")?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Some(fields) = template.read(entry.path().strip_prefix(archive)?) else {
+                continue;
+            };
+            if let (Some(date), Some(batch)) = (fields.date(), fields.batch()) {
+                history.add(date, batch.to_string());
+            }
+        }
+        Ok(history)
+    }
+
+    pub fn add(&mut self, date: NaiveDate, batch: String) {
+        self.entries.insert((date, batch));
+    }
+}
+
+/// Suggests names for a batch from its location, holidays, family birthdays and
+/// the archive's history
+#[derive(Debug, Clone, Default)]
+pub struct NameSuggester {
+    birthdays: Vec<Birthday>,
+    history: BatchHistory,
+}
+
+impl NameSuggester {
+    pub fn new(birthdays: Vec<Birthday>, history: BatchHistory) -> Self {
+        NameSuggester { birthdays, history }
+    }
+
+    /// Read `family.toml` and the batch history of an archive, in its layout
+    pub fn load(archive: &Path) -> Result<Self> {
+        let template = FolioConfig::load_layered(archive)?
+            .template
+            .unwrap_or_default();
+        Ok(NameSuggester::new(
+            FamilyConfig::load(archive)?.birthdays,
+            BatchHistory::scan(archive, &template)?,
+        ))
+    }
+
+    /// Suggestions for a batch covering `start..=end` taken mostly at `location`,
+    /// best first, without repeats
+    pub fn suggest(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        location: Option<&Location>,
+    ) -> Vec<NameSuggestion> {
+        let mut suggestions = Vec::new();
+
+        if let Some(location) = location {
+            for place in [&location.landmark, &location.city].into_iter().flatten() {
+                suggestions.push(NameSuggestion {
+                    name: slugify_batch_name(place),
+                    reason: location.describe(),
+                });
+            }
+        }
+
+        for year in start.year()..=end.year() {
+            for holiday in HOLIDAYS {
+                if holiday
+                    .date(year)
+                    .is_some_and(|date| start <= date && date <= end)
+                {
+                    suggestions.push(NameSuggestion {
+                        name: holiday.name.to_string(),
+                        reason: holiday.title.to_string(),
+                    });
+                }
+            }
+        }
+
+        let window = Duration::days(BIRTHDAY_WINDOW_DAYS);
+        for birthday in &self.birthdays {
+            if anniversary_within(birthday.date, start - window, end + window) {
+                suggestions.push(NameSuggestion {
+                    name: slugify_batch_name(&format!("{} birthday", birthday.name)),
+                    reason: format!("{}'s birthday", birthday.name),
+                });
+            }
+        }
+
+        // The same dates in earlier years, most recent year first
+        let window = Duration::days(PREVIOUS_YEARS_WINDOW_DAYS);
+        let mut previous: Vec<_> = self
+            .history
+            .entries
+            .iter()
+            .filter(|(date, _)| {
+                date.year() < start.year()
+                    && anniversary_within(*date, start - window, end + window)
+            })
+            .collect();
+        previous.sort_by_key(|(date, _)| std::cmp::Reverse(*date));
+        for (date, batch) in previous {
+            suggestions.push(NameSuggestion {
+                name: batch.clone(),
+                reason: format!("used on {}", date),
+            });
+        }
+
+        // Batches already archived around these dates, closest first
+        let window = Duration::days(NEARBY_WINDOW_DAYS);
+        let distance = |date: NaiveDate| {
+            if date < start {
+                start - date
+            } else if date > end {
+                date - end
+            } else {
+                Duration::zero()
+            }
+        };
+        let mut nearby: Vec<_> = self
+            .history
+            .entries
+            .iter()
+            .filter(|(date, _)| distance(*date) <= window)
+            .collect();
+        nearby.sort_by_key(|(date, _)| distance(*date));
+        for (date, batch) in nearby {
+            suggestions.push(NameSuggestion {
+                name: batch.clone(),
+                reason: format!("archived batch on {}", date),
+            });
+        }
+
+        let mut seen = HashSet::new();
+        suggestions.retain(|s| validate_batch_name(&s.name).is_ok() && seen.insert(s.name.clone()));
+        suggestions.truncate(MAX_SUGGESTIONS);
+        suggestions
+    }
+}
+
+/// Whether the month and day of `date` fall within `start..=end` in any year of that range
+fn anniversary_within(date: NaiveDate, start: NaiveDate, end: NaiveDate) -> bool {
+    (start.year()..=end.year()).any(|year| {
+        NaiveDate::from_ymd_opt(year, date.month(), date.day())
+            .is_some_and(|anniversary| start <= anniversary && anniversary <= end)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn names(suggestions: &[NameSuggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_holiday_rules() {
+        let suggester = NameSuggester::default();
+
+        assert_eq!(
+            names(&suggester.suggest(date("2024-11-28"), date("2024-11-28"), None)),
+            vec!["thanksgiving"]
+        );
+        assert_eq!(
+            names(&suggester.suggest(date("2024-03-31"), date("2024-03-31"), None)),
+            vec!["easter"]
+        );
+        assert_eq!(
+            names(&suggester.suggest(date("2024-12-24"), date("2025-01-01"), None)),
+            vec!["christmas-eve", "christmas", "new-years-eve", "new-year"]
+        );
+        assert!(suggester
+            .suggest(date("2024-11-21"), date("2024-11-21"), None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_birthdays_match_within_a_few_days() {
+        let suggester = NameSuggester::new(
+            vec![Birthday {
+                name: "Emma".to_string(),
+                date: date("2016-03-14"),
+            }],
+            BatchHistory::default(),
+        );
+
+        let suggestions = suggester.suggest(date("2024-03-16"), date("2024-03-16"), None);
+        assert_eq!(
+            suggestions,
+            vec![NameSuggestion {
+                name: "emma-birthday".to_string(),
+                reason: "Emma's birthday".to_string(),
+            }]
+        );
+        assert!(suggester
+            .suggest(date("2024-04-14"), date("2024-04-14"), None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_history_suggests_previous_years_and_nearby_batches() {
+        let mut history = BatchHistory::default();
+        history.add(date("2022-11-05"), "fall-hike".to_string());
+        history.add(date("2023-11-02"), "pumpkin-farm".to_string());
+        history.add(date("2024-11-05"), "school-play".to_string());
+        history.add(date("2024-11-05"), "thanksgiving".to_string());
+        history.add(date("2024-06-01"), "summer".to_string());
+        let suggester = NameSuggester::new(Vec::new(), history);
+
+        assert_eq!(
+            names(&suggester.suggest(date("2024-11-04"), date("2024-11-04"), None)),
+            vec!["pumpkin-farm", "fall-hike", "school-play", "thanksgiving"]
+        );
+    }
+
+    #[test]
+    fn test_location_comes_first() {
+        let location = Location {
+            landmark: Some("Lake Tahoe".to_string()),
+            city: Some("South Lake Tahoe".to_string()),
+            region: Some("California".to_string()),
+            country: Some("United States".to_string()),
+        };
+
+        let suggestions = NameSuggester::default().suggest(
+            date("2024-07-04"),
+            date("2024-07-04"),
+            Some(&location),
+        );
+
+        assert_eq!(
+            names(&suggestions),
+            vec!["lake-tahoe", "south-lake-tahoe", "independence-day"]
+        );
+        assert_eq!(
+            suggestions[0].reason,
+            "Lake Tahoe (South Lake Tahoe, California, United States)"
+        );
+    }
+
+    #[test]
+    fn test_scan_reads_archive_filenames() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("2023/11/02")).unwrap();
+        fs::create_dir_all(dir.path().join(".folio")).unwrap();
+        fs::write(
+            dir.path()
+                .join("2023/11/02/20231102-100000-pumpkin-farm.jpg"),
+            b"a",
+        )
+        .unwrap();
+        fs::write(dir.path().join("2023/11/02/IMG_0001.jpg"), b"b").unwrap();
+        fs::write(dir.path().join(".folio/20231102-100000-hidden.jpg"), b"c").unwrap();
+
+        let template = PathTemplate::default();
+        let history = BatchHistory::scan(dir.path(), &template).unwrap();
+
+        assert_eq!(
+            history.entries.into_iter().collect::<Vec<_>>(),
+            vec![(date("2023-11-02"), "pumpkin-farm".to_string())]
+        );
+        assert!(BatchHistory::scan(&dir.path().join("missing"), &template).is_ok());
+    }
+
+    #[test]
+    fn test_scan_reads_batch_folders_of_custom_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let template: PathTemplate = "{year}/{date}_{batch}/{stem}.{ext}".parse().unwrap();
+        fs::create_dir_all(dir.path().join("2023/2023-11-02_pumpkin-farm")).unwrap();
+        fs::write(
+            dir.path().join("2023/2023-11-02_pumpkin-farm/IMG_0001.jpg"),
+            b"a",
+        )
+        .unwrap();
+
+        let history = BatchHistory::scan(dir.path(), &template).unwrap();
+
+        assert_eq!(
+            history.entries.into_iter().collect::<Vec<_>>(),
+            vec![(date("2023-11-02"), "pumpkin-farm".to_string())]
+        );
+    }
+}
@@ -0,0 +1,698 @@
+use crate::media::{CameraInfo, MediaType};
+use anyhow::Result;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Timelike, Utc};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+/// Layout matching `generate_folder_path` + `generate_filename`
+pub const DEFAULT_TEMPLATE: &str =
+    "{year}/{month}/{day}/{year}{month}{day}-{hour}{minute}{second}-{batch}.{ext}";
+
+/// Placeholders available in a path template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    /// Four-digit year
+    Year,
+    /// Two-digit month
+    Month,
+    /// Two-digit day of month
+    Day,
+    /// Two-digit hour (24h)
+    Hour,
+    /// Two-digit minute
+    Minute,
+    /// Two-digit second
+    Second,
+    /// YYYY-MM-DD
+    Date,
+    /// HHMMSS
+    Time,
+    /// Batch name
+    Batch,
+    /// 1-based position within the batch, zero-padded to the given width
+    Seq(usize),
+    /// Camera label, e.g. nikon-d800 (or "unknown-camera")
+    Camera,
+    /// Original filename without extension
+    Stem,
+    /// "photo" or "video"
+    Kind,
+    /// Original extension
+    Ext,
+}
+
+impl Token {
+    const NAMES: &'static [&'static str] = &[
+        "year", "month", "day", "hour", "minute", "second", "date", "time", "batch", "seq",
+        "camera", "stem", "kind", "ext",
+    ];
+
+    fn parse(spec: &str) -> Result<Token> {
+        let (name, argument) = match spec.split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (spec, None),
+        };
+
+        let token = match name {
+            "year" => Token::Year,
+            "month" => Token::Month,
+            "day" => Token::Day,
+            "hour" => Token::Hour,
+            "minute" => Token::Minute,
+            "second" => Token::Second,
+            "date" => Token::Date,
+            "time" => Token::Time,
+            "batch" => Token::Batch,
+            "seq" => {
+                let width = match argument {
+                    Some(width) => width.parse().map_err(|_| {
+                        anyhow::anyhow!("Invalid width in {{{}}} (expected e.g. {{seq:4}})", spec)
+                    })?,
+                    None => 3,
+                };
+                return Ok(Token::Seq(width));
+            }
+            "camera" => Token::Camera,
+            "stem" => Token::Stem,
+            "kind" => Token::Kind,
+            "ext" => Token::Ext,
+            _ => anyhow::bail!(
+                "Unknown template token {{{}}} (available: {})",
+                spec,
+                Token::NAMES.join(", ")
+            ),
+        };
+
+        if argument.is_some() {
+            anyhow::bail!("Template token {{{}}} takes no argument", name);
+        }
+        Ok(token)
+    }
+
+    /// Tokens that tell files within one folder apart
+    fn is_distinguishing(&self) -> bool {
+        matches!(
+            self,
+            Token::Second | Token::Time | Token::Seq(_) | Token::Stem
+        )
+    }
+
+    /// Whether both are the same placeholder, whatever their arguments
+    fn same_kind(&self, other: &Token) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// The value of a date or time token at `ts`
+    fn format_time(&self, ts: DateTime<Utc>) -> Option<String> {
+        Some(match self {
+            Token::Year => format!("{:04}", ts.year()),
+            Token::Month => format!("{:02}", ts.month()),
+            Token::Day => format!("{:02}", ts.day()),
+            Token::Hour => format!("{:02}", ts.hour()),
+            Token::Minute => format!("{:02}", ts.minute()),
+            Token::Second => format!("{:02}", ts.second()),
+            Token::Date => ts.format("%Y-%m-%d").to_string(),
+            Token::Time => ts.format("%H%M%S").to_string(),
+            _ => return None,
+        })
+    }
+
+    /// Whether `value` is something this token renders to
+    fn accepts(&self, value: &str) -> bool {
+        let digits = |len: usize| value.len() == len && value.bytes().all(|b| b.is_ascii_digit());
+        let name_chars = || {
+            value
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        };
+        match self {
+            Token::Year => digits(4),
+            Token::Month | Token::Day | Token::Hour | Token::Minute | Token::Second => digits(2),
+            Token::Date => {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok() && value.len() == 10
+            }
+            Token::Time => digits(6),
+            Token::Seq(width) => value.len() >= *width && digits(value.len()),
+            Token::Batch | Token::Camera => name_chars(),
+            Token::Stem => !value.contains('/'),
+            Token::Kind => value == "photo" || value == "video",
+            Token::Ext => value.chars().all(|c| c.is_alphanumeric()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Token(Token),
+}
+
+/// Values substituted into a template for one file
+#[derive(Debug, Clone)]
+pub struct TemplateContext<'a> {
+    pub timestamp: DateTime<Utc>,
+    pub batch_name: &'a str,
+    pub seq: usize,
+    pub camera: Option<&'a CameraInfo>,
+    pub original_path: &'a Path,
+    pub media_type: &'a MediaType,
+}
+
+/// Token values read back from an archived path, see `PathTemplate::read`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathFields {
+    values: Vec<(Token, String)>,
+    /// Counter added to a name that was taken, see `with_counter`
+    pub counter: Option<usize>,
+}
+
+impl PathFields {
+    fn value(&self, token: Token) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(t, _)| t.same_kind(&token))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn batch(&self) -> Option<&str> {
+        self.value(Token::Batch)
+    }
+
+    pub fn seq(&self) -> Option<usize> {
+        self.value(Token::Seq(0))?.parse().ok()
+    }
+
+    /// Capture date, if the path holds one
+    pub fn date(&self) -> Option<NaiveDate> {
+        if let Some(date) = self.value(Token::Date) {
+            return NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+        }
+        let number = |token| self.value(token)?.parse().ok();
+        NaiveDate::from_ymd_opt(
+            number(Token::Year)? as i32,
+            number(Token::Month)?,
+            number(Token::Day)?,
+        )
+    }
+
+    /// Capture time, if the path holds the date and the time to the second
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        let time = match self.value(Token::Time) {
+            Some(time) => NaiveTime::parse_from_str(time, "%H%M%S").ok()?,
+            None => {
+                let number = |token| self.value(token)?.parse().ok();
+                NaiveTime::from_hms_opt(
+                    number(Token::Hour)?,
+                    number(Token::Minute)?,
+                    number(Token::Second)?,
+                )?
+            }
+        };
+        Some(self.date()?.and_time(time).and_utc())
+    }
+}
+
+/// A validated folder + filename template, relative to the archive root
+///
+/// Example: `{year}/{date}_{batch}/{date}-{time}-{batch}-{seq}.{ext}`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct PathTemplate {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl PathTemplate {
+    /// Parse and validate a template
+    ///
+    /// The template must be relative, end in `.{ext}`, and contain a token
+    /// that tells files apart ({second}, {time}, {seq} or {stem}). Names that
+    /// still coincide, e.g. burst shots, get a counter when ingested.
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    let Some(close) = chars.as_str().find('}') else {
+                        anyhow::bail!("Unmatched '{{' in template '{}'", template);
+                    };
+                    let spec = chars.as_str()[..close].to_string();
+                    chars = chars.as_str()[close + 1..].chars();
+
+                    if spec.is_empty() || spec.contains('{') {
+                        anyhow::bail!("Empty or nested token in template '{}'", template);
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Token(Token::parse(&spec)?));
+                }
+                '}' => anyhow::bail!("Unmatched '}}' in template '{}'", template),
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        let parsed = PathTemplate {
+            source: template.to_string(),
+            segments,
+        };
+        parsed.validate()?;
+        Ok(parsed)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let template = &self.source;
+
+        let path = Path::new(template);
+        if path.is_absolute() || template.starts_with('/') {
+            anyhow::bail!(
+                "Template must be relative to the archive (got: '{}')",
+                template
+            );
+        }
+        if path
+            .components()
+            .any(|c| matches!(c, Component::ParentDir | Component::CurDir))
+            || template.split('/').any(|part| part.is_empty())
+        {
+            anyhow::bail!(
+                "Template must not contain empty, '.' or '..' folders (got: '{}')",
+                template
+            );
+        }
+
+        let ends_with_ext = matches!(
+            self.segments.as_slice(),
+            [.., Segment::Literal(dot), Segment::Token(Token::Ext)] if dot.ends_with('.')
+        );
+        if !ends_with_ext {
+            anyhow::bail!("Template must end with '.{{ext}}' (got: '{}')", template);
+        }
+
+        let file_part_start = self
+            .segments
+            .iter()
+            .rposition(|s| matches!(s, Segment::Literal(l) if l.contains('/')))
+            .unwrap_or(0);
+        let unique = self.segments[file_part_start..]
+            .iter()
+            .any(|s| matches!(s, Segment::Token(t) if t.is_distinguishing()));
+        if !unique {
+            anyhow::bail!(
+                "Template filename must contain {{second}}, {{time}}, {{seq}} or {{stem}} \
+                 to tell files apart (got: '{}')",
+                template
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The template text
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether rendered names hold a `{seq}` number
+    pub fn has_seq(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|s| matches!(s, Segment::Token(Token::Seq(_))))
+    }
+
+    /// Read the token values back from an archive-relative path this template
+    /// rendered, including a counter added by `with_counter`
+    pub fn read(&self, relative: &Path) -> Option<PathFields> {
+        let text = relative
+            .components()
+            .map(|c| c.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()?
+            .join("/");
+        let mut values = Vec::new();
+        if match_segments(&self.segments, &text, &mut values) {
+            return Some(PathFields {
+                values,
+                counter: None,
+            });
+        }
+
+        let (rest, ext) = text.rsplit_once('.')?;
+        let (base, counter) = rest.rsplit_once('.')?;
+        if counter.is_empty() || !counter.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let counter = counter.parse().ok()?;
+        let mut values = Vec::new();
+        match_segments(&self.segments, &format!("{}.{}", base, ext), &mut values).then_some(
+            PathFields {
+                values,
+                counter: Some(counter),
+            },
+        )
+    }
+
+    /// Render a path read by `read` again, with another batch and, if given,
+    /// capture time; everything else, counter included, stays as read
+    pub fn rerender(
+        &self,
+        fields: &PathFields,
+        timestamp: Option<DateTime<Utc>>,
+        batch: &str,
+    ) -> PathBuf {
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => rendered.push_str(text),
+                Segment::Token(Token::Batch) => rendered.push_str(batch),
+                Segment::Token(token) => {
+                    let value = timestamp
+                        .and_then(|ts| token.format_time(ts))
+                        .or_else(|| fields.value(*token).map(str::to_string))
+                        .unwrap_or_default();
+                    rendered.push_str(&value);
+                }
+            }
+        }
+        match fields.counter {
+            Some(counter) => with_counter(Path::new(&rendered), counter),
+            None => PathBuf::from(rendered),
+        }
+    }
+
+    /// Render the archive-relative path for one file
+    pub fn render(&self, context: &TemplateContext) -> PathBuf {
+        let ts = context.timestamp;
+        let mut rendered = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => rendered.push_str(text),
+                Segment::Token(token) => {
+                    let value = match token {
+                        Token::Year
+                        | Token::Month
+                        | Token::Day
+                        | Token::Hour
+                        | Token::Minute
+                        | Token::Second
+                        | Token::Date
+                        | Token::Time => token.format_time(ts).unwrap_or_default(),
+                        Token::Batch => context.batch_name.to_string(),
+                        Token::Seq(width) => format!("{:0width$}", context.seq, width = width),
+                        Token::Camera => context
+                            .camera
+                            .map(|camera| path_safe(&camera.label()).to_lowercase())
+                            .unwrap_or_else(|| "unknown-camera".to_string()),
+                        Token::Stem => context
+                            .original_path
+                            .file_stem()
+                            .map(|stem| path_safe(&stem.to_string_lossy()))
+                            .unwrap_or_default(),
+                        Token::Kind => {
+                            if context.media_type.is_video() {
+                                "video".to_string()
+                            } else {
+                                "photo".to_string()
+                            }
+                        }
+                        Token::Ext => context
+                            .original_path
+                            .extension()
+                            .and_then(|e| e.to_str())
+                            .unwrap_or("jpg")
+                            .to_string(),
+                    };
+                    rendered.push_str(&value);
+                }
+            }
+        }
+
+        PathBuf::from(rendered)
+    }
+}
+
+impl Default for PathTemplate {
+    fn default() -> Self {
+        PathTemplate::parse(DEFAULT_TEMPLATE).expect("default template is valid")
+    }
+}
+
+impl FromStr for PathTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        PathTemplate::parse(s)
+    }
+}
+
+impl TryFrom<String> for PathTemplate {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        PathTemplate::parse(&s)
+    }
+}
+
+impl fmt::Display for PathTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Match `text` against `segments`, collecting token values; a token that
+/// appears more than once must have the same value each time
+fn match_segments(segments: &[Segment], text: &str, values: &mut Vec<(Token, String)>) -> bool {
+    let Some((first, rest)) = segments.split_first() else {
+        return text.is_empty();
+    };
+    let token = match first {
+        Segment::Literal(literal) => {
+            return text
+                .strip_prefix(literal.as_str())
+                .is_some_and(|text| match_segments(rest, text, values))
+        }
+        Segment::Token(token) => token,
+    };
+
+    // Longest value first, within the current folder
+    let folder_end = text.find('/').unwrap_or(text.len());
+    let ends: Vec<usize> = text[..folder_end]
+        .char_indices()
+        .map(|(i, c)| i + c.len_utf8())
+        .collect();
+    for &end in ends.iter().rev() {
+        let value = &text[..end];
+        let repeated = values
+            .iter()
+            .find(|(t, _)| *t == *token)
+            .map(|(_, v)| v.as_str());
+        if !token.accepts(value) || repeated.is_some_and(|v| v != value) {
+            continue;
+        }
+        values.push((*token, value.to_string()));
+        if match_segments(rest, &text[end..], values) {
+            return true;
+        }
+        values.pop();
+    }
+    false
+}
+
+/// `path` with a counter before its extension, e.g. `a/b.2.jpg`, for a file
+/// whose rendered name is taken
+///
+/// A dot can't appear in batch names, so the counter never reads as part of one.
+pub fn with_counter(path: &Path, counter: usize) -> PathBuf {
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(ext)) => path.with_file_name(format!(
+            "{}.{}.{}",
+            stem.to_string_lossy(),
+            counter,
+            ext.to_string_lossy()
+        )),
+        _ => PathBuf::from(format!("{}.{}", path.display(), counter)),
+    }
+}
+
+/// Replace characters that would change the path structure with hyphens
+fn path_safe(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::{generate_filename, generate_folder_path, PhotoFormat, VideoFormat};
+
+    fn timestamp() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-11-04T14:02:15Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn render(template: &str, seq: usize) -> String {
+        let camera = CameraInfo {
+            make: Some("Nikon".to_string()),
+            model: Some("D800".to_string()),
+            serial: None,
+        };
+        let context = TemplateContext {
+            timestamp: timestamp(),
+            batch_name: "thanksgiving",
+            seq,
+            camera: Some(&camera),
+            original_path: Path::new("/Volumes/SD/DCIM/DSC_0042.JPG"),
+            media_type: &MediaType::Photo(PhotoFormat::Jpeg),
+        };
+        PathTemplate::parse(template)
+            .unwrap()
+            .render(&context)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_default_template_matches_generate_functions() {
+        let expected = generate_folder_path(timestamp()).join(generate_filename(
+            timestamp(),
+            "thanksgiving",
+            "JPG",
+        ));
+
+        assert_eq!(render(DEFAULT_TEMPLATE, 1), expected.to_string_lossy());
+    }
+
+    #[test]
+    fn test_adr_layout_with_seq() {
+        assert_eq!(
+            render("{year}/{date}_{batch}/{date}-{time}-{batch}-{seq}.{ext}", 7),
+            "2024/2024-11-04_thanksgiving/2024-11-04-140215-thanksgiving-007.JPG"
+        );
+    }
+
+    #[test]
+    fn test_camera_stem_and_kind_tokens() {
+        assert_eq!(
+            render("{kind}/{camera}/{stem}-{seq:2}.{ext}", 3),
+            "photo/nikon-d800/DSC_0042-03.JPG"
+        );
+
+        let context = TemplateContext {
+            timestamp: timestamp(),
+            batch_name: "b",
+            seq: 1,
+            camera: None,
+            original_path: Path::new("clip.mov"),
+            media_type: &MediaType::Video(VideoFormat::Mov),
+        };
+        let rendered = PathTemplate::parse("{kind}/{camera}/{stem}.{ext}")
+            .unwrap()
+            .render(&context);
+        assert_eq!(rendered, PathBuf::from("video/unknown-camera/clip.mov"));
+    }
+
+    #[test]
+    fn test_read_default_template() {
+        let template = PathTemplate::default();
+        let fields = template
+            .read(Path::new("2024/11/04/20241104-140215-lake-tahoe_2.jpg"))
+            .unwrap();
+        assert_eq!(fields.batch(), Some("lake-tahoe_2"));
+        assert_eq!(fields.timestamp(), Some(timestamp()));
+        assert_eq!(fields.counter, None);
+
+        // A counter is never read as part of the batch name
+        let fields = template
+            .read(Path::new("2024/11/04/20241104-140215-day-2.3.jpg"))
+            .unwrap();
+        assert_eq!(fields.batch(), Some("day-2"));
+        assert_eq!(fields.counter, Some(3));
+
+        // Folder and filename disagree on the date
+        assert!(template
+            .read(Path::new("2024/11/05/20241104-140215-trip.jpg"))
+            .is_none());
+        assert!(template
+            .read(Path::new("2024/11/04/IMG_1234.jpg"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_read_and_rerender_custom_template() {
+        let template =
+            PathTemplate::parse("{year}/{date}_{batch}/{date}-{time}-{batch}-{seq}.{ext}").unwrap();
+        let path = Path::new("2024/2024-11-04_thanksgving/2024-11-04-140215-thanksgving-007.JPG");
+        let fields = template.read(path).unwrap();
+        assert_eq!(fields.batch(), Some("thanksgving"));
+        assert_eq!(fields.seq(), Some(7));
+        assert_eq!(fields.timestamp(), Some(timestamp()));
+        assert_eq!(
+            template.rerender(&fields, None, "thanksgiving"),
+            PathBuf::from("2024/2024-11-04_thanksgiving/2024-11-04-140215-thanksgiving-007.JPG")
+        );
+        let later = timestamp() + chrono::Duration::days(1);
+        assert_eq!(
+            template.rerender(&fields, Some(later), "thanksgving"),
+            PathBuf::from("2024/2024-11-05_thanksgving/2024-11-05-140215-thanksgving-007.JPG")
+        );
+
+        // Without a time in the name there is no timestamp, only a date
+        let template = PathTemplate::parse("{year}/{batch}/{batch}-{seq}.{ext}").unwrap();
+        let fields = template
+            .read(Path::new("2024/trip/trip-012.2.jpg"))
+            .unwrap();
+        assert_eq!(fields.seq(), Some(12));
+        assert_eq!(fields.counter, Some(2));
+        assert_eq!(fields.timestamp(), None);
+        assert_eq!(
+            template.rerender(&fields, None, "hike"),
+            PathBuf::from("2024/hike/hike-012.2.jpg")
+        );
+    }
+
+    #[test]
+    fn test_with_counter() {
+        assert_eq!(
+            with_counter(Path::new("2024/11/04/20241104-140215-trip.jpg"), 2),
+            PathBuf::from("2024/11/04/20241104-140215-trip.2.jpg")
+        );
+        assert_eq!(with_counter(Path::new("a/b"), 3), PathBuf::from("a/b.3"));
+    }
+
+    #[test]
+    fn test_invalid_templates_are_rejected() {
+        // Unknown token
+        assert!(PathTemplate::parse("{year}/{colour}-{seq}.{ext}").is_err());
+        // Unbalanced braces
+        assert!(PathTemplate::parse("{year/{seq}.{ext}").is_err());
+        assert!(PathTemplate::parse("{year}}/{seq}.{ext}").is_err());
+        // Missing extension
+        assert!(PathTemplate::parse("{year}/{seq}").is_err());
+        // Filename could collide
+        assert!(PathTemplate::parse("{year}/{time}/{batch}.{ext}").is_err());
+        // Escapes the archive
+        assert!(PathTemplate::parse("../{year}/{seq}.{ext}").is_err());
+        assert!(PathTemplate::parse("/{year}/{seq}.{ext}").is_err());
+        assert!(PathTemplate::parse("{year}//{seq}.{ext}").is_err());
+        // Bad argument
+        assert!(PathTemplate::parse("{seq:x}.{ext}").is_err());
+        assert!(PathTemplate::parse("{year:4}/{seq}.{ext}").is_err());
+    }
+}
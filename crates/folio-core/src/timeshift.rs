@@ -0,0 +1,619 @@
+use crate::config::FolioConfig;
+use crate::fs_ops::{is_hidden, move_file, prune_empty_parents};
+use crate::manifest::relocate_in_manifests;
+use crate::media::{
+    detect_media_type, generate_folder_path, get_camera_info, get_capture_timestamp, CameraInfo,
+    MediaItem, MediaType,
+};
+use crate::template::{PathFields, PathTemplate};
+use crate::xmp::{format_xmp_date, parse_xmp_date, sidecar_path, XmpDocument, XmpField};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use walkdir::WalkDir;
+
+/// A camera clock correction, e.g. `NIKON D800=+1h`
+///
+/// The camera key is matched case-insensitively against the camera label,
+/// the model, "make model" and the body serial number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeShift {
+    pub camera: String,
+    pub offset: Duration,
+}
+
+impl TimeShift {
+    /// Whether this correction applies to an item's camera
+    pub fn applies_to(&self, item: &MediaItem) -> bool {
+        item.camera
+            .as_ref()
+            .is_some_and(|camera| camera_matches(&self.camera, camera))
+    }
+}
+
+impl FromStr for TimeShift {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (camera, offset) = s
+            .rsplit_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Expected CAMERA=OFFSET (got: '{}')", s))?;
+        let camera = camera.trim();
+        if camera.is_empty() {
+            anyhow::bail!("Camera name cannot be empty (got: '{}')", s);
+        }
+
+        Ok(TimeShift {
+            camera: camera.to_string(),
+            offset: parse_offset(offset.trim())?,
+        })
+    }
+}
+
+/// Parse a clock offset such as `+1h`, `-30m`, `+1h30m` or `+5110d`
+///
+/// Units are d (days), h (hours), m (minutes) and s (seconds). The sign is
+/// optional and defaults to `+`.
+pub fn parse_offset(s: &str) -> Result<Duration> {
+    let invalid = || anyhow::anyhow!("Invalid time offset '{}' (expected e.g. +1h or -30m)", s);
+
+    let (negative, body) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    if body.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+    for c in body.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let value: i64 = digits.parse().map_err(|_| invalid())?;
+        digits.clear();
+        total += match c {
+            'd' => Duration::days(value),
+            'h' => Duration::hours(value),
+            'm' => Duration::minutes(value),
+            's' => Duration::seconds(value),
+            _ => return Err(invalid()),
+        };
+    }
+    if !digits.is_empty() {
+        // Trailing number without a unit
+        return Err(invalid());
+    }
+
+    Ok(if negative { -total } else { total })
+}
+
+/// Format an offset the way `parse_offset` reads it, e.g. `+1h30m`
+pub fn format_offset(offset: Duration) -> String {
+    let sign = if offset < Duration::zero() { '-' } else { '+' };
+    let mut seconds = offset.num_seconds().abs();
+    if seconds == 0 {
+        return "+0s".to_string();
+    }
+
+    let mut formatted = sign.to_string();
+    for (unit, size) in [('d', 86_400), ('h', 3_600), ('m', 60), ('s', 1)] {
+        if seconds >= size {
+            formatted.push_str(&format!("{}{}", seconds / size, unit));
+            seconds %= size;
+        }
+    }
+    formatted
+}
+
+fn camera_matches(key: &str, camera: &CameraInfo) -> bool {
+    let key = key.to_lowercase();
+    let make_model = match (&camera.make, &camera.model) {
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        _ => None,
+    };
+
+    [
+        Some(camera.label()),
+        camera.model.clone(),
+        make_model,
+        camera.serial.clone(),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|candidate| candidate.to_lowercase() == key)
+}
+
+/// Apply the first matching correction to each item's timestamp and folder path
+/// Returns the number of items that were shifted
+pub fn apply_time_shifts(items: &mut [MediaItem], shifts: &[TimeShift]) -> usize {
+    let mut shifted = 0;
+
+    for item in items.iter_mut() {
+        let Some(shift) = shifts.iter().find(|shift| shift.applies_to(item)) else {
+            continue;
+        };
+        if let Some(timestamp) = item.timestamp {
+            let corrected = timestamp + shift.offset;
+            item.timestamp = Some(corrected);
+            item.folder_path = generate_folder_path(corrected);
+            shifted += 1;
+        }
+    }
+
+    shifted
+}
+
+/// The time zone an item's clock was set to, when its device profile puts it
+/// in a different zone than the archive's `timezone`
+pub fn device_timezone(config: &FolioConfig, item: &MediaItem, volume: Option<&str>) -> Option<Tz> {
+    let archive_zone = config.timezone?;
+    let device_zone = config.profile_for(item.camera.as_ref(), volume)?.timezone?;
+    (device_zone != archive_zone).then_some(device_zone)
+}
+
+/// Convert capture times of devices set to another time zone into the archive's,
+/// updating folder paths. Returns the number of items that were converted
+pub fn apply_device_timezones(
+    items: &mut [MediaItem],
+    config: &FolioConfig,
+    volume: Option<&str>,
+) -> usize {
+    let Some(archive_zone) = config.timezone else {
+        return 0;
+    };
+    let mut converted = 0;
+
+    for item in items.iter_mut() {
+        let Some(device_zone) = device_timezone(config, item, volume) else {
+            continue;
+        };
+        if let Some(timestamp) = item.timestamp {
+            let corrected = convert_wall_clock(timestamp, device_zone, archive_zone);
+            item.timestamp = Some(corrected);
+            item.folder_path = generate_folder_path(corrected);
+            converted += 1;
+        }
+    }
+
+    converted
+}
+
+/// Capture times are wall-clock times stored as UTC; re-read one as `from`
+/// local time and give the wall-clock time in `to`
+fn convert_wall_clock(timestamp: DateTime<Utc>, from: Tz, to: Tz) -> DateTime<Utc> {
+    match from.from_local_datetime(&timestamp.naive_utc()).earliest() {
+        Some(local) => {
+            DateTime::<Utc>::from_naive_utc_and_offset(local.with_timezone(&to).naive_local(), Utc)
+        }
+        // Skipped by a daylight saving change; the clock can't have shown it
+        None => timestamp,
+    }
+}
+
+/// Selects which archived files `plan_retime` corrects
+#[derive(Debug, Clone, Default)]
+pub struct RetimeFilter {
+    /// Camera key, matched like `TimeShift::camera`
+    pub camera: Option<String>,
+    /// Batch name as it appears in the filename
+    pub batch_name: Option<String>,
+}
+
+/// A rename planned by `plan_retime`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetimeMove {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub timestamp: DateTime<Utc>,
+    /// Batch read from the path, if the template names one
+    pub batch: Option<String>,
+}
+
+/// Plan renames that shift already-archived files by `offset`
+///
+/// Files are recognised by reading their archive path with `template`. The
+/// time comes from the path when it holds one to the second, otherwise from
+/// the sidecar or the file's EXIF data. Fails without planning anything if a
+/// corrected path would overwrite an existing file.
+pub fn plan_retime(
+    archive: &Path,
+    template: &PathTemplate,
+    offset: Duration,
+    filter: &RetimeFilter,
+) -> Result<Vec<RetimeMove>> {
+    let mut moves = Vec::new();
+
+    let walker = WalkDir::new(archive)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !is_hidden(entry.path()));
+    for entry in walker {
+        let entry = entry.context("Failed to read directory entry")?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path();
+        let Some(media_type) = detect_media_type(path) else {
+            continue;
+        };
+        let Some(fields) = template.read(path.strip_prefix(archive)?) else {
+            continue;
+        };
+
+        if filter
+            .batch_name
+            .as_ref()
+            .is_some_and(|wanted| fields.batch() != Some(wanted.as_str()))
+        {
+            continue;
+        }
+        if let Some(key) = &filter.camera {
+            let camera = get_camera_info(path, &media_type)?;
+            if !camera.is_some_and(|camera| camera_matches(key, &camera)) {
+                continue;
+            }
+        }
+        let Some(timestamp) = capture_time(path, &media_type, &fields)? else {
+            continue;
+        };
+
+        let corrected = timestamp + offset;
+        let batch = fields.batch().map(str::to_string);
+        let to = archive.join(template.rerender(
+            &fields,
+            Some(corrected),
+            batch.as_deref().unwrap_or_default(),
+        ));
+        moves.push(RetimeMove {
+            from: path.to_path_buf(),
+            to,
+            timestamp: corrected,
+            batch,
+        });
+    }
+
+    // Apply in an order where no file lands on another that hasn't moved yet
+    moves.sort_by_key(|m| m.timestamp);
+    if offset > Duration::zero() {
+        moves.reverse();
+    }
+
+    let sources: HashSet<&PathBuf> = moves.iter().map(|m| &m.from).collect();
+    let mut targets = HashSet::new();
+    for m in &moves {
+        if !targets.insert(&m.to) || (m.to.exists() && !sources.contains(&m.to)) {
+            anyhow::bail!(
+                "Retiming {:?} would overwrite {:?}; nothing was changed",
+                m.from,
+                m.to
+            );
+        }
+    }
+
+    Ok(moves)
+}
+
+/// When an archived file was taken: from its path, or else the time recorded
+/// in its sidecar (which holds earlier corrections), or else its EXIF data
+fn capture_time(
+    path: &Path,
+    media_type: &MediaType,
+    fields: &PathFields,
+) -> Result<Option<DateTime<Utc>>> {
+    if let Some(timestamp) = fields.timestamp() {
+        return Ok(Some(timestamp));
+    }
+    let sidecar = sidecar_path(path);
+    if sidecar.exists() {
+        let recorded = XmpDocument::load_or_default(&sidecar)?
+            .get(XmpField::DateTimeOriginal)
+            .and_then(|value| parse_xmp_date(&value));
+        if recorded.is_some() {
+            return Ok(recorded);
+        }
+    }
+    get_capture_timestamp(path, media_type)
+}
+
+/// Carry out planned renames, moving sidecars along and recording the
+/// corrected time in each file's XMP sidecar
+pub fn apply_retime(archive: &Path, moves: &[RetimeMove]) -> Result<()> {
+    for m in moves {
+        let old_sidecar = sidecar_path(&m.from);
+        let new_sidecar = sidecar_path(&m.to);
+        // A layout without the time keeps the path; only the sidecar changes
+        if m.from != m.to {
+            move_file(&m.from, &m.to)?;
+            let relative = |path: &Path| path.strip_prefix(archive).unwrap_or(path).to_path_buf();
+            relocate_in_manifests(archive, &[(relative(&m.from), relative(&m.to))])?;
+            if old_sidecar.exists() {
+                move_file(&old_sidecar, &new_sidecar)?;
+            }
+        }
+        let mut xmp = XmpDocument::load_or_default(&new_sidecar)?;
+        xmp.set(XmpField::DateTimeOriginal, &format_xmp_date(m.timestamp));
+        xmp.save(&new_sidecar)?;
+
+        if let Some(parent) = m.from.parent() {
+            prune_empty_parents(parent, archive)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::PhotoFormat;
+    use std::fs;
+
+    fn nikon_item(timestamp: &str) -> MediaItem {
+        let timestamp = DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc);
+        MediaItem {
+            path: PathBuf::from("DSC_0001.jpg"),
+            hash: blake3::hash(b"test"),
+            size: 1000,
+            media_type: MediaType::Photo(PhotoFormat::Jpeg),
+            timestamp: Some(timestamp),
+            folder_path: generate_folder_path(timestamp),
+            camera: Some(CameraInfo {
+                make: Some("NIKON CORPORATION".to_string()),
+                model: Some("NIKON D800".to_string()),
+                serial: Some("3012345".to_string()),
+            }),
+            gps: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("+1h").unwrap(), Duration::hours(1));
+        assert_eq!(parse_offset("-30m").unwrap(), Duration::minutes(-30));
+        assert_eq!(
+            parse_offset("1h30m").unwrap(),
+            Duration::hours(1) + Duration::minutes(30)
+        );
+        assert_eq!(parse_offset("+5110d").unwrap(), Duration::days(5110));
+        assert!(parse_offset("").is_err());
+        assert!(parse_offset("+1").is_err());
+        assert!(parse_offset("+1y").is_err());
+        assert!(parse_offset("h").is_err());
+    }
+
+    #[test]
+    fn test_format_offset_round_trips() {
+        for text in ["+1h", "-30m", "+1d2h3m4s", "+0s"] {
+            assert_eq!(format_offset(parse_offset(text).unwrap()), text);
+        }
+    }
+
+    #[test]
+    fn test_parse_time_shift() {
+        let shift: TimeShift = "NIKON D800=+1h".parse().unwrap();
+        assert_eq!(shift.camera, "NIKON D800");
+        assert_eq!(shift.offset, Duration::hours(1));
+
+        assert!("NIKON D800".parse::<TimeShift>().is_err());
+        assert!("=+1h".parse::<TimeShift>().is_err());
+    }
+
+    #[test]
+    fn test_apply_time_shifts_by_model_and_serial() {
+        let mut items = vec![nikon_item("2024-11-04T14:02:15Z")];
+
+        let by_model: TimeShift = "nikon d800=+1h".parse().unwrap();
+        assert_eq!(apply_time_shifts(&mut items, &[by_model]), 1);
+        assert_eq!(
+            items[0].timestamp.unwrap().to_rfc3339(),
+            "2024-11-04T15:02:15+00:00"
+        );
+
+        let by_serial: TimeShift = "3012345=+10h".parse().unwrap();
+        apply_time_shifts(&mut items, &[by_serial]);
+        assert_eq!(items[0].folder_path, PathBuf::from("2024/11/05"));
+
+        let other: TimeShift = "iPhone 15=+1h".parse().unwrap();
+        assert_eq!(apply_time_shifts(&mut items, &[other]), 0);
+    }
+
+    #[test]
+    fn test_apply_device_timezones() {
+        let config = FolioConfig::parse(
+            r#"
+timezone = "America/Los_Angeles"
+
+[[profile]]
+name = "Dad's camera"
+model = "NIKON D800"
+timezone = "America/Chicago"
+"#,
+        )
+        .unwrap();
+        // 00:30 in Chicago is 22:30 the day before in Los Angeles
+        let mut items = vec![nikon_item("2024-11-05T00:30:00Z")];
+
+        assert_eq!(apply_device_timezones(&mut items, &config, None), 1);
+        assert_eq!(
+            items[0].timestamp.unwrap().to_rfc3339(),
+            "2024-11-04T22:30:00+00:00"
+        );
+        assert_eq!(items[0].folder_path, PathBuf::from("2024/11/04"));
+
+        // Without an archive time zone there is nothing to convert to
+        let config = FolioConfig {
+            timezone: None,
+            ..config
+        };
+        assert_eq!(apply_device_timezones(&mut items, &config, None), 0);
+    }
+
+    #[test]
+    fn test_retime_moves_files_and_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path();
+        let day = archive.join("2024/11/04");
+        fs::create_dir_all(&day).unwrap();
+        fs::write(day.join("20241104-233000-party.jpg"), b"a").unwrap();
+        fs::write(
+            day.join("20241104-233000-party.xmp"),
+            XmpDocument::default().as_str(),
+        )
+        .unwrap();
+        fs::write(day.join("20241104-120000-lunch.jpg"), b"b").unwrap();
+
+        let filter = RetimeFilter {
+            camera: None,
+            batch_name: Some("party".to_string()),
+        };
+        let moves = plan_retime(
+            archive,
+            &PathTemplate::default(),
+            Duration::hours(1),
+            &filter,
+        )
+        .unwrap();
+        assert_eq!(moves.len(), 1);
+
+        apply_retime(archive, &moves).unwrap();
+
+        let moved = archive.join("2024/11/05/20241105-003000-party.jpg");
+        assert!(moved.exists());
+        let xmp = XmpDocument::load_or_default(&sidecar_path(&moved)).unwrap();
+        assert_eq!(
+            xmp.get(XmpField::DateTimeOriginal).as_deref(),
+            Some("2024-11-05T00:30:00")
+        );
+        assert!(!day.join("20241104-233000-party.xmp").exists());
+        // Other batches are untouched and the folder is kept
+        assert!(day.join("20241104-120000-lunch.jpg").exists());
+    }
+
+    #[test]
+    fn test_retime_follows_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path();
+        // Batch folders and sequence numbers; the seq is kept as it is
+        let template: PathTemplate = "{year}/{date}_{batch}/{time}-{seq}.{ext}".parse().unwrap();
+        let folder = archive.join("2024/2024-11-04_party");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("233000-007.jpg"), b"a").unwrap();
+
+        let filter = RetimeFilter {
+            camera: None,
+            batch_name: Some("party".to_string()),
+        };
+        let moves = plan_retime(archive, &template, Duration::hours(1), &filter).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].batch.as_deref(), Some("party"));
+        apply_retime(archive, &moves).unwrap();
+
+        assert!(archive
+            .join("2024/2024-11-05_party/003000-007.jpg")
+            .exists());
+        assert!(!folder.exists());
+    }
+
+    #[test]
+    fn test_retime_reads_time_from_sidecar_when_path_has_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path();
+        let template: PathTemplate = "{year}/{batch}/{stem}.{ext}".parse().unwrap();
+        let folder = archive.join("2024/party");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("IMG_0001.jpg"), b"a").unwrap();
+        let mut xmp = XmpDocument::default();
+        xmp.set(XmpField::DateTimeOriginal, "2024-12-31T23:30:00");
+        xmp.save(&folder.join("IMG_0001.xmp")).unwrap();
+
+        let moves = plan_retime(
+            archive,
+            &template,
+            Duration::hours(1),
+            &RetimeFilter::default(),
+        )
+        .unwrap();
+        apply_retime(archive, &moves).unwrap();
+
+        let moved = archive.join("2025/party/IMG_0001.jpg");
+        assert!(moved.exists());
+        let xmp = XmpDocument::load_or_default(&sidecar_path(&moved)).unwrap();
+        assert_eq!(
+            xmp.get(XmpField::DateTimeOriginal).as_deref(),
+            Some("2025-01-01T00:30:00")
+        );
+    }
+
+    #[test]
+    fn test_retime_chain_within_batch_does_not_clobber() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path();
+        let day = archive.join("2024/11/04");
+        fs::create_dir_all(&day).unwrap();
+        fs::write(day.join("20241104-140000-trip.jpg"), b"first").unwrap();
+        fs::write(day.join("20241104-150000-trip.jpg"), b"second").unwrap();
+
+        let moves = plan_retime(
+            archive,
+            &PathTemplate::default(),
+            Duration::hours(1),
+            &RetimeFilter::default(),
+        )
+        .unwrap();
+        apply_retime(archive, &moves).unwrap();
+
+        assert_eq!(
+            fs::read(day.join("20241104-150000-trip.jpg")).unwrap(),
+            b"first"
+        );
+        assert_eq!(
+            fs::read(day.join("20241104-160000-trip.jpg")).unwrap(),
+            b"second"
+        );
+    }
+
+    #[test]
+    fn test_retime_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path();
+        let day = archive.join("2024/11/04");
+        fs::create_dir_all(&day).unwrap();
+
+        // Only the Nikon file matches; its corrected name is taken by another file
+        let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("test-data/fixtures/sample-with-exif.jpg");
+        fs::copy(&fixture, day.join("20241104-140000-trip.jpg")).unwrap();
+        fs::write(day.join("20241104-150000-trip.jpg"), b"phone photo").unwrap();
+
+        let filter = RetimeFilter {
+            camera: Some("Nikon D800".to_string()),
+            batch_name: None,
+        };
+        let result = plan_retime(
+            archive,
+            &PathTemplate::default(),
+            Duration::hours(1),
+            &filter,
+        );
+
+        assert!(result.is_err());
+        assert!(day.join("20241104-140000-trip.jpg").exists());
+        assert_eq!(
+            fs::read(day.join("20241104-150000-trip.jpg")).unwrap(),
+            b"phone photo"
+        );
+    }
+}
@@ -0,0 +1,440 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// XMP properties folio reads and writes in sidecar files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XmpField {
+    /// exif:DateTimeOriginal - corrected capture time
+    DateTimeOriginal,
+    /// dc:title - human-readable batch title (a language alternative)
+    Title,
+    /// photoshop:City - city the photo was taken in
+    City,
+    /// photoshop:State - region (state, province) the photo was taken in
+    State,
+    /// photoshop:Country - country the photo was taken in
+    Country,
+}
+
+impl XmpField {
+    fn prefix(&self) -> &'static str {
+        match self {
+            XmpField::DateTimeOriginal => "exif",
+            XmpField::Title => "dc",
+            XmpField::City | XmpField::State | XmpField::Country => "photoshop",
+        }
+    }
+
+    fn namespace(&self) -> &'static str {
+        match self {
+            XmpField::DateTimeOriginal => "http://ns.adobe.com/exif/1.0/",
+            XmpField::Title => "http://purl.org/dc/elements/1.1/",
+            XmpField::City | XmpField::State | XmpField::Country => {
+                "http://ns.adobe.com/photoshop/1.0/"
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            XmpField::DateTimeOriginal => "DateTimeOriginal",
+            XmpField::Title => "title",
+            XmpField::City => "City",
+            XmpField::State => "State",
+            XmpField::Country => "Country",
+        }
+    }
+
+    /// Language alternatives are stored as an rdf:Alt with an x-default entry
+    fn is_lang_alt(&self) -> bool {
+        matches!(self, XmpField::Title)
+    }
+
+    fn qualified_name(&self) -> String {
+        format!("{}:{}", self.prefix(), self.name())
+    }
+}
+
+/// Format a timestamp as an XMP date (e.g. 2024-11-04T14:02:15)
+pub fn format_xmp_date(timestamp: DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+/// Read an XMP date written by `format_xmp_date`; a time zone or fraction
+/// other tools append is ignored
+pub fn parse_xmp_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.get(..19)?;
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .ok()
+        .map(|naive| naive.and_utc())
+}
+
+/// Sidecar path for a media file, following the ADR layout
+/// Example: IMG_1234.jpg -> IMG_1234.xmp
+pub fn sidecar_path(media_path: &Path) -> PathBuf {
+    media_path.with_extension("xmp")
+}
+
+/// An XMP sidecar document
+///
+/// Edits are made in place on the document text so that properties written by
+/// other tools (Lightroom, digiKam, ...) are preserved untouched.
+#[derive(Debug, Clone)]
+pub struct XmpDocument {
+    xml: String,
+}
+
+impl Default for XmpDocument {
+    fn default() -> Self {
+        XmpDocument {
+            xml: concat!(
+                "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n",
+                "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+                " <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+                "  <rdf:Description rdf:about=\"\">\n",
+                "  </rdf:Description>\n",
+                " </rdf:RDF>\n",
+                "</x:xmpmeta>\n",
+                "<?xpacket end=\"w\"?>\n",
+            )
+            .to_string(),
+        }
+    }
+}
+
+impl XmpDocument {
+    /// Parse a sidecar from its text
+    pub fn parse(xml: &str) -> Result<Self> {
+        if !xml.contains("<rdf:Description") {
+            anyhow::bail!("XMP document has no rdf:Description element");
+        }
+        Ok(XmpDocument {
+            xml: xml.to_string(),
+        })
+    }
+
+    /// Load a sidecar file, or start an empty document if it doesn't exist
+    pub fn load_or_default(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(XmpDocument::default());
+        }
+        let xml = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read XMP sidecar {:?}", path))?;
+        XmpDocument::parse(&xml).with_context(|| format!("Invalid XMP sidecar {:?}", path))
+    }
+
+    /// Write the sidecar to disk
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, &self.xml)
+            .with_context(|| format!("Failed to write XMP sidecar {:?}", path))
+    }
+
+    /// The document text
+    pub fn as_str(&self) -> &str {
+        &self.xml
+    }
+
+    /// Read a property, written either as an attribute or as an element
+    /// For language alternatives the first entry (normally x-default) is returned
+    pub fn get(&self, field: XmpField) -> Option<String> {
+        let qualified = field.qualified_name();
+
+        if field.is_lang_alt() {
+            let (start, end) = self.lang_alt_text_range(&qualified)?;
+            return Some(unescape(&self.xml[start..end]));
+        }
+
+        if let Some((start, end)) = self.attribute_value_range(&qualified) {
+            return Some(unescape(&self.xml[start..end]));
+        }
+        if let Some((start, end)) = self.element_text_range(&qualified) {
+            return Some(unescape(self.xml[start..end].trim()));
+        }
+        None
+    }
+
+    /// Set a property, replacing an existing value wherever it is written
+    pub fn set(&mut self, field: XmpField, value: &str) {
+        let qualified = field.qualified_name();
+        let escaped = escape(value);
+
+        if field.is_lang_alt() {
+            self.set_lang_alt(field, &qualified, &escaped);
+            return;
+        }
+
+        if let Some((start, end)) = self.attribute_value_range(&qualified) {
+            self.xml.replace_range(start..end, &escaped);
+            return;
+        }
+        if let Some((start, end)) = self.element_text_range(&qualified) {
+            self.xml.replace_range(start..end, &escaped);
+            return;
+        }
+
+        // New property: add it as an attribute of the first rdf:Description
+        self.declare_namespace(field);
+        let insert_at = self.description_tag_end();
+        self.xml
+            .insert_str(insert_at, &format!("\n   {}=\"{}\"", qualified, escaped));
+    }
+
+    fn set_lang_alt(&mut self, field: XmpField, qualified: &str, escaped: &str) {
+        if let Some((start, end)) = self.lang_alt_text_range(qualified) {
+            self.xml.replace_range(start..end, escaped);
+            return;
+        }
+
+        // New property: add it as a child element of the first rdf:Description
+        self.declare_namespace(field);
+        let tag_end = self.description_tag_end();
+        if self.xml[tag_end..].starts_with("/>") {
+            self.xml
+                .replace_range(tag_end..tag_end + 2, ">\n  </rdf:Description>");
+        }
+        let content_start = tag_end + 1;
+        self.xml.insert_str(
+            content_start,
+            &format!(
+                "\n   <{q}>\n    <rdf:Alt>\n     <rdf:li xml:lang=\"x-default\">{v}</rdf:li>\n    </rdf:Alt>\n   </{q}>",
+                q = qualified,
+                v = escaped
+            ),
+        );
+    }
+
+    /// Range of the text of the first rdf:li inside `<name>...</name>`
+    fn lang_alt_text_range(&self, qualified: &str) -> Option<(usize, usize)> {
+        let (start, end) = self.element_text_range(qualified)?;
+        let li = start + self.xml[start..end].find("<rdf:li")?;
+        let text_start = li + self.xml[li..end].find('>')? + 1;
+        let text_end = text_start + self.xml[text_start..end].find("</rdf:li>")?;
+        Some((text_start, text_end))
+    }
+
+    /// Byte offset just before the closing `>` (or `/>`) of the first rdf:Description tag
+    fn description_tag_end(&self) -> usize {
+        let start = self.xml.find("<rdf:Description").unwrap();
+        let close = start + self.xml[start..].find('>').unwrap();
+        if self.xml[..close].ends_with('/') {
+            close - 1
+        } else {
+            close
+        }
+    }
+
+    fn declare_namespace(&mut self, field: XmpField) {
+        let declaration = format!("xmlns:{}=", field.prefix());
+        if self.xml.contains(&declaration) {
+            return;
+        }
+        let insert_at = self.description_tag_end();
+        self.xml.insert_str(
+            insert_at,
+            &format!("\n   {}\"{}\"", declaration, field.namespace()),
+        );
+    }
+
+    /// Range of the value of `name="..."` inside any tag
+    fn attribute_value_range(&self, qualified: &str) -> Option<(usize, usize)> {
+        let mut search_from = 0;
+        while let Some(offset) = self.xml[search_from..].find(qualified) {
+            let at = search_from + offset;
+            search_from = at + qualified.len();
+
+            // Must be a whole attribute name preceded by whitespace
+            let preceded_by_space = self.xml[..at]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_whitespace);
+            let rest = self.xml[search_from..].trim_start();
+            if !preceded_by_space || !rest.starts_with('=') {
+                continue;
+            }
+            let after_eq = rest[1..].trim_start();
+            let quote = after_eq.chars().next()?;
+            if quote != '"' && quote != '\'' {
+                continue;
+            }
+            let value_start = self.xml.len() - after_eq.len() + 1;
+            let value_end = value_start + self.xml[value_start..].find(quote)?;
+            return Some((value_start, value_end));
+        }
+        None
+    }
+
+    /// Range of the text content of `<name>...</name>`
+    fn element_text_range(&self, qualified: &str) -> Option<(usize, usize)> {
+        let open = format!("<{}>", qualified);
+        let close = format!("</{}>", qualified);
+        let start = self.xml.find(&open)? + open.len();
+        let end = start + self.xml[start..].find(&close)?;
+        Some((start, end))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_xmp_date() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 11, 4, 14, 2, 15).unwrap();
+        assert_eq!(parse_xmp_date(&format_xmp_date(timestamp)), Some(timestamp));
+        assert_eq!(
+            parse_xmp_date("2024-11-04T14:02:15.25-06:00"),
+            Some(timestamp)
+        );
+        assert_eq!(parse_xmp_date("2024-11-04"), None);
+    }
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(
+            sidecar_path(Path::new("2024/11/04/IMG_1234.jpg")),
+            PathBuf::from("2024/11/04/IMG_1234.xmp")
+        );
+    }
+
+    #[test]
+    fn test_set_and_get_on_new_document() {
+        let mut doc = XmpDocument::default();
+        assert_eq!(doc.get(XmpField::DateTimeOriginal), None);
+
+        doc.set(XmpField::DateTimeOriginal, "2024-11-04T15:02:15");
+
+        assert_eq!(
+            doc.get(XmpField::DateTimeOriginal).as_deref(),
+            Some("2024-11-04T15:02:15")
+        );
+        assert!(doc
+            .as_str()
+            .contains("xmlns:exif=\"http://ns.adobe.com/exif/1.0/\""));
+
+        // Round trip through text
+        let reparsed = XmpDocument::parse(doc.as_str()).unwrap();
+        assert_eq!(
+            reparsed.get(XmpField::DateTimeOriginal).as_deref(),
+            Some("2024-11-04T15:02:15")
+        );
+    }
+
+    #[test]
+    fn test_set_preserves_foreign_properties() {
+        // Sidecar as written by another tool, with the date as an element
+        let xml = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:exif="http://ns.adobe.com/exif/1.0/"
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmp:Rating="5">
+   <exif:DateTimeOriginal>2024-11-04T14:02:15</exif:DateTimeOriginal>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+        let mut doc = XmpDocument::parse(xml).unwrap();
+
+        doc.set(XmpField::DateTimeOriginal, "2024-11-04T15:02:15");
+
+        assert_eq!(
+            doc.get(XmpField::DateTimeOriginal).as_deref(),
+            Some("2024-11-04T15:02:15")
+        );
+        assert!(doc.as_str().contains("xmp:Rating=\"5\""));
+        assert_eq!(doc.as_str().matches("xmlns:exif=").count(), 1);
+    }
+
+    #[test]
+    fn test_set_location_fields() {
+        let mut doc = XmpDocument::default();
+        doc.set(XmpField::City, "South Lake Tahoe");
+        doc.set(XmpField::Country, "United States");
+
+        let reparsed = XmpDocument::parse(doc.as_str()).unwrap();
+        assert_eq!(
+            reparsed.get(XmpField::City).as_deref(),
+            Some("South Lake Tahoe")
+        );
+        assert_eq!(
+            reparsed.get(XmpField::Country).as_deref(),
+            Some("United States")
+        );
+        assert_eq!(reparsed.get(XmpField::State), None);
+        assert_eq!(
+            reparsed
+                .as_str()
+                .matches("xmlns:photoshop=\"http://ns.adobe.com/photoshop/1.0/\"")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_set_and_get_title() {
+        let mut doc = XmpDocument::default();
+        doc.set(XmpField::DateTimeOriginal, "2024-11-28T15:00:00");
+        doc.set(XmpField::Title, "Thanksgiving at Grandma's & Grandpa's");
+
+        assert_eq!(
+            doc.get(XmpField::Title).as_deref(),
+            Some("Thanksgiving at Grandma's & Grandpa's")
+        );
+        assert!(doc.as_str().contains("<rdf:li xml:lang=\"x-default\">"));
+        assert!(doc
+            .as_str()
+            .contains("xmlns:dc=\"http://purl.org/dc/elements/1.1/\""));
+
+        doc.set(XmpField::Title, "Thanksgiving");
+        let reparsed = XmpDocument::parse(doc.as_str()).unwrap();
+        assert_eq!(
+            reparsed.get(XmpField::Title).as_deref(),
+            Some("Thanksgiving")
+        );
+        assert_eq!(
+            reparsed.get(XmpField::DateTimeOriginal).as_deref(),
+            Some("2024-11-28T15:00:00")
+        );
+        assert_eq!(reparsed.as_str().matches("<dc:title>").count(), 1);
+    }
+
+    #[test]
+    fn test_set_title_in_self_closing_description() {
+        let xml = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="5"/>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+        let mut doc = XmpDocument::parse(xml).unwrap();
+
+        doc.set(XmpField::Title, "Lake Tahoe");
+
+        assert_eq!(doc.get(XmpField::Title).as_deref(), Some("Lake Tahoe"));
+        assert!(doc.as_str().contains("xmp:Rating=\"5\""));
+        assert!(!doc.as_str().contains("/>\n </rdf:RDF>"));
+        assert!(doc.as_str().contains("</rdf:Description>"));
+    }
+
+    #[test]
+    fn test_parse_rejects_non_xmp() {
+        assert!(XmpDocument::parse("not xmp").is_err());
+    }
+}
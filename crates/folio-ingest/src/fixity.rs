@@ -0,0 +1,368 @@
+use crate::journal::Journal;
+use anyhow::{Context, Result};
+use blake3::Hash as Blake3Hash;
+use chrono::{DateTime, Utc};
+use folio_core::{detect_media_type, hash_file, manifest_hashes};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use walkdir::WalkDir;
+
+/// When each archived file last passed verification, relative to the archive
+pub const FIXITY_FILE: &str = ".folio/fixity.json";
+
+/// The content hash recorded for each archived file, relative to the archive root
+///
+/// Taken from the folders' manifests and the ingest journals (following later
+/// renames; files of undone ingests are left out). Where both list a file, the
+/// journal wins. Files that journals record with different hashes are left out.
+pub fn recorded_hashes(archive_root: &Path) -> Result<BTreeMap<PathBuf, Blake3Hash>> {
+    Ok(read_records(archive_root)?.0)
+}
+
+/// The recorded hashes, and the files two ingests still in effect record with
+/// different hashes: renames and undos are already accounted for, so either
+/// record could be the wrong one and neither is trusted
+fn read_records(archive_root: &Path) -> Result<(BTreeMap<PathBuf, Blake3Hash>, BTreeSet<PathBuf>)> {
+    let mut journaled: BTreeMap<PathBuf, Blake3Hash> = BTreeMap::new();
+    let mut conflicting = BTreeSet::new();
+    for journal in Journal::list(archive_root)? {
+        if journal.is_undone() {
+            continue;
+        }
+        for (index, file) in journal.plan().files.iter().enumerate() {
+            if !journal.is_done(index) || journal.is_reverted(index) {
+                continue;
+            }
+            let hash = file.blake3()?;
+            if journaled
+                .insert(file.archived.clone(), hash)
+                .is_some_and(|h| h != hash)
+            {
+                conflicting.insert(file.archived.clone());
+            }
+        }
+    }
+
+    let mut hashes = manifest_hashes(archive_root)?;
+    hashes.extend(journaled);
+    for path in &conflicting {
+        hashes.remove(path);
+    }
+    Ok((hashes, conflicting))
+}
+
+/// How many recorded files one verification run hashes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sample {
+    All,
+    /// A share of the files, e.g. `5%`
+    Percent(f64),
+    /// A number of files, e.g. `500`
+    Count(usize),
+}
+
+impl Sample {
+    /// Files to check out of `total`; a share never rounds down to nothing
+    pub fn size(self, total: usize) -> usize {
+        match self {
+            Sample::All => total,
+            Sample::Percent(percent) => {
+                ((total as f64 * percent / 100.0).ceil() as usize).clamp(total.min(1), total)
+            }
+            Sample::Count(count) => count.min(total),
+        }
+    }
+}
+
+impl FromStr for Sample {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(percent) = s.strip_suffix('%') {
+            let percent: f64 = percent
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid sample '{}'", s))?;
+            if !(percent > 0.0 && percent <= 100.0) {
+                anyhow::bail!("Sample must be between 0% and 100%, got '{}'", s);
+            }
+            return Ok(Sample::Percent(percent));
+        }
+        let count: usize = s
+            .parse()
+            .with_context(|| format!("Invalid sample '{}', expected e.g. 5% or 500", s))?;
+        Ok(Sample::Count(count))
+    }
+}
+
+/// When archived files last passed verification
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FixityLog {
+    verified: BTreeMap<PathBuf, DateTime<Utc>>,
+}
+
+impl FixityLog {
+    pub fn load(archive_root: &Path) -> Result<Self> {
+        let path = archive_root.join(FIXITY_FILE);
+        if !path.exists() {
+            return Ok(FixityLog::default());
+        }
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid fixity log {:?}", path))
+    }
+
+    /// Write the log, replacing the previous one in a single rename
+    pub fn save(&self, archive_root: &Path) -> Result<()> {
+        let path = archive_root.join(FIXITY_FILE);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        }
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write {:?}", partial))?;
+        fs::rename(&partial, &path).with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    pub fn last_verified(&self, relative: &Path) -> Option<DateTime<Utc>> {
+        self.verified.get(relative).copied()
+    }
+
+    pub fn record(&mut self, relative: PathBuf, at: DateTime<Utc>) {
+        self.verified.insert(relative, at);
+    }
+}
+
+/// Outcome of a verification run; paths are relative to the archive root
+#[derive(Debug, Default)]
+pub struct FixityReport {
+    /// Files whose hash was recomputed
+    pub checked: usize,
+    /// Recorded files with a hash still present in the archive
+    pub recorded: usize,
+    /// Content differs from the recorded hash (or can't be read)
+    pub corrupted: Vec<PathBuf>,
+    /// Recorded with different hashes by two ingests, so can't be checked
+    pub conflicting: Vec<PathBuf>,
+    /// Recorded, but no longer in the archive
+    pub missing: Vec<PathBuf>,
+    /// Media files in the archive without a recorded hash
+    pub unexpected: Vec<PathBuf>,
+}
+
+impl FixityReport {
+    pub fn is_clean(&self) -> bool {
+        self.corrupted.is_empty()
+            && self.conflicting.is_empty()
+            && self.missing.is_empty()
+            && self.unexpected.is_empty()
+    }
+}
+
+/// Check archived files against the hashes recorded at ingest
+///
+/// Missing and unexpected files, and files with conflicting records, are
+/// always looked for; hashing covers `sample`
+/// of the files, those never verified or verified longest ago first. Files that
+/// pass are stamped in the fixity log, so repeated sampled runs cycle through
+/// the whole archive.
+pub fn verify_archive(archive_root: &Path, sample: Sample) -> Result<FixityReport> {
+    let (recorded, conflicting) = read_records(archive_root)?;
+    let mut log = FixityLog::load(archive_root)?;
+    let mut report = FixityReport {
+        conflicting: conflicting.iter().cloned().collect(),
+        ..FixityReport::default()
+    };
+
+    let mut present = Vec::new();
+    for (relative, hash) in &recorded {
+        if archive_root.join(relative).is_file() {
+            present.push((relative, hash));
+        } else {
+            report.missing.push(relative.clone());
+        }
+    }
+    report.recorded = present.len();
+
+    let entries = WalkDir::new(archive_root)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || entry.file_name() != ".folio");
+    for entry in entries {
+        let entry = entry.context("Failed to read directory entry")?;
+        if !entry.file_type().is_file() || detect_media_type(entry.path()).is_none() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(archive_root)?;
+        if !recorded.contains_key(relative) && !conflicting.contains(relative) {
+            report.unexpected.push(relative.to_path_buf());
+        }
+    }
+
+    // Never verified sorts first (None < Some)
+    present.sort_by_key(|(relative, _)| (log.last_verified(relative), relative.to_path_buf()));
+    let count = sample.size(present.len());
+    for (relative, expected) in present.into_iter().take(count) {
+        report.checked += 1;
+        match hash_file(&archive_root.join(relative)) {
+            Ok(actual) if actual == *expected => log.record(relative.clone(), Utc::now()),
+            Ok(_) => report.corrupted.push(relative.clone()),
+            Err(e) => {
+                tracing::warn!("Failed to read {:?}: {:#}", relative, e);
+                report.corrupted.push(relative.clone());
+            }
+        }
+    }
+
+    if report.checked > 0 {
+        log.save(archive_root)?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::{IngestPlan, PlannedFile};
+    use crate::transfer::LinkMode;
+    use tempfile::TempDir;
+
+    /// An archive with three ingested photos
+    fn archive() -> TempDir {
+        let archive = TempDir::new().unwrap();
+        let mut files = Vec::new();
+        for name in ["a.jpg", "b.jpg", "c.jpg"] {
+            let path = archive.path().join("2024").join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, name).unwrap();
+            files.push(PlannedFile {
+                source: PathBuf::from("/card").join(name),
+                hash: blake3::hash(name.as_bytes()).to_hex().to_string(),
+                archived: Path::new("2024").join(name),
+                batch: "trip".to_string(),
+                batch_index: 0,
+                duplicate: false,
+                corrected_time: None,
+                title: None,
+                location: None,
+            });
+        }
+        let count = files.len();
+        let mut journal = Journal::create(
+            archive.path(),
+            IngestPlan {
+                source: PathBuf::from("/card"),
+                extra_sources: Vec::new(),
+                link: LinkMode::Copy,
+                move_sources: false,
+                files,
+            },
+        )
+        .unwrap();
+        for index in 0..count {
+            journal.record_done(index, None).unwrap();
+        }
+        journal.complete().unwrap();
+        archive
+    }
+
+    #[test]
+    fn test_verify_reports_corrupted_missing_and_unexpected() {
+        let archive = archive();
+        let report = verify_archive(archive.path(), Sample::All).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.checked, 3);
+
+        fs::write(archive.path().join("2024/a.jpg"), "bit rot").unwrap();
+        fs::remove_file(archive.path().join("2024/b.jpg")).unwrap();
+        fs::write(archive.path().join("2024/stray.jpg"), "?").unwrap();
+        fs::write(archive.path().join("2024/notes.txt"), "not media").unwrap();
+
+        let report = verify_archive(archive.path(), Sample::All).unwrap();
+        assert!(!report.is_clean());
+        assert_eq!(report.corrupted, vec![PathBuf::from("2024/a.jpg")]);
+        assert_eq!(report.missing, vec![PathBuf::from("2024/b.jpg")]);
+        assert_eq!(report.unexpected, vec![PathBuf::from("2024/stray.jpg")]);
+    }
+
+    #[test]
+    fn test_conflicting_records_are_reported_not_trusted() {
+        let archive = archive();
+        // A later ingest claims 2024/a.jpg holds other content
+        let mut journal = Journal::create(
+            archive.path(),
+            IngestPlan {
+                source: PathBuf::from("/other-card"),
+                extra_sources: Vec::new(),
+                link: LinkMode::Copy,
+                move_sources: false,
+                files: vec![PlannedFile {
+                    source: PathBuf::from("/other-card/a.jpg"),
+                    hash: blake3::hash(b"other").to_hex().to_string(),
+                    archived: PathBuf::from("2024/a.jpg"),
+                    batch: "trip".to_string(),
+                    batch_index: 0,
+                    duplicate: false,
+                    corrected_time: None,
+                    title: None,
+                    location: None,
+                }],
+            },
+        )
+        .unwrap();
+        journal.record_done(0, None).unwrap();
+        journal.complete().unwrap();
+
+        assert!(!recorded_hashes(archive.path())
+            .unwrap()
+            .contains_key(Path::new("2024/a.jpg")));
+        let report = verify_archive(archive.path(), Sample::All).unwrap();
+        assert_eq!(report.conflicting, vec![PathBuf::from("2024/a.jpg")]);
+        assert!(report.corrupted.is_empty());
+        assert!(report.unexpected.is_empty());
+        assert!(!report.is_clean());
+
+        // Once the later ingest is undone, the first record stands
+        journal.record_undone().unwrap();
+        assert!(verify_archive(archive.path(), Sample::All)
+            .unwrap()
+            .is_clean());
+    }
+
+    #[test]
+    fn test_sampled_runs_check_oldest_verified_first() {
+        let archive = archive();
+
+        // One file per run, in turn, until each was verified once
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            let report = verify_archive(archive.path(), Sample::Count(1)).unwrap();
+            assert_eq!(report.checked, 1);
+            let log = FixityLog::load(archive.path()).unwrap();
+            let newest = ["a.jpg", "b.jpg", "c.jpg"]
+                .iter()
+                .map(|name| Path::new("2024").join(name))
+                .max_by_key(|path| log.last_verified(path))
+                .unwrap();
+            assert!(!seen.contains(&newest));
+            seen.push(newest);
+        }
+    }
+
+    #[test]
+    fn test_sample_parsing_and_size() {
+        assert_eq!("5%".parse::<Sample>().unwrap(), Sample::Percent(5.0));
+        assert_eq!("200".parse::<Sample>().unwrap(), Sample::Count(200));
+        assert!("0%".parse::<Sample>().is_err());
+        assert!("lots".parse::<Sample>().is_err());
+
+        assert_eq!(Sample::Percent(5.0).size(1000), 50);
+        assert_eq!(Sample::Percent(5.0).size(10), 1);
+        assert_eq!(Sample::Percent(5.0).size(0), 0);
+        assert_eq!(Sample::Count(200).size(10), 10);
+        assert_eq!(Sample::All.size(7), 7);
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Import histories inside an archive, one JSON file per device
+pub const IMPORTS_DIR: &str = ".folio/imports";
+
+/// What was known about a source file when it was imported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportedFile {
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+    /// BLAKE3 hash, hex encoded
+    pub hash: String,
+}
+
+/// The files imported so far from one device (a card, phone or profile)
+///
+/// Files are keyed by their path relative to the source folder. A file with the
+/// same path, size and modification time as a recorded one is taken to be the
+/// recorded file, so re-plugging a card that was never wiped only reads what is new.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportHistory {
+    pub device: String,
+    /// When the last ingest from the device finished
+    pub last_import: Option<DateTime<Utc>>,
+    files: BTreeMap<PathBuf, ImportedFile>,
+}
+
+impl ImportHistory {
+    /// Load the history of `device`; empty if nothing was imported from it yet
+    pub fn load(archive_root: &Path, device: &str) -> Result<Self> {
+        let path = history_path(archive_root, device);
+        if !path.exists() {
+            return Ok(ImportHistory {
+                device: device.to_string(),
+                ..ImportHistory::default()
+            });
+        }
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid import history {:?}", path))
+    }
+
+    /// Write the history, replacing the previous one in a single rename
+    pub fn save(&self, archive_root: &Path) -> Result<()> {
+        let path = history_path(archive_root, &self.device);
+        let dir = archive_root.join(IMPORTS_DIR);
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write {:?}", partial))?;
+        fs::rename(&partial, &path).with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    /// Whether the file at `relative` was imported before and hasn't changed since
+    pub fn is_known(&self, relative: &Path, size: u64, modified: DateTime<Utc>) -> bool {
+        self.files
+            .get(relative)
+            .is_some_and(|file| file.size == size && file.modified == modified)
+    }
+
+    pub fn record(&mut self, relative: PathBuf, file: ImportedFile) {
+        self.files.insert(relative, file);
+    }
+
+    /// Drop the files with any of `hashes` (hex encoded); whether any were recorded
+    pub fn forget(&mut self, hashes: &HashSet<String>) -> bool {
+        let before = self.files.len();
+        self.files.retain(|_, file| !hashes.contains(&file.hash));
+        self.files.len() != before
+    }
+
+    /// Number of files recorded
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Drop the files with any of `hashes` from every device's history, so the next
+/// ingest reads them again
+pub fn forget_imports(archive_root: &Path, hashes: &HashSet<String>) -> Result<()> {
+    let dir = archive_root.join(IMPORTS_DIR);
+    if !dir.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read {:?}", dir))? {
+        let path = entry?.path();
+        if !matches!(path.extension(), Some(ext) if ext == "json") {
+            continue;
+        }
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let mut history: ImportHistory = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid import history {:?}", path))?;
+        if history.forget(hashes) {
+            history.save(archive_root)?;
+        }
+    }
+    Ok(())
+}
+
+/// `<archive>/.folio/imports/<slug>-<hash>.json`; the hash keeps devices whose
+/// names slugify alike apart
+fn history_path(archive_root: &Path, device: &str) -> PathBuf {
+    let slug: String = device
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let slug = slug
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let hash = blake3::hash(device.as_bytes()).to_hex();
+    archive_root
+        .join(IMPORTS_DIR)
+        .join(format!("{}-{}.json", slug, &hash[..8]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_history_round_trip_and_lookup() {
+        let archive = TempDir::new().unwrap();
+        let modified = Utc.with_ymd_and_hms(2024, 11, 4, 14, 2, 15).unwrap();
+
+        let mut history = ImportHistory::load(archive.path(), "Dad's camera").unwrap();
+        assert!(history.is_empty());
+        history.record(
+            PathBuf::from("100NIKON/DSC_0001.JPG"),
+            ImportedFile {
+                size: 821,
+                modified,
+                hash: "ab".repeat(32),
+            },
+        );
+        history.last_import = Some(modified);
+        history.save(archive.path()).unwrap();
+
+        let loaded = ImportHistory::load(archive.path(), "Dad's camera").unwrap();
+        assert_eq!(loaded, history);
+        let known = Path::new("100NIKON/DSC_0001.JPG");
+        assert!(loaded.is_known(known, 821, modified));
+        assert!(!loaded.is_known(known, 822, modified));
+        assert!(!loaded.is_known(known, 821, modified + chrono::Duration::seconds(1)));
+        assert!(!loaded.is_known(Path::new("100NIKON/DSC_0002.JPG"), 821, modified));
+
+        // Devices are kept apart
+        assert!(ImportHistory::load(archive.path(), "dad-s-camera")
+            .unwrap()
+            .is_empty());
+        assert!(archive
+            .path()
+            .join(IMPORTS_DIR)
+            .read_dir()
+            .unwrap()
+            .all(|entry| entry
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with("dad-s-camera-")));
+    }
+
+    #[test]
+    fn test_forget_imports_drops_files_by_hash() {
+        let archive = TempDir::new().unwrap();
+        let modified = Utc.with_ymd_and_hms(2024, 11, 4, 14, 2, 15).unwrap();
+        let mut history = ImportHistory::load(archive.path(), "card").unwrap();
+        for (name, hash) in [("a.jpg", "aa"), ("b.jpg", "bb")] {
+            history.record(
+                PathBuf::from(name),
+                ImportedFile {
+                    size: 1,
+                    modified,
+                    hash: hash.repeat(32),
+                },
+            );
+        }
+        history.save(archive.path()).unwrap();
+
+        forget_imports(archive.path(), &HashSet::from(["aa".repeat(32)])).unwrap();
+        let loaded = ImportHistory::load(archive.path(), "card").unwrap();
+        assert!(!loaded.is_known(Path::new("a.jpg"), 1, modified));
+        assert!(loaded.is_known(Path::new("b.jpg"), 1, modified));
+    }
+}
@@ -0,0 +1,831 @@
+use crate::transfer::{LinkMode, TransferMethod};
+use anyhow::{Context, Result};
+use blake3::Hash as Blake3Hash;
+use chrono::{DateTime, Utc};
+use folio_core::Location;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Journal directory inside an archive
+pub const JOURNAL_DIR: &str = ".folio/journal";
+
+/// One file of an ingest plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedFile {
+    /// Source file being ingested
+    pub source: PathBuf,
+    /// BLAKE3 hash of the source, hex encoded
+    pub hash: String,
+    /// Destination, relative to the archive root
+    pub archived: PathBuf,
+    /// Batch name chosen for the file
+    pub batch: String,
+    /// Which of the plan's batches the file belongs to; files of one batch
+    /// can have different names when device profiles prefix them
+    #[serde(default)]
+    pub batch_index: usize,
+    /// The file is already in the archive at `archived`, nothing to copy
+    pub duplicate: bool,
+    /// Clock-corrected capture time to record in the XMP sidecar
+    pub corrected_time: Option<DateTime<Utc>>,
+    /// Human-readable batch title to record in the XMP sidecar
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Where the file was taken, to record in the XMP sidecar
+    #[serde(default)]
+    pub location: Option<Location>,
+}
+
+impl PlannedFile {
+    /// Parsed BLAKE3 hash
+    pub fn blake3(&self) -> Result<Blake3Hash> {
+        Blake3Hash::from_hex(&self.hash)
+            .with_context(|| format!("Invalid hash in journal for {:?}", self.source))
+    }
+
+    /// The ingest writes an XMP sidecar for this file
+    pub fn writes_sidecar(&self) -> bool {
+        self.corrected_time.is_some() || self.title.is_some() || self.location.is_some()
+    }
+}
+
+/// Everything decided before the first file is copied
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestPlan {
+    /// Source directory (canonical path)
+    pub source: PathBuf,
+    /// Further source directories of a multi-source ingest (canonical paths)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_sources: Vec<PathBuf>,
+    pub link: LinkMode,
+    pub move_sources: bool,
+    /// Files in batch order
+    pub files: Vec<PlannedFile>,
+}
+
+impl IngestPlan {
+    /// Every source directory of the ingest, in the order given
+    pub fn sources(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(self.source.as_path())
+            .chain(self.extra_sources.iter().map(PathBuf::as_path))
+    }
+}
+
+/// A new location (and batch name) for an archived file, e.g. after a batch rename
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relocation {
+    /// Index of the file in the plan
+    pub index: usize,
+    /// New destination, relative to the archive root
+    pub archived: PathBuf,
+    pub batch: String,
+    /// New batch title, if it changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// BLAKE3 hash (hex) of the ingest's sidecar after it was rewritten
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sidecar: Option<String>,
+}
+
+/// A new batch title for an archived file, e.g. after a batch rename
+#[derive(Debug, Clone, PartialEq)]
+pub struct Retitle {
+    /// Path of the file, relative to the archive root
+    pub archived: PathBuf,
+    pub title: String,
+    /// Hashes of the file's sidecar before and after the title was rewritten there
+    pub sidecar: Option<(Blake3Hash, Blake3Hash)>,
+}
+
+/// Journal line; the first line is always the plan
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum JournalEvent {
+    Planned {
+        id: String,
+        started: DateTime<Utc>,
+        plan: IngestPlan,
+    },
+    /// Placing the file began; whatever is at its destination is this ingest's
+    Started {
+        index: usize,
+    },
+    /// The ingest wrote the file's XMP sidecar, with this BLAKE3 hash (hex)
+    SidecarWritten {
+        index: usize,
+        hash: String,
+    },
+    Done {
+        index: usize,
+        method: Option<TransferMethod>,
+    },
+    SourceRemoved {
+        index: usize,
+    },
+    Completed {
+        at: DateTime<Utc>,
+    },
+    /// `folio undo` took the file back out of the archive
+    Reverted {
+        index: usize,
+    },
+    Undone {
+        at: DateTime<Utc>,
+    },
+    Relocated {
+        moves: Vec<Relocation>,
+    },
+}
+
+/// Append-only record of one ingest
+///
+/// Written to `<archive>/.folio/journal/<id>.jsonl` as JSON lines: the plan
+/// (batch names included), then one line per completed file. An interrupted
+/// ingest is resumed by replaying it; once completed the journal is kept as the
+/// ingest's permanent record.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: Option<File>,
+    id: String,
+    started: DateTime<Utc>,
+    plan: IngestPlan,
+    placing: HashSet<usize>,
+    sidecars: HashMap<usize, String>,
+    done: HashMap<usize, Option<TransferMethod>>,
+    removed: HashSet<usize>,
+    reverted: HashSet<usize>,
+    completed: Option<DateTime<Utc>>,
+    undone: Option<DateTime<Utc>>,
+}
+
+impl Journal {
+    /// Start the journal of a new ingest
+    pub fn create(archive_root: &Path, plan: IngestPlan) -> Result<Self> {
+        let dir = archive_root.join(JOURNAL_DIR);
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+
+        // Ids are timestamps; ingests started within the same second get a suffix
+        let started = Utc::now();
+        let stamp = started.format("%Y%m%d-%H%M%S").to_string();
+        let mut suffix = 1;
+        let (id, path, partial) = loop {
+            let id = match suffix {
+                1 => stamp.clone(),
+                n => format!("{}-{}", stamp, n),
+            };
+            suffix += 1;
+            let path = dir.join(format!("{}.jsonl", id));
+            if path.exists() {
+                continue;
+            }
+            // The plan is written in full before the journal appears under its
+            // name, so a crash never leaves a journal without a readable plan
+            let partial = path.with_extension("jsonl.partial");
+            let mut file = match OpenOptions::new()
+                .create_new(true)
+                .write(true)
+                .open(&partial)
+            {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create journal {:?}", path))
+                }
+            };
+            let mut line = serde_json::to_string(&JournalEvent::Planned {
+                id: id.clone(),
+                started,
+                plan: plan.clone(),
+            })?;
+            line.push('\n');
+            file.write_all(line.as_bytes())
+                .and_then(|_| file.sync_data())
+                .with_context(|| format!("Failed to write journal {:?}", partial))?;
+            break (id, path, partial);
+        };
+        fs::rename(&partial, &path)
+            .with_context(|| format!("Failed to create journal {:?}", path))?;
+
+        Ok(Journal {
+            path,
+            file: None,
+            id,
+            started,
+            plan,
+            placing: HashSet::new(),
+            sidecars: HashMap::new(),
+            done: HashMap::new(),
+            removed: HashSet::new(),
+            reverted: HashSet::new(),
+            completed: None,
+            undone: None,
+        })
+    }
+
+    /// Replay a journal file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read journal {:?}", path))?;
+        let mut lines = text.lines();
+
+        let first = lines.next().unwrap_or_default();
+        let JournalEvent::Planned { id, started, plan } = serde_json::from_str(first)
+            .with_context(|| format!("Journal {:?} has no plan", path))?
+        else {
+            anyhow::bail!("Journal {:?} doesn't start with a plan", path);
+        };
+
+        let mut journal = Journal {
+            path: path.to_path_buf(),
+            file: None,
+            id,
+            started,
+            plan,
+            placing: HashSet::new(),
+            sidecars: HashMap::new(),
+            done: HashMap::new(),
+            removed: HashSet::new(),
+            reverted: HashSet::new(),
+            completed: None,
+            undone: None,
+        };
+        for line in lines {
+            // A crash can leave a torn line; it only loses its own event
+            let Ok(event) = serde_json::from_str::<JournalEvent>(line) else {
+                continue;
+            };
+            match event {
+                JournalEvent::Planned { .. } => {}
+                JournalEvent::Started { index } => {
+                    journal.placing.insert(index);
+                }
+                JournalEvent::SidecarWritten { index, hash } => {
+                    journal.sidecars.insert(index, hash);
+                }
+                JournalEvent::Done { index, method } => {
+                    journal.done.insert(index, method);
+                }
+                JournalEvent::SourceRemoved { index } => {
+                    journal.removed.insert(index);
+                }
+                JournalEvent::Reverted { index } => {
+                    journal.reverted.insert(index);
+                }
+                JournalEvent::Completed { at } => journal.completed = Some(at),
+                JournalEvent::Undone { at } => journal.undone = Some(at),
+                JournalEvent::Relocated { moves } => journal.apply_relocations(&moves),
+            }
+        }
+        Ok(journal)
+    }
+
+    /// All journals of an archive, oldest first
+    pub fn list(archive_root: &Path) -> Result<Vec<Journal>> {
+        let dir = archive_root.join(JOURNAL_DIR);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read {:?}", dir))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect();
+        paths.sort();
+
+        // One damaged journal shouldn't take the others down with it
+        let mut journals: Vec<Journal> = paths
+            .iter()
+            .filter_map(|path| match Journal::load(path) {
+                Ok(journal) => Some(journal),
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable journal {:?}: {:#}", path, e);
+                    None
+                }
+            })
+            .collect();
+        journals.sort_by_key(|journal| journal.started);
+        Ok(journals)
+    }
+
+    /// The most recent interrupted ingest of the same `sources` into the archive
+    pub fn find_incomplete(archive_root: &Path, sources: &[PathBuf]) -> Result<Option<Journal>> {
+        Ok(Journal::list(archive_root)?
+            .into_iter()
+            .rev()
+            .find(|journal| {
+                !journal.is_completed()
+                    && !journal.is_undone()
+                    && journal.reverted.is_empty()
+                    && journal
+                        .plan
+                        .sources()
+                        .eq(sources.iter().map(PathBuf::as_path))
+            }))
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn started(&self) -> DateTime<Utc> {
+        self.started
+    }
+
+    pub fn plan(&self) -> &IngestPlan {
+        &self.plan
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.completed.is_some()
+    }
+
+    /// Whether `folio undo` has reverted this ingest
+    pub fn is_undone(&self) -> bool {
+        self.undone.is_some()
+    }
+
+    /// Whether an undo that didn't finish already took the file at `index`
+    /// back out of the archive
+    pub fn is_reverted(&self, index: usize) -> bool {
+        self.reverted.contains(&index)
+    }
+
+    /// Whether placing the file at `index` began, so a file at its destination
+    /// is a leftover of this ingest rather than someone else's
+    pub fn is_started(&self, index: usize) -> bool {
+        self.placing.contains(&index)
+    }
+
+    /// Whether the file at `index` of the plan is in the archive
+    pub fn is_done(&self, index: usize) -> bool {
+        self.done.contains_key(&index)
+    }
+
+    /// How the file at `index` was placed in the archive (None for duplicates)
+    pub fn method(&self, index: usize) -> Option<TransferMethod> {
+        self.done.get(&index).copied().flatten()
+    }
+
+    /// Hash of the sidecar the ingest wrote for the file at `index`, if recorded
+    pub fn sidecar_hash(&self, index: usize) -> Option<Blake3Hash> {
+        Blake3Hash::from_hex(self.sidecars.get(&index)?).ok()
+    }
+
+    /// Whether the source of the file at `index` was removed by move mode
+    pub fn is_source_removed(&self, index: usize) -> bool {
+        self.removed.contains(&index)
+    }
+
+    pub fn record_started(&mut self, index: usize) -> Result<()> {
+        self.append(&JournalEvent::Started { index })?;
+        self.placing.insert(index);
+        Ok(())
+    }
+
+    pub fn record_sidecar(&mut self, index: usize, hash: Blake3Hash) -> Result<()> {
+        let hash = hash.to_hex().to_string();
+        self.append(&JournalEvent::SidecarWritten {
+            index,
+            hash: hash.clone(),
+        })?;
+        self.sidecars.insert(index, hash);
+        Ok(())
+    }
+
+    pub fn record_done(&mut self, index: usize, method: Option<TransferMethod>) -> Result<()> {
+        self.append(&JournalEvent::Done { index, method })?;
+        self.done.insert(index, method);
+        Ok(())
+    }
+
+    pub fn record_source_removed(&mut self, index: usize) -> Result<()> {
+        self.append(&JournalEvent::SourceRemoved { index })?;
+        self.removed.insert(index);
+        Ok(())
+    }
+
+    /// Mark the ingest as finished
+    pub fn complete(&mut self) -> Result<()> {
+        let at = Utc::now();
+        self.append(&JournalEvent::Completed { at })?;
+        self.completed = Some(at);
+        Ok(())
+    }
+
+    pub fn record_reverted(&mut self, index: usize) -> Result<()> {
+        self.append(&JournalEvent::Reverted { index })?;
+        self.reverted.insert(index);
+        Ok(())
+    }
+
+    /// Mark the ingest as reverted
+    pub fn record_undone(&mut self) -> Result<()> {
+        let at = Utc::now();
+        self.append(&JournalEvent::Undone { at })?;
+        self.undone = Some(at);
+        Ok(())
+    }
+
+    /// Record that archived files were renamed or moved since the ingest
+    ///
+    /// Written as a single line, so a journal is never left half-updated.
+    pub fn record_relocations(&mut self, moves: Vec<Relocation>) -> Result<()> {
+        if moves.is_empty() {
+            return Ok(());
+        }
+        self.append(&JournalEvent::Relocated {
+            moves: moves.clone(),
+        })?;
+        self.apply_relocations(&moves);
+        Ok(())
+    }
+
+    fn apply_relocations(&mut self, moves: &[Relocation]) {
+        for relocation in moves {
+            if let Some(file) = self.plan.files.get_mut(relocation.index) {
+                file.archived = relocation.archived.clone();
+                file.batch = relocation.batch.clone();
+                if let Some(title) = &relocation.title {
+                    file.title = Some(title.clone());
+                }
+            }
+            if let Some(hash) = &relocation.sidecar {
+                self.sidecars.insert(relocation.index, hash.clone());
+            }
+        }
+    }
+
+    fn append(&mut self, event: &JournalEvent) -> Result<()> {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => self.file.insert(open_log(&self.path)?),
+        };
+
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.sync_data())
+            .with_context(|| format!("Failed to write journal {:?}", self.path))
+    }
+}
+
+/// Open a JSON lines file for appending, first cutting off a torn last line
+/// a crash may have left, so the next entry starts on a line of its own
+pub(crate) fn open_log(path: &Path) -> Result<File> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {:?}", path))?;
+    let mut text = Vec::new();
+    file.read_to_end(&mut text)
+        .with_context(|| format!("Failed to read {:?}", path))?;
+    let complete = text
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |i| i + 1);
+    if complete < text.len() {
+        file.set_len(complete as u64)
+            .with_context(|| format!("Failed to repair {:?}", path))?;
+    }
+    Ok(file)
+}
+
+/// Point every journal of the archive at files' new locations
+///
+/// `moves` are (old, new) paths relative to the archive root with the new batch
+/// name, if it changes. If a journal can't be updated, the ones already updated
+/// are reverted.
+/// Returns how many journal entries changed.
+pub fn relocate_in_catalog(
+    archive_root: &Path,
+    moves: &[(PathBuf, PathBuf, Option<String>)],
+) -> Result<usize> {
+    let targets: HashMap<&Path, (&PathBuf, &Option<String>)> = moves
+        .iter()
+        .map(|(from, to, batch)| (from.as_path(), (to, batch)))
+        .collect();
+
+    record_in_catalog(archive_root, |journal| {
+        journal
+            .plan
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(index, file)| {
+                let (to, batch) = targets.get(file.archived.as_path())?;
+                Some(Relocation {
+                    index,
+                    archived: (*to).clone(),
+                    batch: (*batch).clone().unwrap_or_else(|| file.batch.clone()),
+                    title: None,
+                    sidecar: None,
+                })
+            })
+            .collect()
+    })
+}
+
+/// Record new batch titles in every journal of the archive
+///
+/// Only files that have a title get the new one. A sidecar hash is only replaced where
+/// the journal recorded the old one, so sidecars edited since the ingest
+/// still keep undo from removing them. If a journal can't be updated, the
+/// ones already updated are reverted.
+/// Returns how many journal entries changed.
+pub fn retitle_in_catalog(archive_root: &Path, titles: &[Retitle]) -> Result<usize> {
+    let targets: HashMap<&Path, &Retitle> = titles
+        .iter()
+        .map(|retitle| (retitle.archived.as_path(), retitle))
+        .collect();
+
+    record_in_catalog(archive_root, |journal| {
+        journal
+            .plan
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(index, file)| {
+                let retitle = targets.get(file.archived.as_path())?;
+                let title = file.title.as_ref().map(|_| retitle.title.clone());
+                let sidecar = retitle.sidecar.and_then(|(old, new)| {
+                    (journal.sidecar_hash(index) == Some(old)).then(|| new.to_hex().to_string())
+                });
+                if title.is_none() && sidecar.is_none() {
+                    return None;
+                }
+                Some(Relocation {
+                    index,
+                    archived: file.archived.clone(),
+                    batch: file.batch.clone(),
+                    title,
+                    sidecar,
+                })
+            })
+            .collect()
+    })
+}
+
+/// Record the relocations `plan` picks for each journal, all or nothing
+fn record_in_catalog(
+    archive_root: &Path,
+    plan: impl Fn(&Journal) -> Vec<Relocation>,
+) -> Result<usize> {
+    let mut updated: Vec<(Journal, Vec<Relocation>)> = Vec::new();
+    let mut count = 0;
+    for mut journal in Journal::list(archive_root)? {
+        let relocations = plan(&journal);
+        if relocations.is_empty() {
+            continue;
+        }
+
+        let inverse: Vec<Relocation> = relocations
+            .iter()
+            .map(|r| {
+                let file = &journal.plan.files[r.index];
+                Relocation {
+                    index: r.index,
+                    archived: file.archived.clone(),
+                    batch: file.batch.clone(),
+                    title: r.title.as_ref().and(file.title.clone()),
+                    sidecar: r
+                        .sidecar
+                        .as_ref()
+                        .and(journal.sidecars.get(&r.index).cloned()),
+                }
+            })
+            .collect();
+
+        count += relocations.len();
+        if let Err(e) = journal.record_relocations(relocations) {
+            for (mut done, inverse) in updated {
+                if let Err(e) = done.record_relocations(inverse) {
+                    tracing::error!("Failed to revert journal {:?}: {}", done.path(), e);
+                }
+            }
+            return Err(e);
+        }
+        updated.push((journal, inverse));
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan() -> IngestPlan {
+        let file = |name: &str| PlannedFile {
+            source: PathBuf::from("/card/DCIM").join(name),
+            hash: blake3::hash(name.as_bytes()).to_hex().to_string(),
+            archived: PathBuf::from("2024/11/04").join(name),
+            batch: "halloween".to_string(),
+            batch_index: 0,
+            duplicate: false,
+            corrected_time: None,
+            title: None,
+            location: None,
+        };
+        IngestPlan {
+            source: PathBuf::from("/card/DCIM"),
+            extra_sources: Vec::new(),
+            link: LinkMode::Copy,
+            move_sources: false,
+            files: vec![file("a.jpg"), file("b.jpg")],
+        }
+    }
+
+    #[test]
+    fn test_journal_replays_progress() {
+        let archive = tempfile::tempdir().unwrap();
+
+        let mut journal = Journal::create(archive.path(), plan()).unwrap();
+        journal.record_started(0).unwrap();
+        journal.record_done(0, Some(TransferMethod::Copy)).unwrap();
+        journal.record_source_removed(0).unwrap();
+        journal.record_started(1).unwrap();
+
+        let loaded = Journal::load(journal.path()).unwrap();
+        assert_eq!(loaded.id(), journal.id());
+        assert_eq!(loaded.plan(), &plan());
+        assert!(loaded.is_done(0));
+        assert!(!loaded.is_done(1));
+        assert!(loaded.is_started(1));
+        assert_eq!(loaded.method(0), Some(TransferMethod::Copy));
+        assert!(loaded.is_source_removed(0));
+        assert!(!loaded.is_completed());
+    }
+
+    #[test]
+    fn test_find_incomplete_ignores_completed_and_other_sources() {
+        let archive = tempfile::tempdir().unwrap();
+
+        let mut finished = Journal::create(archive.path(), plan()).unwrap();
+        finished.complete().unwrap();
+        let interrupted = Journal::create(archive.path(), plan()).unwrap();
+        assert_ne!(finished.id(), interrupted.id());
+
+        let found = Journal::find_incomplete(archive.path(), &[PathBuf::from("/card/DCIM")])
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id(), interrupted.id());
+        assert!(
+            Journal::find_incomplete(archive.path(), &[PathBuf::from("/other")])
+                .unwrap()
+                .is_none()
+        );
+        // A multi-source ingest only resumes with the same set of sources
+        let both = [PathBuf::from("/card/DCIM"), PathBuf::from("/phone/DCIM")];
+        assert!(Journal::find_incomplete(archive.path(), &both)
+            .unwrap()
+            .is_none());
+        let multi = Journal::create(
+            archive.path(),
+            IngestPlan {
+                extra_sources: vec![PathBuf::from("/phone/DCIM")],
+                ..plan()
+            },
+        )
+        .unwrap();
+        let found = Journal::find_incomplete(archive.path(), &both)
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id(), multi.id());
+    }
+
+    #[test]
+    fn test_relocations_survive_reload() {
+        let archive = tempfile::tempdir().unwrap();
+        let journal = Journal::create(archive.path(), plan()).unwrap();
+
+        let count = relocate_in_catalog(
+            archive.path(),
+            &[(
+                PathBuf::from("2024/11/04/b.jpg"),
+                PathBuf::from("2024/11/04/c.jpg"),
+                Some("thanksgiving".to_string()),
+            )],
+        )
+        .unwrap();
+
+        assert_eq!(count, 1);
+        let loaded = Journal::load(journal.path()).unwrap();
+        assert_eq!(
+            loaded.plan().files[1].archived,
+            PathBuf::from("2024/11/04/c.jpg")
+        );
+        assert_eq!(loaded.plan().files[1].batch, "thanksgiving");
+        assert_eq!(loaded.plan().files[0].batch, "halloween");
+    }
+
+    #[test]
+    fn test_retitle_only_changes_titled_files() {
+        let archive = tempfile::tempdir().unwrap();
+        let mut plan = plan();
+        plan.files[0].title = Some("Thanksgving".to_string());
+        let mut journal = Journal::create(archive.path(), plan).unwrap();
+        let (old, new) = (blake3::hash(b"old"), blake3::hash(b"new"));
+        journal.record_sidecar(0, old).unwrap();
+
+        let count = retitle_in_catalog(
+            archive.path(),
+            &[
+                Retitle {
+                    archived: PathBuf::from("2024/11/04/a.jpg"),
+                    title: "Thanksgiving".to_string(),
+                    sidecar: Some((old, new)),
+                },
+                Retitle {
+                    archived: PathBuf::from("2024/11/04/b.jpg"),
+                    title: "Thanksgiving".to_string(),
+                    sidecar: None,
+                },
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(count, 1);
+        let loaded = Journal::load(journal.path()).unwrap();
+        assert_eq!(
+            loaded.plan().files[0].title.as_deref(),
+            Some("Thanksgiving")
+        );
+        assert_eq!(loaded.sidecar_hash(0), Some(new));
+        assert_eq!(loaded.plan().files[1].title, None);
+    }
+
+    #[test]
+    fn test_load_tolerates_torn_last_line() {
+        let archive = tempfile::tempdir().unwrap();
+        let mut journal = Journal::create(archive.path(), plan()).unwrap();
+        journal.record_done(0, Some(TransferMethod::Copy)).unwrap();
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(journal.path())
+            .unwrap();
+        file.write_all(b"{\"event\":\"done\",\"ind").unwrap();
+
+        let mut loaded = Journal::load(journal.path()).unwrap();
+        assert!(loaded.is_done(0));
+        assert!(!loaded.is_done(1));
+
+        // Resuming appends after the last complete line, not onto the fragment
+        loaded.record_done(1, Some(TransferMethod::Copy)).unwrap();
+        loaded.complete().unwrap();
+        let reloaded = Journal::load(journal.path()).unwrap();
+        assert!(reloaded.is_done(0));
+        assert!(reloaded.is_done(1));
+        assert!(reloaded.is_completed());
+    }
+
+    #[test]
+    fn test_load_skips_damaged_lines() {
+        let archive = tempfile::tempdir().unwrap();
+        let mut journal = Journal::create(archive.path(), plan()).unwrap();
+        journal.record_done(0, Some(TransferMethod::Copy)).unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(journal.path())
+            .unwrap();
+        file.write_all(b"{\"event\":\"do\n").unwrap();
+        journal.record_done(1, None).unwrap();
+
+        let loaded = Journal::load(journal.path()).unwrap();
+        assert!(loaded.is_done(0));
+        assert!(loaded.is_done(1));
+    }
+
+    #[test]
+    fn test_list_skips_journals_with_a_torn_plan() {
+        let archive = tempfile::tempdir().unwrap();
+        let journal = Journal::create(archive.path(), plan()).unwrap();
+        let dir = archive.path().join(JOURNAL_DIR);
+        let names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names.len(), 1, "no partial journal is left behind");
+
+        // Written by a version that could crash half way through the plan
+        fs::write(
+            dir.join("20200101-000000.jsonl"),
+            "{\"event\":\"planned\",\"id\":\"20",
+        )
+        .unwrap();
+
+        let journals = Journal::list(archive.path()).unwrap();
+        assert_eq!(journals.len(), 1);
+        assert_eq!(journals[0].id(), journal.id());
+    }
+}
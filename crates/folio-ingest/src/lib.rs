@@ -1,14 +1,26 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
-}
+pub mod fixity;
+pub mod history;
+pub mod journal;
+pub mod preflight;
+pub mod replicate;
+pub mod s3;
+pub mod transfer;
+pub mod undo;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
-}
+pub use fixity::{recorded_hashes, verify_archive, FixityLog, FixityReport, Sample, FIXITY_FILE};
+pub use history::{ImportHistory, ImportedFile, IMPORTS_DIR};
+pub use journal::{
+    relocate_in_catalog, retitle_in_catalog, IngestPlan, Journal, PlannedFile, Relocation, Retitle,
+    JOURNAL_DIR,
+};
+pub use preflight::{preflight, space_needed, Destination, SPACE_MARGIN};
+pub use replicate::{
+    delete_replica, plan_replication, remove_partial_copies, replicate_file, ReplicaCopy,
+    ReplicaRecord, ReplicationPlan, REPLICAS_DIR,
+};
+pub use s3::{
+    plan_store_upload, verify_store, S3Location, S3Store, StorePlan, DEFAULT_PART_SIZE,
+    MIN_PART_SIZE,
+};
+pub use transfer::{transfer_file, verify_or_remove, LinkMode, MoveLog, TransferMethod};
+pub use undo::{apply_undo, plan_undo, UndoAction, UndoPlan};
@@ -0,0 +1,162 @@
+use crate::transfer::{same_device, LinkMode};
+use anyhow::{Context, Result};
+use folio_core::config::CONFIG_FILE_NAME;
+use folio_core::{format_size, ArchiveMarker};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Free space kept on top of what an ingest copies, for journals, sidecars and
+/// manifests, and so the archive's disk is never filled to the last byte
+pub const SPACE_MARGIN: u64 = 64 << 20;
+
+/// What a directory given as an archive turned out to be
+#[derive(Debug, Clone, PartialEq)]
+pub enum Destination {
+    /// An archive with its `.folio/archive.toml` marker
+    Archive(ArchiveMarker),
+    /// An archive from before `folio init`: it has `.folio/` but no marker
+    Unmarked,
+    /// Missing or empty (apart from a `folio.toml`)
+    New,
+    /// A directory with files in it that isn't an archive
+    Foreign,
+}
+
+impl Destination {
+    /// Look at `archive` without changing anything
+    pub fn inspect(archive: &Path) -> Result<Self> {
+        if let Some(marker) = ArchiveMarker::load(archive)? {
+            return Ok(Destination::Archive(marker));
+        }
+        if archive.join(".folio").is_dir() {
+            return Ok(Destination::Unmarked);
+        }
+        if !archive.exists() {
+            return Ok(Destination::New);
+        }
+        let mut entries =
+            fs::read_dir(archive).with_context(|| format!("Failed to read {:?}", archive))?;
+        let foreign =
+            entries.any(|entry| entry.map_or(true, |entry| entry.file_name() != CONFIG_FILE_NAME));
+        Ok(if foreign {
+            Destination::Foreign
+        } else {
+            Destination::New
+        })
+    }
+}
+
+/// Bytes an ingest of `files` (source paths and sizes) will add to `archive`
+///
+/// Hardlinks on the archive's own filesystem take no space; everything else
+/// is counted, as a clone that isn't possible falls back to a copy.
+pub fn space_needed(files: &[(PathBuf, u64)], archive: &Path, link: LinkMode) -> u64 {
+    let existing = existing_ancestor(archive);
+    files
+        .iter()
+        .filter(|(source, _)| !(link == LinkMode::Hard && same_device(source, &existing.join("_"))))
+        .map(|(_, size)| size)
+        .sum()
+}
+
+/// Check the destination can take `needed` bytes, before anything is asked or copied
+///
+/// The directory (or, if it doesn't exist yet, its nearest existing parent)
+/// must be writable, and its filesystem must have room for `needed` plus a
+/// margin of 5% and `SPACE_MARGIN`.
+pub fn preflight(archive: &Path, needed: u64) -> Result<()> {
+    let existing = existing_ancestor(archive);
+    check_writable(&existing)?;
+    let available = fs4::available_space(&existing)
+        .with_context(|| format!("Failed to read free space of {:?}", existing))?;
+    check_space(&existing, needed, available)
+}
+
+/// Whether `needed` bytes fit into `available` with the safety margin
+fn check_space(dir: &Path, needed: u64, available: u64) -> Result<()> {
+    let margin = needed / 20 + SPACE_MARGIN;
+    if needed.saturating_add(margin) > available {
+        anyhow::bail!(
+            "Not enough space on {}: {} to copy (plus {} margin), {} free",
+            dir.display(),
+            format_size(needed),
+            format_size(margin),
+            format_size(available)
+        );
+    }
+    Ok(())
+}
+
+/// Create and remove a probe file, as permissions alone don't show read-only
+/// mounts or full quotas
+fn check_writable(dir: &Path) -> Result<()> {
+    let probe = dir.join(format!(".folio-write-test-{}", std::process::id()));
+    fs::write(&probe, b"")
+        .and_then(|_| fs::remove_file(&probe))
+        .with_context(|| format!("Cannot write to {}", dir.display()))
+}
+
+/// `path` itself or its closest parent that exists
+fn existing_ancestor(path: &Path) -> PathBuf {
+    path.ancestors()
+        .find(|dir| dir.exists())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_destination_kinds() {
+        let root = TempDir::new().unwrap();
+        let archive = root.path().join("archive");
+        assert_eq!(Destination::inspect(&archive).unwrap(), Destination::New);
+
+        fs::create_dir(&archive).unwrap();
+        fs::write(archive.join(CONFIG_FILE_NAME), "").unwrap();
+        assert_eq!(Destination::inspect(&archive).unwrap(), Destination::New);
+        fs::write(archive.join("notes.txt"), "").unwrap();
+        assert_eq!(
+            Destination::inspect(&archive).unwrap(),
+            Destination::Foreign
+        );
+
+        fs::create_dir(archive.join(".folio")).unwrap();
+        assert_eq!(
+            Destination::inspect(&archive).unwrap(),
+            Destination::Unmarked
+        );
+        let marker = ArchiveMarker::create(&archive).unwrap();
+        assert_eq!(
+            Destination::inspect(&archive).unwrap(),
+            Destination::Archive(marker)
+        );
+    }
+
+    #[test]
+    fn test_space_check_keeps_a_margin() {
+        let dir = Path::new("/archive");
+        assert!(check_space(dir, 0, SPACE_MARGIN).is_ok());
+        assert!(check_space(dir, 1, SPACE_MARGIN).is_err());
+        assert!(check_space(dir, 20 << 30, (21 << 30) + SPACE_MARGIN).is_ok());
+        let error = check_space(dir, 20 << 30, 20 << 30).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Not enough space on /archive: 20.0 GB to copy (plus 1.1 GB margin), 20.0 GB free"
+        );
+    }
+
+    #[test]
+    fn test_preflight_of_missing_archive_checks_its_parent() {
+        let root = TempDir::new().unwrap();
+        let archive = root.path().join("new/archive");
+        preflight(&archive, 1024).unwrap();
+        // Nothing is created yet, not even the probe file
+        assert!(!root.path().join("new").exists());
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 0);
+        assert!(preflight(&archive, u64::MAX / 2).is_err());
+    }
+}
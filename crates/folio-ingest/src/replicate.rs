@@ -0,0 +1,427 @@
+use crate::fixity::FIXITY_FILE;
+use crate::journal::open_log;
+use crate::transfer::verify_or_remove;
+use anyhow::{Context, Result};
+use blake3::Hash as Blake3Hash;
+use folio_core::fs_ops::prune_empty_parents;
+use folio_core::hash_file;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Replication records inside the source archive, one file per target
+pub const REPLICAS_DIR: &str = ".folio/replicas";
+
+/// Suffix of files still being copied to a target
+const PARTIAL_SUFFIX: &str = ".folio-partial";
+
+/// A file to copy to the target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaCopy {
+    /// Relative to both roots
+    pub path: PathBuf,
+    pub hash: Blake3Hash,
+    /// Replaces an older replica of the file
+    pub update: bool,
+}
+
+/// What a replication run will do, and where source and target drifted apart
+///
+/// Paths are relative to the archive and target roots.
+#[derive(Debug, Default)]
+pub struct ReplicationPlan {
+    /// New files, and files changed in the archive since they were replicated
+    pub copies: Vec<ReplicaCopy>,
+    /// Identical on both sides
+    pub unchanged: usize,
+    /// Identical on both sides but not yet in the replication record
+    pub adopt: Vec<(PathBuf, Blake3Hash)>,
+    /// Differ from the archive, and the target copy isn't the one replicated
+    /// (edited or damaged on the target); left alone
+    pub changed_on_target: Vec<PathBuf>,
+    /// Replicated before and since deleted from the archive; only removed on request
+    pub deleted_from_source: Vec<PathBuf>,
+    /// On the target only, never replicated from this archive
+    pub only_on_target: Vec<PathBuf>,
+}
+
+impl ReplicationPlan {
+    pub fn has_drift(&self) -> bool {
+        !self.changed_on_target.is_empty()
+            || !self.deleted_from_source.is_empty()
+            || !self.only_on_target.is_empty()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum ReplicaEvent {
+    Copied { path: PathBuf, hash: String },
+    Deleted { path: PathBuf },
+}
+
+/// Which files were replicated to a target, with the hash they had
+///
+/// Kept in `<archive>/.folio/replicas/` as JSON lines appended after every
+/// verified copy, so an interrupted run loses nothing and the next run carries
+/// on. It tells a file deleted from the archive apart from one that only ever
+/// existed on the target.
+#[derive(Debug)]
+pub struct ReplicaRecord {
+    path: PathBuf,
+    file: Option<File>,
+    replicated: BTreeMap<PathBuf, Blake3Hash>,
+}
+
+impl ReplicaRecord {
+    /// The record of replications from `archive` to `target`
+    pub fn load(archive: &Path, target: &Path) -> Result<Self> {
+        let target = fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf());
+        let key = blake3::hash(target.to_string_lossy().as_bytes()).to_hex();
+        let path = archive
+            .join(REPLICAS_DIR)
+            .join(format!("{}.jsonl", &key[..16]));
+
+        let mut replicated = BTreeMap::new();
+        if path.exists() {
+            let text =
+                fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+            for line in text.lines() {
+                // A torn line only loses that file's entry
+                let Ok(event) = serde_json::from_str::<ReplicaEvent>(line) else {
+                    continue;
+                };
+                match event {
+                    ReplicaEvent::Copied { path, hash } => {
+                        if let Ok(hash) = Blake3Hash::from_hex(&hash) {
+                            replicated.insert(path, hash);
+                        }
+                    }
+                    ReplicaEvent::Deleted { path } => {
+                        replicated.remove(&path);
+                    }
+                }
+            }
+        }
+        Ok(ReplicaRecord {
+            path,
+            file: None,
+            replicated,
+        })
+    }
+
+    /// Hash of the file as last replicated
+    pub fn get(&self, path: &Path) -> Option<&Blake3Hash> {
+        self.replicated.get(path)
+    }
+
+    pub fn record_copied(&mut self, path: &Path, hash: Blake3Hash) -> Result<()> {
+        self.append(&ReplicaEvent::Copied {
+            path: path.to_path_buf(),
+            hash: hash.to_hex().to_string(),
+        })?;
+        self.replicated.insert(path.to_path_buf(), hash);
+        Ok(())
+    }
+
+    pub fn record_deleted(&mut self, path: &Path) -> Result<()> {
+        self.append(&ReplicaEvent::Deleted {
+            path: path.to_path_buf(),
+        })?;
+        self.replicated.remove(path);
+        Ok(())
+    }
+
+    fn append(&mut self, event: &ReplicaEvent) -> Result<()> {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => {
+                if let Some(dir) = self.path.parent() {
+                    fs::create_dir_all(dir)
+                        .with_context(|| format!("Failed to create {:?}", dir))?;
+                }
+                self.file.insert(open_log(&self.path)?)
+            }
+        };
+
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.sync_data())
+            .with_context(|| format!("Failed to write {:?}", self.path))
+    }
+}
+
+/// Compare archive and target by content hash
+pub fn plan_replication(
+    archive: &Path,
+    target: &Path,
+    record: &ReplicaRecord,
+) -> Result<ReplicationPlan> {
+    let sources = replicated_files(archive)?;
+    let targets = if target.exists() {
+        replicated_files(target)?
+    } else {
+        Vec::new()
+    };
+    let mut plan = ReplicationPlan::default();
+
+    for path in &sources {
+        let hash = hash_file(&archive.join(path))?;
+        if !target.join(path).is_file() {
+            plan.copies.push(ReplicaCopy {
+                path: path.clone(),
+                hash,
+                update: false,
+            });
+            continue;
+        }
+
+        let replica = hash_file(&target.join(path))?;
+        if replica == hash {
+            plan.unchanged += 1;
+            if record.get(path) != Some(&hash) {
+                plan.adopt.push((path.clone(), hash));
+            }
+        } else if record.get(path) == Some(&replica) {
+            // The target still holds what was replicated; the archive moved on
+            plan.copies.push(ReplicaCopy {
+                path: path.clone(),
+                hash,
+                update: true,
+            });
+        } else {
+            plan.changed_on_target.push(path.clone());
+        }
+    }
+
+    let in_source: HashSet<&PathBuf> = sources.iter().collect();
+    for path in targets.iter().filter(|path| !in_source.contains(path)) {
+        let untouched = match record.get(path) {
+            Some(replicated) => hash_file(&target.join(path))? == *replicated,
+            None => false,
+        };
+        if untouched {
+            plan.deleted_from_source.push(path.clone());
+        } else {
+            plan.only_on_target.push(path.clone());
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Copy one file to the target, verify the copy and only then put it in place
+///
+/// The copy is written next to its destination under a temporary name, so an
+/// interruption never leaves a truncated file under the real name.
+pub fn replicate_file(archive: &Path, target: &Path, copy: &ReplicaCopy) -> Result<()> {
+    let dest = target.join(&copy.path);
+    let dir = dest.parent().unwrap_or(target);
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+
+    let partial = partial_path(&dest);
+    fs::copy(archive.join(&copy.path), &partial)
+        .with_context(|| format!("Failed to copy {:?}", copy.path))?;
+    verify_or_remove(&partial, &copy.hash)?;
+    fs::rename(&partial, &dest).with_context(|| format!("Failed to replace {:?}", dest))
+}
+
+/// Remove a replica whose original was deleted from the archive
+pub fn delete_replica(target: &Path, path: &Path) -> Result<()> {
+    let file = target.join(path);
+    fs::remove_file(&file).with_context(|| format!("Failed to remove {:?}", file))?;
+    if let Some(parent) = file.parent() {
+        prune_empty_parents(parent, target)?;
+    }
+    Ok(())
+}
+
+/// Remove copies left half-written by an interrupted run; returns how many
+pub fn remove_partial_copies(target: &Path) -> Result<usize> {
+    if !target.exists() {
+        return Ok(0);
+    }
+    let mut removed = 0;
+    for entry in WalkDir::new(target) {
+        let entry = entry.context("Failed to read directory entry")?;
+        if entry.file_type().is_file()
+            && entry
+                .file_name()
+                .to_string_lossy()
+                .ends_with(PARTIAL_SUFFIX)
+        {
+            fs::remove_file(entry.path())
+                .with_context(|| format!("Failed to remove {:?}", entry.path()))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+fn partial_path(dest: &Path) -> PathBuf {
+    let name = dest.file_name().unwrap_or_default().to_string_lossy();
+    dest.with_file_name(format!(".{}{}", name, PARTIAL_SUFFIX))
+}
+
+/// Files a replica holds, sorted: everything except each copy's own records
+/// (replications, verification times) and unfinished copies
+pub(crate) fn replicated_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let local = [root.join(REPLICAS_DIR), root.join(FIXITY_FILE)];
+    let mut files = Vec::new();
+    let walker = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| !local.iter().any(|path| entry.path() == path));
+    for entry in walker {
+        let entry = entry.context("Failed to read directory entry")?;
+        if !entry.file_type().is_file()
+            || entry
+                .file_name()
+                .to_string_lossy()
+                .ends_with(PARTIAL_SUFFIX)
+        {
+            continue;
+        }
+        files.push(entry.path().strip_prefix(root)?.to_path_buf());
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let file = root.join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, content).unwrap();
+    }
+
+    /// Plan and carry out a replication, deleting nothing
+    fn replicate(archive: &Path, target: &Path) -> ReplicationPlan {
+        let mut record = ReplicaRecord::load(archive, target).unwrap();
+        let plan = plan_replication(archive, target, &record).unwrap();
+        for copy in &plan.copies {
+            replicate_file(archive, target, copy).unwrap();
+            record.record_copied(&copy.path, copy.hash).unwrap();
+        }
+        plan
+    }
+
+    #[test]
+    fn test_replication_copies_new_and_changed_files() {
+        let archive = TempDir::new().unwrap();
+        let target = TempDir::new().unwrap();
+        write(archive.path(), "2024/a.jpg", "a");
+        write(archive.path(), "2024/a.xmp", "sidecar");
+        write(archive.path(), ".folio/journal/1.jsonl", "{}");
+
+        let plan = replicate(archive.path(), target.path());
+        assert_eq!(plan.copies.len(), 3);
+        assert_eq!(
+            fs::read_to_string(target.path().join("2024/a.jpg")).unwrap(),
+            "a"
+        );
+        // The record of this target isn't replicated itself
+        assert!(!target.path().join(REPLICAS_DIR).exists());
+
+        write(archive.path(), "2024/a.xmp", "edited sidecar");
+        let plan = replicate(archive.path(), target.path());
+        assert_eq!(plan.unchanged, 2);
+        assert_eq!(
+            plan.copies,
+            vec![ReplicaCopy {
+                path: PathBuf::from("2024/a.xmp"),
+                hash: blake3::hash(b"edited sidecar"),
+                update: true,
+            }]
+        );
+        assert_eq!(
+            fs::read_to_string(target.path().join("2024/a.xmp")).unwrap(),
+            "edited sidecar"
+        );
+        assert!(!plan.has_drift());
+    }
+
+    #[test]
+    fn test_drift_is_reported_not_propagated() {
+        let archive = TempDir::new().unwrap();
+        let target = TempDir::new().unwrap();
+        write(archive.path(), "2024/a.jpg", "a");
+        write(archive.path(), "2024/b.jpg", "b");
+        write(archive.path(), "2024/c.jpg", "c");
+        replicate(archive.path(), target.path());
+
+        fs::remove_file(archive.path().join("2024/a.jpg")).unwrap();
+        write(target.path(), "2024/b.jpg", "rotted");
+        write(target.path(), "2024/extra.jpg", "?");
+        // Changed on both sides: the target copy is left alone
+        write(archive.path(), "2024/c.jpg", "c2");
+        write(target.path(), "2024/c.jpg", "c3");
+
+        let record = ReplicaRecord::load(archive.path(), target.path()).unwrap();
+        let plan = plan_replication(archive.path(), target.path(), &record).unwrap();
+        assert!(plan.copies.is_empty());
+        assert_eq!(plan.deleted_from_source, vec![PathBuf::from("2024/a.jpg")]);
+        assert_eq!(
+            plan.changed_on_target,
+            vec![PathBuf::from("2024/b.jpg"), PathBuf::from("2024/c.jpg")]
+        );
+        assert_eq!(plan.only_on_target, vec![PathBuf::from("2024/extra.jpg")]);
+
+        delete_replica(target.path(), Path::new("2024/a.jpg")).unwrap();
+        assert!(!target.path().join("2024/a.jpg").exists());
+    }
+
+    #[test]
+    fn test_interrupted_copy_is_cleaned_up_and_redone() {
+        let archive = TempDir::new().unwrap();
+        let target = TempDir::new().unwrap();
+        write(archive.path(), "2024/a.jpg", "a");
+        write(target.path(), "2024/.a.jpg.folio-partial", "trunc");
+
+        let record = ReplicaRecord::load(archive.path(), target.path()).unwrap();
+        let plan = plan_replication(archive.path(), target.path(), &record).unwrap();
+        assert_eq!(plan.copies.len(), 1);
+        assert!(plan.only_on_target.is_empty());
+
+        assert_eq!(remove_partial_copies(target.path()).unwrap(), 1);
+        replicate(archive.path(), target.path());
+        assert_eq!(
+            fs::read_to_string(target.path().join("2024/a.jpg")).unwrap(),
+            "a"
+        );
+    }
+
+    #[test]
+    fn test_record_appends_after_torn_line() {
+        let archive = TempDir::new().unwrap();
+        let target = TempDir::new().unwrap();
+        let mut record = ReplicaRecord::load(archive.path(), target.path()).unwrap();
+        record
+            .record_copied(Path::new("2024/a.jpg"), blake3::hash(b"a"))
+            .unwrap();
+        let mut file = OpenOptions::new().append(true).open(&record.path).unwrap();
+        file.write_all(b"{\"event\":\"copied\",\"pa").unwrap();
+
+        let mut record = ReplicaRecord::load(archive.path(), target.path()).unwrap();
+        record
+            .record_copied(Path::new("2024/b.jpg"), blake3::hash(b"b"))
+            .unwrap();
+        let record = ReplicaRecord::load(archive.path(), target.path()).unwrap();
+        assert_eq!(
+            record.get(Path::new("2024/a.jpg")),
+            Some(&blake3::hash(b"a"))
+        );
+        assert_eq!(
+            record.get(Path::new("2024/b.jpg")),
+            Some(&blake3::hash(b"b"))
+        );
+    }
+}
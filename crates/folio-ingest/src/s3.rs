@@ -0,0 +1,728 @@
+use crate::fixity::{recorded_hashes, FixityLog, FixityReport, Sample};
+use crate::replicate::replicated_files;
+use anyhow::{Context, Result};
+use blake3::Hash as Blake3Hash;
+use chrono::Utc;
+use folio_core::{format_manifest, hash_file, parse_manifest, MANIFEST_FILE};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Files larger than this are sent in parts of this size
+pub const DEFAULT_PART_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Smallest part S3 accepts in a multipart upload, except for the last one
+pub const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Verification times of the store's objects, next to its manifest
+const FIXITY_OBJECT: &str = "fixity.json";
+
+/// A bucket and key prefix, written `s3://bucket/prefix`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Location {
+    pub bucket: String,
+    /// Without leading or trailing slashes; may be empty
+    pub prefix: String,
+}
+
+impl S3Location {
+    /// `None` unless `url` starts with `s3://` and names a bucket
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix("s3://")?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return None;
+        }
+        Some(S3Location {
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+        })
+    }
+
+    fn key(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.prefix, name)
+        }
+    }
+}
+
+impl std::fmt::Display for S3Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.prefix)
+    }
+}
+
+/// An S3-compatible bucket (AWS, MinIO, Backblaze, ...) holding archive files
+/// by content
+///
+/// Each file is stored once under `<prefix>/blake3/<2 hex>/<hash>`, so renames
+/// and duplicates in the archive cost nothing, and `<prefix>/manifest-blake3.txt`
+/// maps archive paths to hashes in the same format as the folder manifests.
+/// Credentials come from the usual `AWS_*` environment variables; requests are
+/// signed with Signature Version 4 and use path-style URLs.
+pub struct S3Store {
+    agent: ureq::Agent,
+    endpoint: String,
+    host: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+    location: S3Location,
+    part_size: u64,
+}
+
+/// A request to the store; `query` and `headers` are added to the signature
+struct S3Request<'a> {
+    method: &'a str,
+    key: &'a str,
+    query: Vec<(&'a str, String)>,
+    headers: Vec<(&'a str, String)>,
+    body: &'a [u8],
+}
+
+impl<'a> S3Request<'a> {
+    fn new(method: &'a str, key: &'a str) -> Self {
+        S3Request {
+            method,
+            key,
+            query: Vec::new(),
+            headers: Vec::new(),
+            body: &[],
+        }
+    }
+}
+
+impl S3Store {
+    /// Connect using `AWS_ENDPOINT_URL` (default: AWS itself), `AWS_REGION`,
+    /// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+    pub fn from_env(location: S3Location) -> Result<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let region = var("AWS_REGION")
+            .or_else(|| var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| "us-east-1".to_string());
+        let endpoint = var("AWS_ENDPOINT_URL")
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region));
+        let endpoint = endpoint.trim_end_matches('/').to_string();
+        let access_key = var("AWS_ACCESS_KEY_ID").context("AWS_ACCESS_KEY_ID is not set")?;
+        let secret_key =
+            var("AWS_SECRET_ACCESS_KEY").context("AWS_SECRET_ACCESS_KEY is not set")?;
+
+        let authority = endpoint
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .with_context(|| format!("Invalid endpoint {:?}", endpoint))?;
+        let authority = authority.split('/').next().unwrap_or(authority);
+        // The Host header leaves out default ports, and so must the signature
+        let host = if endpoint.starts_with("https://") {
+            authority.trim_end_matches(":443")
+        } else {
+            authority.trim_end_matches(":80")
+        };
+
+        Ok(S3Store {
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(10))
+                .timeout_read(Duration::from_secs(300))
+                .build(),
+            host: host.to_string(),
+            endpoint,
+            region,
+            access_key,
+            secret_key,
+            session_token: var("AWS_SESSION_TOKEN"),
+            location,
+            part_size: DEFAULT_PART_SIZE,
+        })
+    }
+
+    /// Send files larger than `part_size` as a multipart upload
+    ///
+    /// Sizes under `MIN_PART_SIZE` are raised to it, S3 would refuse the parts.
+    pub fn with_part_size(mut self, part_size: u64) -> Self {
+        self.part_size = part_size.max(MIN_PART_SIZE);
+        self
+    }
+
+    pub fn location(&self) -> &S3Location {
+        &self.location
+    }
+
+    /// Key of the object holding the content with `hash`
+    pub fn object_key(&self, hash: &Blake3Hash) -> String {
+        let hex = hash.to_hex();
+        self.location.key(&format!("blake3/{}/{}", &hex[..2], hex))
+    }
+
+    /// Size of the object with `hash`, if the store has it
+    pub fn object_size(&self, hash: &Blake3Hash) -> Result<Option<u64>> {
+        let key = self.object_key(hash);
+        let Some(response) = self.fetch(S3Request::new("HEAD", &key))? else {
+            return Ok(None);
+        };
+        let size = response
+            .header("Content-Length")
+            .and_then(|length| length.parse().ok())
+            .with_context(|| format!("No size for {}", key))?;
+        Ok(Some(size))
+    }
+
+    /// Upload `path` as the object for `hash`
+    ///
+    /// The file is hashed again as it is read and nothing is stored unless it
+    /// still matches, so a file damaged on disk is never backed up under its
+    /// original hash. Every request carries a SHA-256 checksum the server checks
+    /// before accepting it, and the stored size is confirmed afterwards.
+    pub fn upload(&self, path: &Path, hash: &Blake3Hash) -> Result<()> {
+        let size = path
+            .metadata()
+            .with_context(|| format!("Failed to read {:?}", path))?
+            .len();
+        let key = self.object_key(hash);
+        let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+
+        if size <= self.part_size {
+            let mut body = Vec::with_capacity(size as usize);
+            file.read_to_end(&mut body)
+                .with_context(|| format!("Failed to read {:?}", path))?;
+            check_hash(path, blake3::hash(&body), hash)?;
+            let mut request = S3Request::new("PUT", &key);
+            request.headers = vec![
+                ("x-amz-checksum-sha256", base64(&Sha256::digest(&body))),
+                ("x-amz-meta-blake3", hash.to_hex().to_string()),
+            ];
+            request.body = &body;
+            self.send(request)?;
+        } else {
+            self.upload_parts(&mut file, path, hash, &key)?;
+        }
+
+        match self.object_size(hash)? {
+            Some(stored) if stored == size => Ok(()),
+            Some(stored) => anyhow::bail!(
+                "{} holds {} bytes after upload, expected {}",
+                key,
+                stored,
+                size
+            ),
+            None => anyhow::bail!("{} is missing after upload", key),
+        }
+    }
+
+    fn upload_parts(
+        &self,
+        file: &mut File,
+        path: &Path,
+        hash: &Blake3Hash,
+        key: &str,
+    ) -> Result<()> {
+        let mut request = S3Request::new("POST", key);
+        request.query = vec![("uploads", String::new())];
+        request.headers = vec![
+            ("x-amz-checksum-algorithm", "SHA256".to_string()),
+            ("x-amz-meta-blake3", hash.to_hex().to_string()),
+        ];
+        let response = self.send(request)?;
+        let text = response.into_string()?;
+        let upload_id =
+            xml_value(&text, "UploadId").with_context(|| format!("No upload id for {}", key))?;
+
+        let result = self.send_parts(file, path, hash, key, &upload_id);
+        if result.is_err() {
+            let mut abort = S3Request::new("DELETE", key);
+            abort.query = vec![("uploadId", upload_id.clone())];
+            if let Err(e) = self.send(abort) {
+                tracing::warn!("Failed to abort upload of {}: {}", key, e);
+            }
+        }
+        result
+    }
+
+    fn send_parts(
+        &self,
+        file: &mut File,
+        path: &Path,
+        hash: &Blake3Hash,
+        key: &str,
+        upload_id: &str,
+    ) -> Result<()> {
+        let mut hasher = blake3::Hasher::new();
+        let mut parts = Vec::new();
+        loop {
+            let mut part = Vec::with_capacity(self.part_size as usize);
+            file.by_ref()
+                .take(self.part_size)
+                .read_to_end(&mut part)
+                .with_context(|| format!("Failed to read {:?}", path))?;
+            if part.is_empty() {
+                break;
+            }
+            hasher.update(&part);
+
+            let number = parts.len() + 1;
+            let checksum = base64(&Sha256::digest(&part));
+            let mut request = S3Request::new("PUT", key);
+            request.query = vec![
+                ("partNumber", number.to_string()),
+                ("uploadId", upload_id.to_string()),
+            ];
+            request.headers = vec![("x-amz-checksum-sha256", checksum.clone())];
+            request.body = &part;
+            let response = self.send(request)?;
+            let etag = response
+                .header("ETag")
+                .with_context(|| format!("No ETag for part {} of {}", number, key))?
+                .to_string();
+            parts.push((number, etag, checksum));
+            if (part.len() as u64) < self.part_size {
+                break;
+            }
+        }
+        // Only complete the upload if what was sent is what was hashed
+        check_hash(path, hasher.finalize(), hash)?;
+
+        let mut body = String::from("<CompleteMultipartUpload>");
+        for (number, etag, checksum) in &parts {
+            body.push_str(&format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag><ChecksumSHA256>{}</ChecksumSHA256></Part>",
+                number,
+                xml_escape(etag),
+                checksum
+            ));
+        }
+        body.push_str("</CompleteMultipartUpload>");
+        let mut request = S3Request::new("POST", key);
+        request.query = vec![("uploadId", upload_id.to_string())];
+        request.body = body.as_bytes();
+        let response = self.send(request)?;
+        // Completion can fail after the 200 status was sent
+        let text = response.into_string()?;
+        if text.contains("<Error>") {
+            anyhow::bail!(
+                "Failed to complete upload of {}: {}",
+                key,
+                xml_value(&text, "Message").unwrap_or(text)
+            );
+        }
+        Ok(())
+    }
+
+    /// BLAKE3 of the stored object for `hash`, computed as it is downloaded
+    pub fn hash_object(&self, hash: &Blake3Hash) -> Result<Option<Blake3Hash>> {
+        let key = self.object_key(hash);
+        let Some(response) = self.fetch(S3Request::new("GET", &key))? else {
+            return Ok(None);
+        };
+        let mut hasher = blake3::Hasher::new();
+        io::copy(&mut response.into_reader(), &mut hasher)
+            .with_context(|| format!("Failed to download {}", key))?;
+        Ok(Some(hasher.finalize()))
+    }
+
+    /// Archive paths the store holds, with their hashes; empty for a new store
+    pub fn read_manifest(&self) -> Result<BTreeMap<PathBuf, Blake3Hash>> {
+        let key = self.location.key(MANIFEST_FILE);
+        let Some(text) = self.get_text(&key)? else {
+            return Ok(BTreeMap::new());
+        };
+        let entries = parse_manifest(&text).with_context(|| format!("Invalid manifest {}", key))?;
+        Ok(entries
+            .into_iter()
+            .map(|(name, hash)| (PathBuf::from(name), hash))
+            .collect())
+    }
+
+    pub fn write_manifest(&self, entries: &BTreeMap<PathBuf, Blake3Hash>) -> Result<()> {
+        let entries: BTreeMap<String, Blake3Hash> = entries
+            .iter()
+            .map(|(path, hash)| (path_name(path), *hash))
+            .collect();
+        self.put_text(
+            &self.location.key(MANIFEST_FILE),
+            &format_manifest(&entries),
+        )
+    }
+
+    fn get_text(&self, key: &str) -> Result<Option<String>> {
+        match self.fetch(S3Request::new("GET", key))? {
+            Some(response) => Ok(Some(response.into_string()?)),
+            None => Ok(None),
+        }
+    }
+
+    fn put_text(&self, key: &str, text: &str) -> Result<()> {
+        let mut request = S3Request::new("PUT", key);
+        request.headers = vec![(
+            "x-amz-checksum-sha256",
+            base64(&Sha256::digest(text.as_bytes())),
+        )];
+        request.body = text.as_bytes();
+        self.send(request)?;
+        Ok(())
+    }
+
+    /// Send a request that must succeed
+    fn send(&self, request: S3Request) -> Result<ureq::Response> {
+        let (method, key) = (request.method, request.key);
+        self.fetch(request)?
+            .with_context(|| format!("{} {} failed: not found", method, key))
+    }
+
+    /// Send a request; `None` if the server answers 404
+    fn fetch(&self, request: S3Request) -> Result<Option<ureq::Response>> {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex(&Sha256::digest(request.body));
+
+        let path = format!(
+            "/{}/{}",
+            uri_encode(&self.location.bucket, false),
+            uri_encode(request.key, true)
+        );
+        let mut query: Vec<(String, String)> = request
+            .query
+            .iter()
+            .map(|(name, value)| (uri_encode(name, false), uri_encode(value, false)))
+            .collect();
+        query.sort();
+        let query = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let mut headers: Vec<(String, String)> = vec![
+            ("host".to_string(), self.host.clone()),
+            ("x-amz-content-sha256".to_string(), payload_hash.clone()),
+            ("x-amz-date".to_string(), amz_date.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        for (name, value) in &request.headers {
+            headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+        }
+        headers.sort();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            request.method, path, query, canonical_headers, signed_headers, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = signing_key(&self.secret_key, &date, &self.region, "s3");
+        let signature = hex(&hmac(&key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key, scope, signed_headers, signature
+        );
+
+        let url = if query.is_empty() {
+            format!("{}{}", self.endpoint, path)
+        } else {
+            format!("{}{}?{}", self.endpoint, path, query)
+        };
+        let mut http = self
+            .agent
+            .request(request.method, &url)
+            .set("Authorization", &authorization);
+        for (name, value) in headers.iter().filter(|(name, _)| name != "host") {
+            http = http.set(name, value);
+        }
+        let result =
+            if request.body.is_empty() && matches!(request.method, "GET" | "HEAD" | "DELETE") {
+                http.call()
+            } else {
+                http.send_bytes(request.body)
+            };
+        match result {
+            Ok(response) => Ok(Some(response)),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(request_error(e, request.method, request.key)),
+        }
+    }
+}
+
+/// The archive's files and which of them the store still needs
+#[derive(Debug, Default)]
+pub struct StorePlan {
+    /// Every archive file with its hash, as the store's manifest will list them
+    pub files: Vec<(PathBuf, Blake3Hash)>,
+    /// One file per content the store doesn't hold yet
+    pub uploads: Vec<(PathBuf, Blake3Hash)>,
+}
+
+/// Find the archive files whose content isn't in the store yet
+///
+/// Hashes recorded at ingest are reused rather than recomputed (the upload
+/// checks them again); content listed in the store's manifest is skipped
+/// without a request, and any other content the store already has costs one
+/// HEAD.
+pub fn plan_store_upload(archive: &Path, store: &S3Store) -> Result<StorePlan> {
+    let recorded = recorded_hashes(archive)?;
+    let mut known: HashSet<Blake3Hash> = store.read_manifest()?.into_values().collect();
+
+    let mut plan = StorePlan::default();
+    for path in replicated_files(archive)? {
+        let hash = match recorded.get(&path) {
+            Some(hash) => *hash,
+            None => hash_file(&archive.join(&path))?,
+        };
+        // Duplicates in the archive are looked up and uploaded once
+        if known.insert(hash) {
+            let size = archive.join(&path).metadata()?.len();
+            let stored = store.object_size(&hash)? == Some(size);
+            if !stored {
+                plan.uploads.push((path.clone(), hash));
+            }
+        }
+        plan.files.push((path, hash));
+    }
+    Ok(plan)
+}
+
+/// Check the store's objects against the hashes in its manifest
+///
+/// Works like `verify_archive`: every listed object is looked for, `sample` of
+/// them downloaded and hashed, least recently verified first. Verification
+/// times are kept in the store, next to the manifest.
+pub fn verify_store(store: &S3Store, sample: Sample) -> Result<FixityReport> {
+    let manifest = store.read_manifest()?;
+    if manifest.is_empty() {
+        anyhow::bail!("Nothing has been replicated to {}", store.location());
+    }
+    let log_key = store.location.key(FIXITY_OBJECT);
+    let mut log: FixityLog = match store.get_text(&log_key)? {
+        Some(text) => serde_json::from_str(&text)
+            .with_context(|| format!("Invalid fixity log {}", log_key))?,
+        None => FixityLog::default(),
+    };
+    let mut report = FixityReport::default();
+
+    let mut exists: HashMap<Blake3Hash, bool> = HashMap::new();
+    let mut present = Vec::new();
+    for (path, hash) in &manifest {
+        let found = match exists.get(hash) {
+            Some(found) => *found,
+            None => {
+                let found = store.object_size(hash)?.is_some();
+                exists.insert(*hash, found);
+                found
+            }
+        };
+        if found {
+            present.push((path, hash));
+        } else {
+            report.missing.push(path.clone());
+        }
+    }
+    report.recorded = present.len();
+
+    present.sort_by_key(|(path, _)| (log.last_verified(path), path.to_path_buf()));
+    let count = sample.size(present.len());
+    // Duplicates share an object, which is only downloaded once
+    let mut intact: HashMap<Blake3Hash, bool> = HashMap::new();
+    for (path, hash) in present.into_iter().take(count) {
+        report.checked += 1;
+        let ok = match intact.get(hash) {
+            Some(ok) => *ok,
+            None => {
+                let ok = match store.hash_object(hash) {
+                    Ok(actual) => actual == Some(*hash),
+                    Err(e) => {
+                        tracing::warn!("Failed to read {}: {:#}", store.object_key(hash), e);
+                        false
+                    }
+                };
+                intact.insert(*hash, ok);
+                ok
+            }
+        };
+        if ok {
+            log.record(path.clone(), Utc::now());
+        } else {
+            report.corrupted.push(path.clone());
+        }
+    }
+
+    if report.checked > 0 {
+        store.put_text(&log_key, &serde_json::to_string_pretty(&log)?)?;
+    }
+    Ok(report)
+}
+
+fn check_hash(path: &Path, actual: Blake3Hash, expected: &Blake3Hash) -> Result<()> {
+    if actual != *expected {
+        anyhow::bail!(
+            "{:?} no longer matches its recorded hash {}; not uploaded",
+            path,
+            expected.to_hex()
+        );
+    }
+    Ok(())
+}
+
+fn request_error(error: ureq::Error, method: &str, key: &str) -> anyhow::Error {
+    match error {
+        ureq::Error::Status(status, response) => {
+            let text = response.into_string().unwrap_or_default();
+            let message = xml_value(&text, "Message").unwrap_or(text);
+            anyhow::anyhow!("{} {} failed ({}): {}", method, key, status, message)
+        }
+        e => anyhow::Error::new(e).context(format!("{} {} failed", method, key)),
+    }
+}
+
+/// Archive path with `/` separators, as used in the store's manifest
+fn path_name(path: &Path) -> String {
+    path.components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let key = hmac(&key, region.as_bytes());
+    let key = hmac(&key, service.as_bytes());
+    hmac(&key, b"aws4_request")
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Percent-encode all but unreserved characters (and `/` in keys), as SigV4 expects
+fn uri_encode(text: &str, keep_slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Text of the first `<tag>` element; enough for S3's flat responses
+fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(
+        xml[start..end]
+            .replace("&quot;", "\"")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&"),
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_parsing() {
+        assert_eq!(
+            S3Location::parse("s3://backups/family/photos/"),
+            Some(S3Location {
+                bucket: "backups".to_string(),
+                prefix: "family/photos".to_string(),
+            })
+        );
+        let bare = S3Location::parse("s3://backups").unwrap();
+        assert_eq!(bare.key(MANIFEST_FILE), MANIFEST_FILE);
+        assert_eq!(S3Location::parse("s3://"), None);
+        assert_eq!(S3Location::parse("/mnt/backup"), None);
+    }
+
+    #[test]
+    fn test_signing_key_matches_aws_example() {
+        // From the AWS Signature Version 4 documentation
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn test_encodings() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(uri_encode("2024/a b+c.jpg", true), "2024/a%20b%2Bc.jpg");
+        assert_eq!(uri_encode("a/b", false), "a%2Fb");
+        assert_eq!(
+            xml_value("<R><UploadId>x&amp;y</UploadId></R>", "UploadId").as_deref(),
+            Some("x&y")
+        );
+        assert_eq!(
+            path_name(&Path::new("2024").join("11").join("a.jpg")),
+            "2024/11/a.jpg"
+        );
+    }
+}
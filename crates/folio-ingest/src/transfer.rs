@@ -0,0 +1,306 @@
+use anyhow::{Context, Result};
+use blake3::Hash as Blake3Hash;
+use chrono::Utc;
+use folio_core::hash_file;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// How ingest should place files in the archive (`--link`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkMode {
+    /// Hardlink the source into the archive
+    Hard,
+    /// Clone the source with copy-on-write (btrfs, XFS, APFS, ...)
+    Reflink,
+    /// Always make a full copy
+    Copy,
+}
+
+/// How a file actually ended up in the archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferMethod {
+    Hardlink,
+    Reflink,
+    Copy,
+}
+
+impl TransferMethod {
+    /// Past-tense label for reports, e.g. "hardlinked"
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransferMethod::Hardlink => "hardlinked",
+            TransferMethod::Reflink => "reflinked",
+            TransferMethod::Copy => "copied",
+        }
+    }
+}
+
+/// Place `source` at `dest` using `mode`, falling back to a plain copy when the
+/// source and destination are on different devices or the link isn't supported.
+/// The parent directory of `dest` must already exist; a file already at `dest`
+/// is never replaced
+pub fn transfer_file(source: &Path, dest: &Path, mode: LinkMode) -> Result<TransferMethod> {
+    let linkable = mode != LinkMode::Copy && same_device(source, dest);
+
+    if linkable {
+        let linked = match mode {
+            LinkMode::Hard => fs::hard_link(source, dest).map(|_| TransferMethod::Hardlink),
+            LinkMode::Reflink => {
+                reflink_copy::reflink(source, dest).map(|_| TransferMethod::Reflink)
+            }
+            LinkMode::Copy => unreachable!(),
+        };
+        match linked {
+            Ok(method) => return Ok(method),
+            Err(e) => tracing::debug!("Falling back to copy for {:?}: {}", source, e),
+        }
+    }
+
+    copy_new(source, dest)?;
+    Ok(TransferMethod::Copy)
+}
+
+/// Copy `source` into a file created at `dest`, failing if one is already there
+fn copy_new(source: &Path, dest: &Path) -> Result<()> {
+    let mut reader = File::open(source).with_context(|| format!("Failed to open {:?}", source))?;
+    let mut writer = match OpenOptions::new().write(true).create_new(true).open(dest) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            anyhow::bail!("{:?} already exists; it is not replaced", dest)
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to create {:?}", dest)),
+    };
+    let copied = std::io::copy(&mut reader, &mut writer)
+        .and_then(|_| writer.set_permissions(reader.metadata()?.permissions()));
+    if let Err(e) = copied {
+        // Only ever our own partial copy
+        fs::remove_file(dest).ok();
+        return Err(e).with_context(|| format!("Failed to copy {:?}", source));
+    }
+    Ok(())
+}
+
+/// Whether `source` and the directory `dest` will be created in share a device
+#[cfg(unix)]
+pub(crate) fn same_device(source: &Path, dest: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let dest_dir = dest.parent().unwrap_or(dest);
+    match (fs::metadata(source), fs::metadata(dest_dir)) {
+        (Ok(source), Ok(dest_dir)) => source.dev() == dest_dir.dev(),
+        _ => false,
+    }
+}
+
+/// Without device numbers, just attempt the link and fall back on failure
+#[cfg(not(unix))]
+pub(crate) fn same_device(_source: &Path, _dest: &Path) -> bool {
+    true
+}
+
+/// Hash a freshly written file and remove it if it doesn't match `expected`
+pub fn verify_or_remove(dest: &Path, expected: &Blake3Hash) -> Result<()> {
+    let actual = hash_file(dest).with_context(|| format!("Failed to verify {:?}", dest))?;
+    if actual != *expected {
+        fs::remove_file(dest).ok();
+        anyhow::bail!(
+            "Verification failed for {:?}: expected {}, got {}",
+            dest,
+            expected.to_hex(),
+            actual.to_hex()
+        );
+    }
+    Ok(())
+}
+
+/// Append-only log of source files removed by move mode
+///
+/// Written to `<archive>/.folio/logs/move-<timestamp>.log`, one tab-separated
+/// line per file: time, source path, BLAKE3 hash, verified archive copy.
+pub struct MoveLog {
+    path: PathBuf,
+    file: File,
+}
+
+impl MoveLog {
+    /// Start a new log in the archive
+    pub fn create(archive_root: &Path) -> Result<Self> {
+        let dir = archive_root.join(".folio").join("logs");
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+
+        let path = dir.join(format!(
+            "move-{}.log",
+            Utc::now().format("%Y%m%dT%H%M%S%.3f")
+        ));
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create move log {:?}", path))?;
+
+        Ok(MoveLog { path, file })
+    }
+
+    /// Location of the log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Delete a source file whose archive copy has been verified, and log it
+    ///
+    /// The line is on disk before the file goes, so a crash can't remove a
+    /// source without a record of where its copy is.
+    pub fn remove_source(
+        &mut self,
+        source: &Path,
+        hash: &Blake3Hash,
+        archived: &Path,
+    ) -> Result<()> {
+        writeln!(
+            self.file,
+            "{}\t{}\t{}\t{}",
+            Utc::now().to_rfc3339(),
+            source.display(),
+            hash.to_hex(),
+            archived.display()
+        )
+        .and_then(|_| self.file.sync_data())
+        .with_context(|| format!("Failed to write move log {:?}", self.path))?;
+        fs::remove_file(source).with_context(|| format!("Failed to remove source {:?}", source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_or_remove_keeps_matching_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("b.jpg");
+        fs::write(&dest, b"photo").unwrap();
+
+        verify_or_remove(&dest, &blake3::hash(b"photo")).unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), b"photo");
+    }
+
+    #[test]
+    fn test_verify_or_remove_removes_mismatched_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("b.jpg");
+        fs::write(&dest, b"changed since scan").unwrap();
+
+        let result = verify_or_remove(&dest, &blake3::hash(b"photo"));
+
+        assert!(result.is_err());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_transfer_file_hardlinks_on_same_device() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.jpg");
+        let dest = dir.path().join("b.jpg");
+        fs::write(&source, b"photo").unwrap();
+
+        let method = transfer_file(&source, &dest, LinkMode::Hard).unwrap();
+
+        assert_eq!(method, TransferMethod::Hardlink);
+        assert_eq!(fs::read(&dest).unwrap(), b"photo");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(
+                fs::metadata(&source).unwrap().ino(),
+                fs::metadata(&dest).unwrap().ino()
+            );
+        }
+    }
+
+    #[test]
+    fn test_transfer_file_reflink_falls_back_to_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.jpg");
+        let dest = dir.path().join("b.jpg");
+        fs::write(&source, b"photo").unwrap();
+
+        // Whether the clone succeeds depends on the filesystem; either way the data arrives
+        let method = transfer_file(&source, &dest, LinkMode::Reflink).unwrap();
+
+        assert!(matches!(
+            method,
+            TransferMethod::Reflink | TransferMethod::Copy
+        ));
+        assert_eq!(fs::read(&dest).unwrap(), b"photo");
+    }
+
+    #[test]
+    fn test_transfer_file_copy_mode_never_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.jpg");
+        let dest = dir.path().join("b.jpg");
+        fs::write(&source, b"photo").unwrap();
+
+        let method = transfer_file(&source, &dest, LinkMode::Copy).unwrap();
+        fs::write(&source, b"edited").unwrap();
+
+        assert_eq!(method, TransferMethod::Copy);
+        assert_eq!(fs::read(&dest).unwrap(), b"photo");
+    }
+
+    #[test]
+    fn test_transfer_file_never_replaces_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.jpg");
+        let dest = dir.path().join("b.jpg");
+        fs::write(&source, b"photo").unwrap();
+        fs::write(&dest, b"archived").unwrap();
+
+        for mode in [LinkMode::Hard, LinkMode::Reflink, LinkMode::Copy] {
+            let error = transfer_file(&source, &dest, mode).unwrap_err();
+            assert!(error.to_string().contains("already exists"));
+            assert_eq!(fs::read(&dest).unwrap(), b"archived");
+        }
+    }
+
+    #[test]
+    fn test_move_log_records_removed_sources() {
+        let archive = tempfile::tempdir().unwrap();
+        let source = archive.path().join("DSC_0001.jpg");
+        fs::write(&source, b"photo").unwrap();
+        let hash = blake3::hash(b"photo");
+
+        let mut log = MoveLog::create(archive.path()).unwrap();
+        log.remove_source(&source, &hash, Path::new("/archive/2024/11/04/a.jpg"))
+            .unwrap();
+
+        assert!(!source.exists());
+        let contents = fs::read_to_string(log.path()).unwrap();
+        assert!(contents.contains("DSC_0001.jpg"));
+        assert!(contents.contains(&hash.to_hex().to_string()));
+        assert!(contents.contains("/archive/2024/11/04/a.jpg"));
+    }
+
+    #[test]
+    fn test_move_log_records_source_before_removing_it() {
+        let archive = tempfile::tempdir().unwrap();
+        // A folder can't be removed as a file, so the removal fails
+        let source = archive.path().join("DSC_0002.jpg");
+        fs::create_dir(&source).unwrap();
+
+        let mut log = MoveLog::create(archive.path()).unwrap();
+        let hash = blake3::hash(b"photo");
+        assert!(log
+            .remove_source(&source, &hash, Path::new("/archive/a.jpg"))
+            .is_err());
+
+        assert!(fs::read_to_string(log.path())
+            .unwrap()
+            .contains("DSC_0002.jpg"));
+    }
+}
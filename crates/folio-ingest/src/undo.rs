@@ -0,0 +1,445 @@
+use crate::history::forget_imports;
+use crate::journal::Journal;
+use anyhow::{Context, Result};
+use folio_core::fs_ops::{move_file, prune_empty_parents};
+use folio_core::{hash_file, sidecar_path, update_manifests};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One archived file to take back out of the archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoAction {
+    /// Index of the file in the ingest's plan
+    pub index: usize,
+    /// File created by the ingest
+    pub archived: PathBuf,
+    /// XMP sidecar written by the ingest
+    pub sidecar: Option<PathBuf>,
+    /// Original location, when move mode removed the source: the file is moved
+    /// back there instead of being deleted
+    pub restore_to: Option<PathBuf>,
+}
+
+/// What undoing an ingest will do
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UndoPlan {
+    pub actions: Vec<UndoAction>,
+    /// Files the ingest created that are already gone from the archive
+    pub missing: Vec<PathBuf>,
+    /// Sidecars left in place: written by an ingest that didn't record their
+    /// hash, so edits made since can't be ruled out
+    pub kept: Vec<PathBuf>,
+}
+
+/// Work out which files an ingest created, refusing if any of them or the
+/// sidecars it wrote were edited since
+///
+/// Duplicates the ingest skipped were already in the archive and are left alone.
+pub fn plan_undo(journal: &Journal, archive: &Path) -> Result<UndoPlan> {
+    if journal.is_undone() {
+        anyhow::bail!("Ingest {} has already been undone", journal.id());
+    }
+
+    let mut plan = UndoPlan::default();
+    let mut edited = Vec::new();
+
+    for (index, file) in journal.plan().files.iter().enumerate() {
+        if journal.method(index).is_none() || journal.is_reverted(index) {
+            continue;
+        }
+
+        let archived = archive.join(&file.archived);
+        if !archived.exists() {
+            plan.missing.push(archived);
+            continue;
+        }
+
+        let hash =
+            hash_file(&archived).with_context(|| format!("Failed to hash {:?}", archived))?;
+        if hash != file.blake3()? {
+            edited.push(archived);
+            continue;
+        }
+
+        let sidecar = sidecar_path(&archived);
+        let sidecar = if file.writes_sidecar() && sidecar.exists() {
+            match journal.sidecar_hash(index) {
+                Some(recorded) => {
+                    let hash = hash_file(&sidecar)
+                        .with_context(|| format!("Failed to hash {:?}", sidecar))?;
+                    if hash != recorded {
+                        edited.push(sidecar);
+                        continue;
+                    }
+                    Some(sidecar)
+                }
+                None => {
+                    plan.kept.push(sidecar);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let restore_to = if journal.is_source_removed(index) {
+            check_restorable(&file.source)?;
+            Some(file.source.clone())
+        } else {
+            None
+        };
+
+        plan.actions.push(UndoAction {
+            index,
+            sidecar,
+            archived,
+            restore_to,
+        });
+    }
+
+    if !edited.is_empty() {
+        let list: Vec<String> = edited
+            .iter()
+            .map(|path| format!("  {}", path.display()))
+            .collect();
+        anyhow::bail!(
+            "Refusing to undo ingest {}: {} file(s) changed since they were archived:\n{}",
+            journal.id(),
+            edited.len(),
+            list.join("\n")
+        );
+    }
+
+    Ok(plan)
+}
+
+/// Remove (or restore) the planned files, prune emptied folders and mark the journal undone
+pub fn apply_undo(journal: &mut Journal, archive: &Path, plan: &UndoPlan) -> Result<()> {
+    // Sources go back only where they came from; a card that was unmounted
+    // since planning stops the undo before anything is removed
+    for original in plan.actions.iter().filter_map(|a| a.restore_to.as_ref()) {
+        check_restorable(original)?;
+    }
+
+    // Each file is recorded in the journal as soon as it is out, and the
+    // manifests are updated even if a later one fails, so a second undo
+    // picks up where this one stopped
+    let mut reverted = Vec::new();
+    let mut result = Ok(());
+    for action in &plan.actions {
+        result = match &action.restore_to {
+            Some(original) => move_file(&action.archived, original),
+            None => fs::remove_file(&action.archived)
+                .with_context(|| format!("Failed to remove {:?}", action.archived)),
+        };
+        if result.is_err() {
+            break;
+        }
+        reverted.push(action);
+        result = journal
+            .record_reverted(action.index)
+            .and_then(|_| remove_sidecar(action));
+        if result.is_err() {
+            break;
+        }
+    }
+
+    let finished = result.is_ok();
+    result.and(forget_reverted(journal, archive, &reverted, finished))?;
+    journal.record_undone()
+}
+
+/// Remove the sidecar of a reverted file; one already gone is fine
+fn remove_sidecar(action: &UndoAction) -> Result<()> {
+    let Some(sidecar) = &action.sidecar else {
+        return Ok(());
+    };
+    match fs::remove_file(sidecar) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result.with_context(|| format!("Failed to remove {:?}", sidecar)),
+    }
+}
+
+/// Drop the `reverted` files from the manifests and the import history, and
+/// prune the folders they leave empty; once the undo `finished`, every file
+/// the ingest created is forgotten, including ones that were already gone
+fn forget_reverted(
+    journal: &Journal,
+    archive: &Path,
+    reverted: &[&UndoAction],
+    finished: bool,
+) -> Result<()> {
+    // Manifests drop the files first, otherwise they would keep the folders alive
+    let removed: Vec<PathBuf> = reverted
+        .iter()
+        .map(|action| {
+            action
+                .archived
+                .strip_prefix(archive)
+                .unwrap_or(&action.archived)
+                .to_path_buf()
+        })
+        .collect();
+    update_manifests(archive, &[], &removed)?;
+    for action in reverted {
+        if let Some(parent) = action.archived.parent() {
+            prune_empty_parents(parent, archive)?;
+        }
+    }
+
+    // The sources are new again: the next ingest from the same card reads them
+    let files = &journal.plan().files;
+    let forgotten: HashSet<String> = if finished {
+        files
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| journal.method(*index).is_some())
+            .map(|(_, file)| file.hash.clone())
+            .collect()
+    } else {
+        reverted
+            .iter()
+            .map(|action| files[action.index].hash.clone())
+            .collect()
+    };
+    forget_imports(archive, &forgotten)
+}
+
+/// A source removed by move mode can be put back: its folder is still there
+/// and nothing else took its place
+fn check_restorable(original: &Path) -> Result<()> {
+    if original.exists() {
+        anyhow::bail!(
+            "Cannot restore {:?}: a different file now exists at its original location",
+            original
+        );
+    }
+    if !original.parent().is_some_and(Path::is_dir) {
+        anyhow::bail!(
+            "Cannot restore {:?}: its folder no longer exists (is the card mounted?); nothing was changed",
+            original
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::{IngestPlan, PlannedFile};
+    use crate::transfer::{LinkMode, TransferMethod};
+
+    /// An archive with one ingested file, its source removed by move mode if `moved`
+    fn ingested(moved: bool) -> (tempfile::TempDir, Journal) {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("archive");
+        let source = dir.path().join("card/a.jpg");
+        fs::create_dir_all(archive.join("2024/11/04")).unwrap();
+        fs::create_dir_all(source.parent().unwrap()).unwrap();
+        fs::write(archive.join("2024/11/04/a.jpg"), b"photo").unwrap();
+        fs::write(archive.join("keep.jpg"), b"older").unwrap();
+        if !moved {
+            fs::write(&source, b"photo").unwrap();
+        }
+
+        let planned = |archived: &str, duplicate: bool| PlannedFile {
+            source: source.clone(),
+            hash: blake3::hash(b"photo").to_hex().to_string(),
+            archived: PathBuf::from(archived),
+            batch: "thanksgving".to_string(),
+            batch_index: 0,
+            duplicate,
+            corrected_time: None,
+            title: None,
+            location: None,
+        };
+        let mut journal = Journal::create(
+            &archive,
+            IngestPlan {
+                source: dir.path().join("card"),
+                extra_sources: Vec::new(),
+                link: LinkMode::Copy,
+                move_sources: moved,
+                files: vec![
+                    planned("2024/11/04/a.jpg", false),
+                    planned("keep.jpg", true),
+                ],
+            },
+        )
+        .unwrap();
+        journal.record_done(0, Some(TransferMethod::Copy)).unwrap();
+        journal.record_done(1, None).unwrap();
+        if moved {
+            journal.record_source_removed(0).unwrap();
+        }
+        journal.complete().unwrap();
+        (dir, journal)
+    }
+
+    #[test]
+    fn test_undo_removes_created_files_and_prunes_folders() {
+        let (dir, mut journal) = ingested(false);
+        let archive = dir.path().join("archive");
+
+        let plan = plan_undo(&journal, &archive).unwrap();
+        assert_eq!(plan.actions.len(), 1);
+        apply_undo(&mut journal, &archive, &plan).unwrap();
+
+        assert!(!archive.join("2024").exists());
+        assert!(archive.join("keep.jpg").exists());
+        assert!(journal.is_undone());
+        assert!(plan_undo(&journal, &archive).is_err());
+    }
+
+    #[test]
+    fn test_undo_restores_moved_sources() {
+        let (dir, mut journal) = ingested(true);
+        let archive = dir.path().join("archive");
+
+        let plan = plan_undo(&journal, &archive).unwrap();
+        apply_undo(&mut journal, &archive, &plan).unwrap();
+
+        assert_eq!(fs::read(dir.path().join("card/a.jpg")).unwrap(), b"photo");
+        assert!(!archive.join("2024/11/04/a.jpg").exists());
+    }
+
+    #[test]
+    fn test_undo_refuses_edited_files() {
+        let (dir, journal) = ingested(false);
+        let archive = dir.path().join("archive");
+        fs::write(archive.join("2024/11/04/a.jpg"), b"retouched").unwrap();
+
+        let err = plan_undo(&journal, &archive).unwrap_err();
+
+        assert!(err.to_string().contains("changed since"));
+        assert!(archive.join("2024/11/04/a.jpg").exists());
+    }
+
+    #[test]
+    fn test_undo_stops_when_restore_folder_is_gone() {
+        let (dir, mut journal) = ingested(true);
+        let archive = dir.path().join("archive");
+        let plan = plan_undo(&journal, &archive).unwrap();
+
+        // The card was unmounted after planning
+        fs::remove_dir(dir.path().join("card")).unwrap();
+        let err = apply_undo(&mut journal, &archive, &plan).unwrap_err();
+
+        assert!(err.to_string().contains("folder no longer exists"));
+        assert!(archive.join("2024/11/04/a.jpg").exists());
+        assert!(!dir.path().join("card").exists());
+        assert!(!journal.is_undone());
+    }
+
+    #[test]
+    fn test_undo_refuses_edited_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("archive");
+        fs::create_dir_all(&archive).unwrap();
+        fs::write(archive.join("a.jpg"), b"photo").unwrap();
+        fs::write(archive.join("a.xmp"), b"<x:xmpmeta/>").unwrap();
+        let mut journal = Journal::create(
+            &archive,
+            IngestPlan {
+                source: dir.path().join("card"),
+                extra_sources: Vec::new(),
+                link: LinkMode::Copy,
+                move_sources: false,
+                files: vec![PlannedFile {
+                    source: dir.path().join("card/a.jpg"),
+                    hash: blake3::hash(b"photo").to_hex().to_string(),
+                    archived: PathBuf::from("a.jpg"),
+                    batch: "trip".to_string(),
+                    batch_index: 0,
+                    duplicate: false,
+                    corrected_time: None,
+                    title: Some("Trip".to_string()),
+                    location: None,
+                }],
+            },
+        )
+        .unwrap();
+        journal
+            .record_sidecar(0, blake3::hash(b"<x:xmpmeta/>"))
+            .unwrap();
+        journal.record_done(0, Some(TransferMethod::Copy)).unwrap();
+
+        let plan = plan_undo(&journal, &archive).unwrap();
+        assert_eq!(plan.actions[0].sidecar, Some(archive.join("a.xmp")));
+
+        // Keywords added in a photo editor since the ingest
+        fs::write(archive.join("a.xmp"), b"<x:xmpmeta>keywords</x:xmpmeta>").unwrap();
+        let err = plan_undo(&journal, &archive).unwrap_err();
+        assert!(format!("{:#}", err).contains("a.xmp"));
+    }
+
+    #[test]
+    fn test_failed_undo_records_the_files_it_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("archive");
+        fs::create_dir_all(&archive).unwrap();
+        let planned = |name: &str| {
+            fs::write(archive.join(name), name).unwrap();
+            PlannedFile {
+                source: dir.path().join("card").join(name),
+                hash: blake3::hash(name.as_bytes()).to_hex().to_string(),
+                archived: PathBuf::from(name),
+                batch: "trip".to_string(),
+                batch_index: 0,
+                duplicate: false,
+                corrected_time: None,
+                title: None,
+                location: None,
+            }
+        };
+        let files = vec![planned("a.jpg"), planned("b.jpg")];
+        update_manifests(
+            &archive,
+            &files
+                .iter()
+                .map(|file| (file.archived.clone(), file.blake3().unwrap()))
+                .collect::<Vec<_>>(),
+            &[],
+        )
+        .unwrap();
+        let mut journal = Journal::create(
+            &archive,
+            IngestPlan {
+                source: dir.path().join("card"),
+                extra_sources: Vec::new(),
+                link: LinkMode::Copy,
+                move_sources: false,
+                files,
+            },
+        )
+        .unwrap();
+        journal.record_done(0, Some(TransferMethod::Copy)).unwrap();
+        journal.record_done(1, Some(TransferMethod::Copy)).unwrap();
+        let plan = plan_undo(&journal, &archive).unwrap();
+
+        // b.jpg can't be removed: a folder took its place since planning
+        fs::remove_file(archive.join("b.jpg")).unwrap();
+        fs::create_dir_all(archive.join("b.jpg/inside")).unwrap();
+        assert!(apply_undo(&mut journal, &archive, &plan).is_err());
+
+        assert!(!archive.join("a.jpg").exists());
+        assert!(journal.is_reverted(0));
+        assert!(!journal.is_undone());
+        let journal = Journal::load(journal.path()).unwrap();
+        assert!(journal.is_reverted(0) && !journal.is_reverted(1));
+        let manifest = crate::fixity::recorded_hashes(&archive).unwrap();
+        assert!(!manifest.contains_key(Path::new("a.jpg")));
+        assert!(manifest.contains_key(Path::new("b.jpg")));
+
+        // The next undo only has b.jpg left to do
+        fs::remove_dir_all(archive.join("b.jpg")).unwrap();
+        fs::write(archive.join("b.jpg"), "b.jpg").unwrap();
+        let mut journal = journal;
+        let plan = plan_undo(&journal, &archive).unwrap();
+        assert_eq!(plan.actions.len(), 1);
+        assert!(plan.missing.is_empty());
+        apply_undo(&mut journal, &archive, &plan).unwrap();
+        assert!(journal.is_undone());
+    }
+}